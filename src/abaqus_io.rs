@@ -0,0 +1,344 @@
+//
+// file: abaqus_io.rs
+// author: L. Nagy
+//
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::mesh::Mesh;
+
+/// The Abaqus element types that are read, all of them are 4-node tetrahedra.
+const ABAQUS_TETRAHEDRA: [&str; 2] = ["C3D4", "C3D4H"];
+
+fn invalid(line_number: usize, message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Abaqus file line {}: {}", line_number, message))
+}
+
+///
+/// A keyword line of an Abaqus input file, e.g. `*ELEMENT, TYPE=C3D4, ELSET=grain`.
+///
+struct Keyword {
+    name: String,
+    parameters: HashMap<String, String>,
+}
+
+impl Keyword {
+    fn parse(line: &str) -> Keyword {
+        let mut items = line.trim_start_matches('*').split(',');
+        let name = items.next().unwrap_or_default().trim().to_uppercase();
+        let parameters = items
+            .map(|item| match item.split_once('=') {
+                Some((key, value)) => (key.trim().to_uppercase(), value.trim().to_string()),
+                None => (item.trim().to_uppercase(), String::new()),
+            })
+            .collect();
+        Keyword { name, parameters }
+    }
+
+    fn parameter(&self, key: &str) -> Option<&str> {
+        self.parameters.get(key).map(|value| value.as_str())
+    }
+}
+
+///
+/// The element sets of an input file in order of first appearance, each set becomes a
+/// subdomain. Members are stored with the line on which they were added.
+///
+#[derive(Default)]
+struct ElementSets {
+    names: Vec<String>,
+    members: HashMap<String, Vec<(i64, usize)>>,
+}
+
+impl ElementSets {
+    fn add(&mut self, name: &str, elements: &[(i64, usize)]) {
+        // Abaqus set names are case insensitive.
+        let key = name.to_uppercase();
+        if !self.members.contains_key(&key) {
+            self.names.push(key.clone());
+        }
+        self.members.entry(key).or_default().extend_from_slice(elements);
+    }
+}
+
+fn parse_values<T: std::str::FromStr>(line: &str, line_number: usize) -> io::Result<Vec<T>> {
+    line.split(',')
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .parse::<T>()
+                .map_err(|_| invalid(line_number, format!("invalid value '{}'", value)))
+        })
+        .collect()
+}
+
+///
+/// Reads a tetrahedral mesh from an Abaqus input (`.inp`) file, such as those exported by Abaqus
+/// or Cubit.
+///
+/// The `*NODE`, `*ELEMENT` and `*ELSET` keywords are read, all other keywords (and their data
+/// lines) are ignored. Only 4-node tetrahedra (`TYPE=C3D4` or `C3D4H`) are supported. Element
+/// sets, whether given with `ELSET=` on an `*ELEMENT` keyword or by an `*ELSET` keyword (with or
+/// without `GENERATE`, and possibly referring to other sets), become subdomains numbered from one
+/// in the order in which the sets first appear. If an element belongs to several sets the last
+/// set to be defined wins, and elements that are not in any set form one further subdomain.
+/// The input is expected to be flat (or to hold a single part), node ids and element ids are
+/// renumbered in the order in which they appear.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the Abaqus input file.
+///
+/// # Returns
+///
+/// A `Mesh` without any fields, labelled with the file stem of `filename`.
+///
+/// # Errors
+/// Returns an `InvalidData` error (with the offending line number) if the file holds an
+/// unsupported element type, a malformed data line, a duplicate id, a reference to an unknown
+/// node, element or set, or no tetrahedra at all.
+///
+pub fn read_mesh_from_abaqus(filename: &str) -> io::Result<Mesh> {
+    let contents = fs::read_to_string(filename)?;
+
+    let mut vertices: Vec<[f64; 3]> = vec![];
+    let mut node_indices: HashMap<i64, usize> = HashMap::new();
+    let mut elements: Vec<([i64; 4], usize)> = vec![];
+    let mut element_indices: HashMap<i64, usize> = HashMap::new();
+    let mut element_sets = ElementSets::default();
+
+    let mut keyword: Option<Keyword> = None;
+    // Element data may continue onto the next line when a line ends with a comma.
+    let mut pending: Vec<i64> = vec![];
+
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("**") {
+            continue;
+        }
+
+        if line.starts_with('*') {
+            if !pending.is_empty() {
+                return Err(invalid(line_number, String::from("incomplete element definition")));
+            }
+            let new_keyword = Keyword::parse(line);
+            if new_keyword.name == "ELEMENT" {
+                let element_type = new_keyword.parameter("TYPE").unwrap_or_default().to_uppercase();
+                if !ABAQUS_TETRAHEDRA.contains(&element_type.as_str()) {
+                    return Err(invalid(
+                        line_number,
+                        format!(
+                            "unsupported element type '{}', only 4-node tetrahedra ({}) are supported",
+                            element_type,
+                            ABAQUS_TETRAHEDRA.join(", ")
+                        ),
+                    ));
+                }
+                // Make sure the set exists (and is numbered) even before its first element.
+                if let Some(name) = new_keyword.parameter("ELSET") {
+                    element_sets.add(name, &[]);
+                }
+            }
+            if new_keyword.name == "ELSET" {
+                let name = new_keyword
+                    .parameter("ELSET")
+                    .ok_or_else(|| invalid(line_number, String::from("*ELSET without ELSET= name")))?;
+                element_sets.add(name, &[]);
+            }
+            keyword = Some(new_keyword);
+            continue;
+        }
+
+        let Some(keyword) = &keyword else {
+            return Err(invalid(line_number, String::from("data line before any keyword")));
+        };
+
+        match keyword.name.as_str() {
+            "NODE" => {
+                let (id, coordinates) = line.split_once(',').unwrap_or((line, ""));
+                let id: Vec<i64> = parse_values(id, line_number)?;
+                let coordinates: Vec<f64> = parse_values(coordinates, line_number)?;
+                if id.len() != 1 || coordinates.len() < 3 {
+                    return Err(invalid(line_number, String::from("expected a node id and three coordinates")));
+                }
+                let id = id[0];
+                if node_indices.insert(id, vertices.len()).is_some() {
+                    return Err(invalid(line_number, format!("duplicate node id {}", id)));
+                }
+                vertices.push([coordinates[0], coordinates[1], coordinates[2]]);
+            }
+            "ELEMENT" => {
+                pending.extend(parse_values::<i64>(line, line_number)?);
+                if line.ends_with(',') && pending.len() < 5 {
+                    continue;
+                }
+                if pending.len() != 5 {
+                    return Err(invalid(line_number, String::from("expected an element id and four nodes")));
+                }
+                let id = pending[0];
+                if element_indices.insert(id, elements.len()).is_some() {
+                    return Err(invalid(line_number, format!("duplicate element id {}", id)));
+                }
+                elements.push(([pending[1], pending[2], pending[3], pending[4]], line_number));
+                if let Some(name) = keyword.parameter("ELSET") {
+                    element_sets.add(name, &[(id, line_number)]);
+                }
+                pending.clear();
+            }
+            "ELSET" => {
+                let name = keyword.parameter("ELSET").unwrap_or_default();
+                if keyword.parameters.contains_key("GENERATE") {
+                    let values: Vec<i64> = parse_values(line, line_number)?;
+                    if values.len() < 2 {
+                        return Err(invalid(line_number, String::from("expected first, last[, step]")));
+                    }
+                    let step = values.get(2).copied().unwrap_or(1).max(1) as usize;
+                    let ids: Vec<(i64, usize)> = (values[0]..=values[1])
+                        .step_by(step)
+                        .map(|id| (id, line_number))
+                        .collect();
+                    element_sets.add(name, &ids);
+                } else {
+                    let mut ids = vec![];
+                    for item in line.split(',').map(|item| item.trim()).filter(|item| !item.is_empty()) {
+                        match item.parse::<i64>() {
+                            Ok(id) => ids.push((id, line_number)),
+                            // Sets may be built from other sets.
+                            Err(_) => {
+                                let members = element_sets
+                                    .members
+                                    .get(&item.to_uppercase())
+                                    .ok_or_else(|| invalid(line_number, format!("unknown element set '{}'", item)))?;
+                                ids.extend_from_slice(members);
+                            }
+                        }
+                    }
+                    element_sets.add(name, &ids);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if !pending.is_empty() {
+        return Err(invalid(contents.lines().count(), String::from("incomplete element definition")));
+    }
+    if elements.is_empty() {
+        return Err(invalid(contents.lines().count(), String::from("no tetrahedral elements found")));
+    }
+
+    // Later sets override earlier ones, unassigned elements get a subdomain of their own.
+    let unassigned = element_sets.names.len() + 1;
+    let mut submesh_indices = vec![unassigned; elements.len()];
+    for (set_index, name) in element_sets.names.iter().enumerate() {
+        for (id, line_number) in &element_sets.members[name] {
+            let element = element_indices
+                .get(id)
+                .ok_or_else(|| invalid(*line_number, format!("element set '{}' refers to unknown element {}", name, id)))?;
+            submesh_indices[*element] = set_index + 1;
+        }
+    }
+
+    let mut tetrahedra = Vec::with_capacity(elements.len());
+    for (nodes, line_number) in elements {
+        let mut element = [0usize; 4];
+        for (vertex, node) in element.iter_mut().zip(nodes.iter()) {
+            *vertex = *node_indices
+                .get(node)
+                .ok_or_else(|| invalid(line_number, format!("element references unknown node {}", node)))?;
+        }
+        tetrahedra.push(element);
+    }
+
+    let label = Path::new(filename)
+        .file_stem()
+        .map_or(String::from("mesh"), |stem| stem.to_string_lossy().to_string());
+
+    Ok(Mesh::new(label, vertices, tetrahedra, submesh_indices, vec![]))
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn read_inp(name: &str, contents: &str) -> io::Result<Mesh> {
+        let file_name = env::temp_dir().join(format!("tec2hdf5_test_abaqus_{}.inp", name));
+        fs::write(&file_name, contents).unwrap();
+        read_mesh_from_abaqus(file_name.to_str().unwrap())
+    }
+
+    //..........................................................................................//
+    //. read_mesh_from_abaqus()                                                                .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_read_mesh_from_abaqus() {
+        let file_name = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join("abaqus_two_tets")
+            .join("two_tets.inp");
+        let mesh = read_mesh_from_abaqus(file_name.to_str().unwrap()).unwrap();
+
+        assert_eq!(mesh.label, "two_tets");
+        assert_eq!(mesh.vertices.len(), 5);
+        assert_eq!(mesh.vertices[4], [1.0, 1.0, 1.0]);
+        assert_eq!(mesh.elements, vec![[0, 1, 2, 3], [1, 2, 3, 4]]);
+        assert_eq!(mesh.submesh_indices, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_read_mesh_from_abaqus_set_override() {
+        let mesh = read_inp(
+            "set_override",
+            "*NODE\n1, 0, 0, 0\n2, 1, 0, 0\n3, 0, 1, 0\n4, 0, 0, 1\n\
+             *ELEMENT, TYPE=C3D4, ELSET=all\n1, 1, 2, 3, 4\n2, 1, 2, 4, 3\n3, 1, 3, 2, 4\n\
+             *ELEMENT, TYPE=C3D4\n4, 2, 1, 3, 4\n\
+             *ELSET, ELSET=inner\n2\n*ELSET, ELSET=copy\ninner\n",
+        )
+        .unwrap();
+
+        // 'copy' (3) is defined after 'inner' (2), element 4 is in no set (4).
+        assert_eq!(mesh.submesh_indices, vec![1, 3, 1, 4]);
+    }
+
+    #[test]
+    fn test_read_mesh_from_abaqus_unsupported_element() {
+        let result = read_inp(
+            "unsupported_element",
+            "*NODE\n1, 0, 0, 0\n*ELEMENT, TYPE=C3D10, ELSET=grain\n1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1\n",
+        );
+        match result {
+            Err(error) => {
+                assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+                let message = error.to_string();
+                assert!(message.contains("line 3"));
+                assert!(message.contains("C3D10"));
+            }
+            Ok(_) => panic!("C3D10 elements should be rejected"),
+        }
+    }
+
+    #[test]
+    fn test_read_mesh_from_abaqus_fractional_node_id() {
+        let result = read_inp("fractional_node_id", "*NODE\n1.5, 0, 0, 0\n");
+        match result {
+            Err(error) => {
+                assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+                let message = error.to_string();
+                assert!(message.contains("line 2"));
+                assert!(message.contains("'1.5'"));
+            }
+            Ok(_) => panic!("fractional node ids should be rejected"),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use tec2hdf5::hdf5_io::{read_mesh_from_hdf5, write_mesh_to_hdf5};
+use tec2hdf5::mesh::Mesh;
+use tec2hdf5::tecplot_parser::create_mesh_from_tecplot;
+use tec2hdf5::vtk_io::{write_mesh_to_vtu, VtuFormat};
+
+use clap::{Arg, Command};
+
+struct CliArgs {
+    first_file: String,
+    second_file: String,
+    deviation_file: Option<String>,
+    tolerance: f64,
+}
+
+fn parse_args() -> CliArgs {
+    let matches = Command::new("fieldcmp")
+        .version("1.0")
+        .author("Les Nagy <lesleisnagy@gmail.com>")
+        .about("A tool to compare the fields of two tecplot or HDF5 files defined on the same mesh, zone by zone")
+        .arg(
+            Arg::new("first_file")
+                .help("The first tecplot file, or an HDF5 (.h5) file")
+                .value_name("FIRST")
+                .required(true),
+        )
+        .arg(
+            Arg::new("second_file")
+                .help("The second tecplot file, or an HDF5 (.h5) file, its fields are subtracted from those of FIRST")
+                .value_name("SECOND")
+                .required(true),
+        )
+        .arg(
+            Arg::new("deviation_file")
+                .help("An optional output file holding the difference (FIRST - SECOND) of each zone, HDF5 if it ends in '.h5' otherwise VTK ('.vtu').")
+                .long("deviation")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("tolerance")
+                .help("The largest difference between vertex coordinates of the two meshes.")
+                .long("tolerance")
+                .value_name("TOLERANCE")
+                .default_value("1e-12")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .get_matches();
+
+    CliArgs {
+        first_file: matches.get_one::<String>("first_file").unwrap().to_string(),
+        second_file: matches.get_one::<String>("second_file").unwrap().to_string(),
+        deviation_file: matches.get_one::<String>("deviation_file").cloned(),
+        tolerance: *matches.get_one::<f64>("tolerance").unwrap(),
+    }
+}
+
+fn read_mesh(filename: &str) -> Mesh {
+    if filename.to_lowercase().ends_with(".h5") {
+        read_mesh_from_hdf5(filename).unwrap()
+    } else {
+        create_mesh_from_tecplot(filename)
+    }
+}
+
+fn main() {
+
+    // Parse command line arguments.
+    let args = parse_args();
+
+    println!("first file: {}", args.first_file);
+    println!("second file: {}", args.second_file);
+
+    let first = read_mesh(&args.first_file);
+    let second = read_mesh(&args.second_file);
+
+    if !first.same_geometry(&second, args.tolerance) {
+        eprintln!("The meshes of '{}' and '{}' differ", args.first_file, args.second_file);
+        std::process::exit(1);
+    }
+    for (mesh, filename) in [(&first, &args.first_file), (&second, &args.second_file)] {
+        if let Some(field) = mesh.fields.iter().find(|field| field.vectors.len() != mesh.vertices.len()) {
+            eprintln!(
+                "Zone {} of '{}' has {} vector(s) for {} vertices",
+                field.label, filename, field.vectors.len(), mesh.vertices.len()
+            );
+            std::process::exit(1);
+        }
+    }
+    if first.fields.len() != second.fields.len() {
+        println!(
+            "Warning: {} zone(s) against {} zone(s), only the first {} are compared",
+            first.fields.len(), second.fields.len(), first.fields.len().min(second.fields.len())
+        );
+    }
+
+    let mut deviations = Vec::new();
+    for (index, (a, b)) in first.fields.iter().zip(second.fields.iter()).enumerate() {
+        let dots = a.dot(b);
+        let mean_dot = dots.iter().sum::<f64>() / dots.len().max(1) as f64;
+        println!("Zone {}: {} vs {}", index + 1, a.label, b.label);
+        println!("  RMS difference:      {:E}", a.rms_difference(&first, b));
+        println!("  Max. angle (deg):    {:.4}", a.max_angular_deviation(b));
+        println!("  Mean dot product:    {:.6}", mean_dot);
+        deviations.push(a.difference(b));
+    }
+
+    if let Some(deviation_file) = args.deviation_file {
+        let mesh = Mesh::new(
+            first.label.clone(),
+            first.vertices.clone(),
+            first.elements.clone(),
+            first.submesh_indices.clone(),
+            deviations,
+        );
+        println!("Writing {}", deviation_file);
+        if deviation_file.to_lowercase().ends_with(".h5") {
+            write_mesh_to_hdf5(&mesh, &deviation_file).unwrap();
+        } else {
+            write_mesh_to_vtu(&mesh, &deviation_file, VtuFormat::Ascii).unwrap();
+        }
+    }
+
+    println!("Done");
+
+}
@@ -0,0 +1,104 @@
+use clap::{Arg, Command};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+use tec2hdf5::forc::{read_forc_files, write_forc_distribution_to_csv};
+use tec2hdf5::hdf5_io::write_forc_distribution_to_hdf5;
+
+struct CliArgs {
+    base_directory: String,
+    forc_file_match: String,
+    output_file: String,
+    smoothing_factor: usize,
+}
+
+fn parse_args() -> CliArgs {
+    let matches = Command::new("forc")
+        .version("1.0")
+        .author("Les Nagy <lesleisnagy@gmail.com>")
+        .about("A tool to compute the FORC distribution of a set of first-order reversal curve files")
+        .arg(
+            Arg::new("base_directory")
+                .help("The base directory containing the reversal curve files")
+                .value_name("BASE_DIR")
+                .required(true),
+        )
+        .arg(
+            Arg::new("forc_file_match")
+                .help("A regular expression to match the reversal curve files")
+                .value_name("FORC_FILE_MATCH")
+                .required(true),
+        )
+        .arg(
+            Arg::new("output_file")
+                .help("The output file, HDF5 for .h5/.hdf5 otherwise CSV, with the distribution on the (Ba, Br) grid and the (Bc, Bu) coordinates of its nodes (a rotated grid, not a regular Bc/Bu grid)")
+                .value_name("OUTPUT_FILE")
+                .required(true),
+        )
+        .arg(
+            Arg::new("smoothing_factor")
+                .help("The smoothing factor SF, the local polynomial is fitted over (2 SF + 1)^2 points.")
+                .long("smoothing-factor")
+                .value_name("SF")
+                .default_value("3")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .get_matches();
+
+    CliArgs {
+        base_directory: matches.get_one::<String>("base_directory").unwrap().to_string(),
+        forc_file_match: matches.get_one::<String>("forc_file_match").unwrap().to_string(),
+        output_file: matches.get_one::<String>("output_file").unwrap().to_string(),
+        smoothing_factor: *matches.get_one::<usize>("smoothing_factor").unwrap(),
+    }
+}
+
+fn main() {
+
+    // Parse command line arguments.
+    let args = parse_args();
+
+    let re = Regex::new(&args.forc_file_match).unwrap();
+    let mut forc_files: Vec<String> = match fs::read_dir(&args.base_directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .filter(|path| path.file_name().and_then(|name| name.to_str()).is_some_and(|name| re.is_match(name)))
+            .filter_map(|path| path.to_str().map(String::from))
+            .collect(),
+        Err(e) => {
+            println!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    forc_files.sort();
+    println!("Processing {} reversal curve files", forc_files.len());
+
+    let forc_data = match read_forc_files(&forc_files) {
+        Ok(forc_data) => forc_data,
+        Err(e) => {
+            println!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let (Some(first), Some(last)) = (forc_data.curves.first(), forc_data.curves.last()) {
+        println!("Reversal fields: {} to {} T", first.br, last.br);
+    }
+    println!("Field step:      {} T", forc_data.field_step());
+
+    let distribution = forc_data.distribution(args.smoothing_factor);
+
+    println!("Writing {}", args.output_file);
+    let extension = Path::new(&args.output_file)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase());
+    match extension.as_deref() {
+        Some("h5") | Some("hdf5") => write_forc_distribution_to_hdf5(&args.output_file, &distribution).unwrap(),
+        _ => write_forc_distribution_to_csv(&args.output_file, &distribution).unwrap(),
+    }
+
+    println!("Done");
+
+}
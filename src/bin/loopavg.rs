@@ -1,14 +1,33 @@
 use clap::{Arg, Command};
 use regex::Regex;
 use std::fs;
-use std::path::{Path, PathBuf};
 
-use tec2hdf5::hysteresis_loops::read_loop_files;
+use tec2hdf5::hdf5_io::write_averaged_loop_to_hdf5;
+use tec2hdf5::hysteresis_loops::{
+    direction_coverage,
+    read_loop_files_resampled,
+    read_loop_files_with_mode,
+    write_averaged_loop_to_csv,
+    write_averaged_loop_to_json,
+    write_averaged_loop_to_loop_file,
+    write_vector_averaged_loop_to_csv,
+    LoopFileFormat,
+    LoopInterpolation,
+    LoopParseMode,
+    LoopWeighting,
+};
 
 struct CliArgs {
     base_directory: String,
     loop_file_match: String,
     output_file: String,
+    format: Option<LoopFileFormat>,
+    lenient: bool,
+    resample: Option<LoopInterpolation>,
+    field_step: Option<f64>,
+    statistics_file: Option<String>,
+    weights: String,
+    weights_file: Option<String>,
 }
 
 fn parse_args() -> CliArgs {
@@ -30,10 +49,57 @@ fn parse_args() -> CliArgs {
         )
         .arg(
             Arg::new("output_file")
-                .help("The output file")
+                .help("The output file, its format is chosen by extension (.csv, .json, .h5/.hdf5, otherwise .loop) unless --format is given")
                 .value_name("OUTPUT_FILE")
                 .required(true),
         )
+        .arg(
+            Arg::new("format")
+                .help("The output file format.")
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(["loop", "csv", "json", "hdf5"]),
+        )
+        .arg(
+            Arg::new("lenient")
+                .help("Skip (and report) malformed rows in the loop files instead of stopping.")
+                .long("lenient")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("resample")
+                .help("Resample loops with different field grids onto a common grid (over their overlapping range) before averaging.")
+                .long("resample")
+                .value_name("INTERPOLATION")
+                .value_parser(["linear", "cubic"]),
+        )
+        .arg(
+            Arg::new("field_step")
+                .help("The field step (T) of the common grid when resampling, by default the coarsest step of the loops.")
+                .long("field-step")
+                .value_name("STEP")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("statistics_file")
+                .help("An optional CSV file for the vector averaged loop, with the mean, standard deviation, standard error, minimum and maximum of the moment components and of the moment parallel and perpendicular to the field.")
+                .long("statistics")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("weights")
+                .help("How the loops are weighted: uniformly, or by the solid angle of each field axis (d and -d are the same loop) so that clustered directions do not dominate the average.")
+                .long("weights")
+                .value_name("WEIGHTING")
+                .default_value("uniform")
+                .value_parser(["uniform", "solid-angle"]),
+        )
+        .arg(
+            Arg::new("weights_file")
+                .help("A file with one weight per line for each loop file (in sorted file name order), overrides --weights.")
+                .long("weights-file")
+                .value_name("FILE"),
+        )
         .get_matches();
 
     CliArgs {
@@ -49,13 +115,27 @@ fn parse_args() -> CliArgs {
             .get_one::<String>("output_file")
             .unwrap()
             .to_string(),
+        format: matches
+            .get_one::<String>("format")
+            .and_then(|format| LoopFileFormat::from_name(format)),
+        lenient: matches.get_one::<bool>("lenient").copied().unwrap_or(false),
+        resample: matches
+            .get_one::<String>("resample")
+            .map(|interpolation| match interpolation.as_str() {
+                "cubic" => LoopInterpolation::MonotoneCubic,
+                _ => LoopInterpolation::Linear,
+            }),
+        field_step: matches.get_one::<f64>("field_step").copied(),
+        statistics_file: matches.get_one::<String>("statistics_file").cloned(),
+        weights: matches.get_one::<String>("weights").unwrap().to_string(),
+        weights_file: matches.get_one::<String>("weights_file").cloned(),
     }
 }
 
 fn main() {
     let args = parse_args();
     let mut loop_files = Vec::new();
-    match fs::read_dir(args.base_directory) {
+    match fs::read_dir(&args.base_directory) {
         Ok(entries) => {
             for entry in entries {
                 let path = entry.unwrap().path();
@@ -85,20 +165,75 @@ fn main() {
         .filter_map(|path| path.to_str().map(String::from))
         .collect();
 
-    let loop_stack = read_loop_files(&loop_files).unwrap();
-    let avg_loop = loop_stack.average();
+    let mode = if args.lenient { LoopParseMode::Lenient } else { LoopParseMode::Strict };
+    let result = match args.resample {
+        Some(interpolation) => read_loop_files_resampled(&loop_files, mode, interpolation, args.field_step)
+            .map(|(loop_stack, skipped, report)| {
+                println!(
+                    "Resampled onto {} field(s) in {} branch(es), {} loop(s) clipped",
+                    loop_stack.steps_per_hysteresis_loop, report.no_of_branches, report.clipped_loops
+                );
+                (loop_stack, skipped)
+            }),
+        None => read_loop_files_with_mode(&loop_files, mode),
+    };
+    let (loop_stack, skipped) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            println!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    for row in &skipped {
+        println!("Skipped {} line {}: {}", row.file, row.line, row.reason);
+    }
 
-    // Print header
-    println!(
-        "{:>15}, {:>15}, {:>15}, {:>15}",
-        "B (Tesla)", "<M> (Am^2)", "Ms (A/m)", "Volume (m^3)"
-    );
+    let coverage = direction_coverage(&loop_stack.field_directions());
+    println!("Field directions:      {}", coverage.no_of_directions);
+    println!("Largest gap (deg):     {:.2}", coverage.max_gap);
+    println!("Weight ratio:          {:.3}", coverage.weight_ratio);
+    println!("Anisotropy:            {:.4}", coverage.anisotropy);
 
-    // Print body
-    for (i, loop_value) in avg_loop.iter().enumerate() {
-        println!(
-            "{:>15.8E}, {:>15.8E}, {:>15.8E}, {:>15.8E}",
-            loop_value.b, loop_value.m, loop_value.ms, loop_value.vol
-        );
+    let weighting = match &args.weights_file {
+        Some(weights_file) => {
+            let weights = fs::read_to_string(weights_file)
+                .unwrap()
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| line.parse::<f64>().unwrap())
+                .collect();
+            LoopWeighting::User(weights)
+        }
+        None if args.weights == "solid-angle" => LoopWeighting::SolidAngle,
+        None => LoopWeighting::Uniform,
+    };
+    let weights = match loop_stack.loop_weights(&weighting) {
+        Ok(weights) => weights,
+        Err(e) => {
+            println!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let avg_loop = loop_stack.weighted_average(&weights);
+    let metadata = loop_stack.metadata();
+
+    let format = args
+        .format
+        .unwrap_or_else(|| LoopFileFormat::from_file_name(&args.output_file));
+    println!("Writing {} ({:?})", args.output_file, format);
+    match format {
+        LoopFileFormat::Loop => write_averaged_loop_to_loop_file(&args.output_file, &avg_loop, &metadata).unwrap(),
+        LoopFileFormat::Csv => write_averaged_loop_to_csv(&args.output_file, &avg_loop, &metadata).unwrap(),
+        LoopFileFormat::Json => write_averaged_loop_to_json(&args.output_file, &avg_loop, &metadata).unwrap(),
+        LoopFileFormat::Hdf5 => write_averaged_loop_to_hdf5(&args.output_file, &avg_loop, &metadata).unwrap(),
     }
+
+    if let Some(statistics_file) = &args.statistics_file {
+        println!("Writing {}", statistics_file);
+        write_vector_averaged_loop_to_csv(statistics_file, &loop_stack.weighted_vector_average(&weights), &metadata).unwrap();
+    }
+
+    println!("Done");
 }
@@ -0,0 +1,130 @@
+use tec2hdf5::grain_ensemble::{ensemble_average, write_ensemble_loop_to_csv, EnsembleWeighting};
+use tec2hdf5::hysteresis_loops::read_averaged_loop_file;
+
+use clap::{Arg, ArgAction, Command};
+
+struct CliArgs {
+    loop_files: Vec<String>,
+    output_file: String,
+    weighting: String,
+    median: Option<f64>,
+    sigma: Option<f64>,
+    fractions: Option<Vec<f64>>,
+}
+
+fn parse_args() -> CliArgs {
+    let matches = Command::new("loopmix")
+        .version("1.0")
+        .author("Les Nagy <lesleisnagy@gmail.com>")
+        .about("A tool to combine averaged loops (from loopavg) of different grain sizes into a synthetic assemblage loop")
+        .arg(
+            Arg::new("output_file")
+                .help("The output CSV file with the normalized (M/Ms) and absolute ensemble moments")
+                .value_name("OUTPUT")
+                .required(true),
+        )
+        .arg(
+            Arg::new("loop_files")
+                .help("The averaged loop files (.loop or .csv), one per grain size, on the same field grid")
+                .value_name("LOOP_FILES")
+                .num_args(1..)
+                .required(true),
+        )
+        .arg(
+            Arg::new("weighting")
+                .help("How the grain sizes are weighted: equal numbers of grains (so by total moment Ms V), a lognormal size distribution (--median, --sigma) or user number fractions (--fractions).")
+                .long("weighting")
+                .value_name("WEIGHTING")
+                .default_value("moment")
+                .value_parser(["moment", "lognormal", "user"]),
+        )
+        .arg(
+            Arg::new("median")
+                .help("The median equivalent sphere diameter (m) of the lognormal distribution.")
+                .long("median")
+                .value_name("DIAMETER")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("sigma")
+                .help("The standard deviation of ln(diameter) of the lognormal distribution.")
+                .long("sigma")
+                .value_name("SIGMA")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("fractions")
+                .help("Comma separated number fractions, one per loop file.")
+                .long("fractions")
+                .value_name("FRACTIONS")
+                .value_delimiter(',')
+                .action(ArgAction::Append)
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .get_matches();
+
+    CliArgs {
+        loop_files: matches.get_many::<String>("loop_files").unwrap().cloned().collect(),
+        output_file: matches.get_one::<String>("output_file").unwrap().to_string(),
+        weighting: matches.get_one::<String>("weighting").unwrap().to_string(),
+        median: matches.get_one::<f64>("median").copied(),
+        sigma: matches.get_one::<f64>("sigma").copied(),
+        fractions: matches.get_many::<f64>("fractions").map(|values| values.copied().collect()),
+    }
+}
+
+fn main() {
+
+    // Parse command line arguments.
+    let args = parse_args();
+
+    let weighting = match args.weighting.as_str() {
+        "lognormal" => match (args.median, args.sigma) {
+            (Some(median), Some(sigma)) => EnsembleWeighting::Lognormal { median, sigma },
+            _ => {
+                println!("error: lognormal weighting needs --median and --sigma");
+                std::process::exit(1);
+            }
+        },
+        "user" => match args.fractions {
+            Some(fractions) => EnsembleWeighting::User(fractions),
+            None => {
+                println!("error: user weighting needs --fractions");
+                std::process::exit(1);
+            }
+        },
+        _ => EnsembleWeighting::Moment,
+    };
+
+    let mut loops = Vec::with_capacity(args.loop_files.len());
+    for loop_file in &args.loop_files {
+        println!("Reading {}", loop_file);
+        match read_averaged_loop_file(loop_file) {
+            Ok(average_loop) => loops.push(average_loop),
+            Err(e) => {
+                println!("error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let ensemble = match ensemble_average(&loops, &weighting) {
+        Ok(ensemble) => ensemble,
+        Err(e) => {
+            println!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    for (index, loop_file) in args.loop_files.iter().enumerate() {
+        println!(
+            "{}: d = {:E} m, number fraction {:.4}, moment fraction {:.4}",
+            loop_file, ensemble.diameters[index], ensemble.number_fractions[index], ensemble.moment_fractions[index]
+        );
+    }
+
+    println!("Writing {}", args.output_file);
+    write_ensemble_loop_to_csv(&args.output_file, &ensemble, &args.loop_files).unwrap();
+
+    println!("Done");
+
+}
@@ -0,0 +1,144 @@
+use tec2hdf5::hysteresis_loops::{
+    average_backfield_curves,
+    averaged_loop_parameters,
+    loop_parameters,
+    read_backfield_file,
+    read_loop_file,
+    read_loop_files,
+    BackfieldCurve,
+    HysteresisParameters,
+};
+
+use clap::{Arg, Command};
+
+struct CliArgs {
+    loop_files: Vec<String>,
+    slope_correction: Option<f64>,
+    backfield_files: Vec<String>,
+}
+
+fn parse_args() -> CliArgs {
+    let matches = Command::new("loopstats")
+        .version("1.0")
+        .author("Les Nagy <lesleisnagy@gmail.com>")
+        .about("A tool to compute the hysteresis parameters (Ms, Mrs, Bc, Mrs/Ms, and Bcr and Bcr/Bc from backfield curves) of loop files and of their average")
+        .arg(
+            Arg::new("loop_files")
+                .help("The loop files")
+                .value_name("LOOP_FILES")
+                .num_args(1..)
+                .required(true),
+        )
+        .arg(
+            Arg::new("slope_correction")
+                .help("Remove the high-field slope, fitted above this fraction (e.g. 0.7) of the largest field, before computing Ms and Bc.")
+                .long("slope-correction")
+                .value_name("FRACTION")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("backfield_files")
+                .help("Backfield remanence curve files, either one per loop file (in the same order, their average is used for the average loop) or a single file used for every loop, to report Bcr and Bcr/Bc.")
+                .long("backfield")
+                .value_name("FILES")
+                .num_args(1..),
+        )
+        .get_matches();
+
+    CliArgs {
+        loop_files: matches.get_many::<String>("loop_files").unwrap().cloned().collect(),
+        slope_correction: matches.get_one::<f64>("slope_correction").copied(),
+        backfield_files: matches
+            .get_many::<String>("backfield_files")
+            .map(|files| files.cloned().collect())
+            .unwrap_or_default(),
+    }
+}
+
+fn optional(value: Option<f64>) -> String {
+    value.map_or(String::from("n/a"), |value| format!("{:E}", value))
+}
+
+fn print_parameters(name: &str, parameters: &HysteresisParameters, volume: f64, backfield: Option<&BackfieldCurve>) {
+    println!("{}", name);
+    println!("    Branches:         {}", parameters.no_of_branches);
+    println!("    Ms (Am^2):        {:E}", parameters.ms);
+    if volume > 0.0 {
+        println!("    Ms (A/m):         {:E}", parameters.ms / volume);
+    }
+    println!("    Mrs (Am^2):       {}", optional(parameters.mrs));
+    println!("    Bc (T):           {}", optional(parameters.bc));
+    println!("    Mrs/Ms:           {}", optional(parameters.squareness));
+    if parameters.high_field_slope != 0.0 {
+        println!("    High-field slope: {:E} Am^2/T", parameters.high_field_slope);
+    }
+    if let Some(backfield) = backfield {
+        let bcr = backfield.coercivity_of_remanence();
+        println!("    Bcr (T):          {}", optional(bcr));
+        println!("    Bcr/Bc:           {}", optional(bcr.and_then(|bcr| parameters.bcr_bc(bcr))));
+    }
+}
+
+fn main() {
+
+    // Parse command line arguments.
+    let args = parse_args();
+
+    if !args.backfield_files.is_empty()
+        && args.backfield_files.len() != 1
+        && args.backfield_files.len() != args.loop_files.len()
+    {
+        println!("error: expected one backfield file, or one per loop file");
+        std::process::exit(1);
+    }
+    let mut backfields = Vec::with_capacity(args.backfield_files.len());
+    for backfield_file in &args.backfield_files {
+        match read_backfield_file(backfield_file) {
+            Ok(backfield) => backfields.push(backfield),
+            Err(e) => {
+                println!("error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let backfield_for = |index: usize| backfields.get(index).or(backfields.first());
+
+    for (index, loop_file) in args.loop_files.iter().enumerate() {
+        let parameters = read_loop_file(loop_file)
+            .map_err(std::io::Error::from)
+            .and_then(|hysteresis_loop| {
+                let volume = hysteresis_loop.first().map_or(0.0, |measurement| measurement.vol);
+                loop_parameters(&hysteresis_loop, args.slope_correction).map(|parameters| (parameters, volume))
+            });
+        match parameters {
+            Ok((parameters, volume)) => print_parameters(loop_file, &parameters, volume, backfield_for(index)),
+            Err(e) => println!("{}\n    error: {}", loop_file, e),
+        }
+    }
+
+    if args.loop_files.len() > 1 {
+        let average_backfield = if backfields.is_empty() {
+            None
+        } else {
+            match average_backfield_curves(&backfields) {
+                Ok(backfield) => Some(backfield),
+                Err(e) => {
+                    println!("error: {}", e);
+                    None
+                }
+            }
+        };
+        let parameters = read_loop_files(&args.loop_files).and_then(|loop_stack| {
+            let average_loop = loop_stack.average();
+            let volume = average_loop.first().map_or(0.0, |value| value.vol);
+            averaged_loop_parameters(&average_loop, args.slope_correction).map(|parameters| (parameters, volume))
+        });
+        match parameters {
+            Ok((parameters, volume)) => print_parameters("Average", &parameters, volume, average_backfield.as_ref()),
+            Err(e) => println!("Average\n    error: {}", e),
+        }
+    }
+
+    println!("Done");
+
+}
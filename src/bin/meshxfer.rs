@@ -0,0 +1,96 @@
+use tec2hdf5::abaqus_io::read_mesh_from_abaqus;
+use tec2hdf5::field_transfer::transfer_fields;
+use tec2hdf5::gmsh_io::read_mesh_from_gmsh;
+use tec2hdf5::hdf5_io::{read_mesh_from_hdf5, write_mesh_to_hdf5};
+use tec2hdf5::mesh::Mesh;
+use tec2hdf5::patran_io::read_mesh_from_patran;
+use tec2hdf5::tecplot_parser::create_mesh_from_tecplot;
+
+use clap::{Arg, Command};
+
+struct CliArgs {
+    source_file: String,
+    target_file: String,
+    output_basename: String,
+}
+
+fn parse_args() -> CliArgs {
+    let matches = Command::new("meshxfer")
+        .version("1.0")
+        .author("Les Nagy <lesleisnagy@gmail.com>")
+        .about("A tool to transfer the fields of one mesh onto another (e.g. refined) mesh as a MERRILL initial guess")
+        .arg(
+            Arg::new("source_file")
+                .help("The tecplot (or HDF5 '.h5') file holding the fields to transfer")
+                .value_name("SOURCE")
+                .required(true),
+        )
+        .arg(
+            Arg::new("target_file")
+                .help("The target mesh, a tecplot, HDF5 (.h5), Gmsh (.msh), Abaqus (.inp) or PATRAN (.pat/.neu) file")
+                .value_name("TARGET")
+                .required(true),
+        )
+        .arg(
+            Arg::new("output_basename")
+                .help("The base name for the output, the file '<BASENAME>.h5' is produced.")
+                .value_name("BASENAME")
+                .required(true),
+        )
+        .get_matches();
+
+    CliArgs {
+        source_file: matches.get_one::<String>("source_file").unwrap().to_string(),
+        target_file: matches.get_one::<String>("target_file").unwrap().to_string(),
+        output_basename: matches.get_one::<String>("output_basename").unwrap().to_string(),
+    }
+}
+
+fn read_mesh(filename: &str) -> Mesh {
+    let lower = filename.to_lowercase();
+    if lower.ends_with(".h5") {
+        read_mesh_from_hdf5(filename).unwrap()
+    } else if lower.ends_with(".msh") {
+        read_mesh_from_gmsh(filename).unwrap()
+    } else if lower.ends_with(".inp") {
+        read_mesh_from_abaqus(filename).unwrap()
+    } else if lower.ends_with(".pat") || lower.ends_with(".neu") {
+        read_mesh_from_patran(filename).unwrap()
+    } else {
+        create_mesh_from_tecplot(filename)
+    }
+}
+
+fn main() {
+
+    // Parse command line arguments.
+    let args = parse_args();
+
+    println!("source file: {}", args.source_file);
+    println!("target file: {}", args.target_file);
+    println!("output basename: {}", args.output_basename);
+
+    let mut source = read_mesh(&args.source_file);
+    source.compute_spatial_index();
+    let mut target = read_mesh(&args.target_file);
+
+    println!("Source vertices: {}", source.vertices.len());
+    println!("Target vertices: {}", target.vertices.len());
+    println!("Fields:          {}", source.fields.len());
+
+    let report = transfer_fields(&source, &mut target);
+    println!(
+        "Fallback nodes:  {} of {} (max. distance to the source boundary {:E})",
+        report.fallback_nodes, report.nodes, report.max_fallback_distance
+    );
+    if report.zero_vectors > 0 {
+        println!("Zero vectors:    {} (not renormalized)", report.zero_vectors);
+    }
+
+    let file_name = format!("{}.h5", args.output_basename);
+    println!("Writing {}", file_name);
+    write_mesh_to_hdf5(&target, &file_name).unwrap();
+
+    println!("Done");
+
+}
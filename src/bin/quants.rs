@@ -1,6 +1,7 @@
 use tec2hdf5::tecplot_parser::{
     create_mesh_from_tecplot,
 };
+use tec2hdf5::domain_state::{classify_field, DomainStateThresholds};
 
 use clap::{Arg, Command};
 
@@ -44,6 +45,12 @@ struct CSVOutputRow<'a> {
     mom_x: &'a f64,
     mom_y: &'a f64,
     mom_z: &'a f64,
+    surf_charge: &'a f64,
+    vol_charge: &'a f64,
+    total_charge: &'a f64,
+    charge: &'a Option<f64>,
+    helicity: &'a f64,
+    state: &'a str,
 }
 
 fn main() {
@@ -58,24 +65,41 @@ fn main() {
     let mut mesh = create_mesh_from_tecplot(&args.tecplot_file);
     mesh.compute_volume();
     mesh.compute_net_moments();
+    mesh.compute_surface_area();
+    mesh.compute_charges();
+    mesh.compute_helicities();
 
     let mesh = mesh;
     println!("Mesh data");
     println!("No. of vertices: {}", mesh.vertices.len());
     println!("No. of elements: {}", mesh.elements.len());
     println!("Volume:          {}", mesh.volume.unwrap());
+    println!("Surface area:    {}", mesh.surface_area.unwrap());
     println!("Computing quantities");
 
     let mut csv_writer = Writer::from_path(args.output_file).unwrap();
 
-    for (index, moment) in mesh.net_moments.unwrap().iter().enumerate() {
+    let surface_area = mesh.surface_area.unwrap();
+    let charges = mesh.charges.as_ref().unwrap();
+    let helicities = mesh.helicities.as_ref().unwrap();
+    let thresholds = DomainStateThresholds::default();
+    for (index, moment) in mesh.net_moments.as_ref().unwrap().iter().enumerate() {
         let out_index = index + 1;
+        let total_charge = charges[index].total();
+        let charge = charges[index].normalised(surface_area);
+        let state = classify_field(&mesh, &mesh.fields[index], &thresholds);
         csv_writer.serialize(
             CSVOutputRow{
                 index: &out_index,
                 mom_x: &moment[0],
                 mom_y: &moment[1],
                 mom_z: &moment[2],
+                surf_charge: &charges[index].surface,
+                vol_charge: &charges[index].volume,
+                total_charge: &total_charge,
+                charge: &charge,
+                helicity: &helicities[index],
+                state: state.name(),
             }
         ).unwrap()
     }
@@ -1,11 +1,17 @@
-use tec2hdf5::tecplot_parser::{Document};
+use tec2hdf5::abaqus_io::read_mesh_from_abaqus;
+use tec2hdf5::gmsh_io::read_mesh_from_gmsh;
+use tec2hdf5::hdf5_io::write_mesh_to_hdf5;
+use tec2hdf5::patran_io::read_mesh_from_patran;
+use tec2hdf5::tecplot_parser::create_mesh_from_tecplot;
 
 use clap::{Arg, Command};
 
 struct CliArgs {
     tecplot_file: String,
     output_basename: String,
-    with_xdmf: bool
+    with_xdmf: bool,
+    normalize: bool,
+    tolerance: f64,
 }
 
 fn parse_args() -> CliArgs {
@@ -15,7 +21,7 @@ fn parse_args() -> CliArgs {
         .about("A simple tool to read tecplot files and produce MERRILL compatible HDF5 files")
         .arg(
             Arg::new("tecplot_file")
-                .help("The input tecplot file")
+                .help("The input tecplot file, or a Gmsh (.msh), Abaqus (.inp) or PATRAN (.pat/.neu) file for a mesh without fields")
                 .value_name("TECPLOT")
                 .required(true),
         )
@@ -31,12 +37,28 @@ fn parse_args() -> CliArgs {
                 .long("with-xdmf")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("normalize")
+                .help("Flag indicates if the fields ought to be renormalized to |m| = 1 before writing.")
+                .long("normalize")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("tolerance")
+                .help("The largest deviation of |m| from one before a node is reported as not normalized.")
+                .long("tolerance")
+                .value_name("TOLERANCE")
+                .default_value("1e-4")
+                .value_parser(clap::value_parser!(f64)),
+        )
         .get_matches();
 
     CliArgs {
         tecplot_file: matches.get_one::<String>("tecplot_file").unwrap().to_string(),
         output_basename: matches.get_one::<String>("output_basename").unwrap().to_string(),
         with_xdmf: matches.get_one::<bool>("with_xdmf").copied().unwrap_or(false),
+        normalize: matches.get_one::<bool>("normalize").copied().unwrap_or(false),
+        tolerance: *matches.get_one::<f64>("tolerance").unwrap(),
     }
 }
 
@@ -53,4 +75,44 @@ fn main() {
         println!("output xdmf: false");
     }
 
+    // Gmsh, Abaqus and PATRAN files provide a starting mesh (without fields) before any MERRILL
+    // run exists.
+    let input_file = args.tecplot_file.to_lowercase();
+    let mut mesh = if input_file.ends_with(".msh") {
+        read_mesh_from_gmsh(&args.tecplot_file).unwrap()
+    } else if input_file.ends_with(".inp") {
+        read_mesh_from_abaqus(&args.tecplot_file).unwrap()
+    } else if input_file.ends_with(".pat") || input_file.ends_with(".neu") {
+        read_mesh_from_patran(&args.tecplot_file).unwrap()
+    } else {
+        create_mesh_from_tecplot(&args.tecplot_file)
+    };
+
+    println!("Vertices:        {}", mesh.vertices.len());
+    println!("Elements:        {}", mesh.elements.len());
+    println!("Fields:          {}", mesh.fields.len());
+
+    // MERRILL expects |m| = 1 at every node.
+    for field in mesh.fields.iter_mut() {
+        let statistics = field.norm_statistics(args.tolerance);
+        println!(
+            "Field {}: |m| min {:E}, max {:E}, mean {:E}, {} node(s) off by more than {:E}",
+            field.label, statistics.min, statistics.max, statistics.mean, statistics.off_count, args.tolerance
+        );
+        if args.normalize && statistics.off_count > 0 {
+            let zero_count = field.normalize();
+            if zero_count > 0 {
+                println!("  Renormalized, {} zero vector(s) left unchanged", zero_count);
+            } else {
+                println!("  Renormalized");
+            }
+        }
+    }
+
+    let file_name = format!("{}.h5", args.output_basename);
+    println!("Writing {}", file_name);
+    write_mesh_to_hdf5(&mesh, &file_name).unwrap();
+
+    println!("Done");
+
 }
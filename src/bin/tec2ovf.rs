@@ -0,0 +1,194 @@
+use tec2hdf5::ovf_io::{write_grid_field_to_ovf, OvfFormat};
+use tec2hdf5::regular_grid::RegularGrid;
+use tec2hdf5::tecplot_parser::create_mesh_from_tecplot;
+
+use clap::{Arg, Command};
+
+struct CliArgs {
+    tecplot_file: String,
+    output_basename: String,
+    cell_size: Option<f64>,
+    cells: Option<[usize; 3]>,
+    min: Option<[f64; 3]>,
+    max: Option<[f64; 3]>,
+    scale: f64,
+    format: OvfFormat,
+}
+
+fn positive_f64(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(value) if value > 0.0 && value.is_finite() => Ok(value),
+        Ok(_) => Err(String::from("must be a positive number")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn triple<T: std::str::FromStr>(value: &str) -> Result<[T; 3], String>
+where
+    T::Err: std::fmt::Display,
+{
+    let values: Vec<T> = value
+        .split(',')
+        .map(|value| value.trim().parse::<T>().map_err(|e| e.to_string()))
+        .collect::<Result<_, _>>()?;
+    match <[T; 3]>::try_from(values) {
+        Ok(values) => Ok(values),
+        Err(values) => Err(format!("expected three comma separated values, found {}", values.len())),
+    }
+}
+
+fn cell_counts(value: &str) -> Result<[usize; 3], String> {
+    let cells: [usize; 3] = triple(value)?;
+    if cells.contains(&0) {
+        return Err(String::from("each cell count must be at least one"));
+    }
+    Ok(cells)
+}
+
+fn parse_args() -> CliArgs {
+    let matches = Command::new("tec2ovf")
+        .version("1.0")
+        .author("Les Nagy <lesleisnagy@gmail.com>")
+        .about("A tool to resample tecplot fields onto a regular grid and produce OVF 2.0 files")
+        .arg(
+            Arg::new("tecplot_file")
+                .help("The input tecplot file")
+                .value_name("TECPLOT")
+                .required(true),
+        )
+        .arg(
+            Arg::new("output_basename")
+                .help("The base name for the output, one file '<BASENAME>_<ZONE>.ovf' is produced per zone.")
+                .value_name("BASENAME")
+                .required(true),
+        )
+        .arg(
+            Arg::new("cell_size")
+                .help("The grid cell size in mesh units (default: the cube root of the mean element volume).")
+                .long("cell-size")
+                .value_name("SIZE")
+                .conflicts_with("cells")
+                .allow_negative_numbers(true)
+                .value_parser(positive_f64),
+        )
+        .arg(
+            Arg::new("cells")
+                .help("The number of grid cells along x, y and z, e.g. 64,64,32, instead of a cell size.")
+                .long("cells")
+                .value_name("NX,NY,NZ")
+                .value_parser(cell_counts),
+        )
+        .arg(
+            Arg::new("min")
+                .help("The lower corner of the grid in mesh units, e.g. -0.1,-0.1,-0.1 (default: that of the mesh bounding box).")
+                .long("min")
+                .value_name("X,Y,Z")
+                .allow_hyphen_values(true)
+                .value_parser(triple::<f64>),
+        )
+        .arg(
+            Arg::new("max")
+                .help("The upper corner of the grid in mesh units (default: that of the mesh bounding box).")
+                .long("max")
+                .value_name("X,Y,Z")
+                .allow_hyphen_values(true)
+                .value_parser(triple::<f64>),
+        )
+        .arg(
+            Arg::new("scale")
+                .help("The factor converting mesh units to metres (MERRILL meshes are in microns).")
+                .long("scale")
+                .value_name("FACTOR")
+                .default_value("1e-6")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("format")
+                .help("The OVF data format.")
+                .long("format")
+                .value_name("FORMAT")
+                .default_value("binary8")
+                .value_parser(["text", "binary8"]),
+        )
+        .get_matches();
+
+    CliArgs {
+        tecplot_file: matches.get_one::<String>("tecplot_file").unwrap().to_string(),
+        output_basename: matches.get_one::<String>("output_basename").unwrap().to_string(),
+        cell_size: matches.get_one::<f64>("cell_size").copied(),
+        cells: matches.get_one::<[usize; 3]>("cells").copied(),
+        min: matches.get_one::<[f64; 3]>("min").copied(),
+        max: matches.get_one::<[f64; 3]>("max").copied(),
+        scale: *matches.get_one::<f64>("scale").unwrap(),
+        format: match matches.get_one::<String>("format").unwrap().as_str() {
+            "text" => OvfFormat::Text,
+            _ => OvfFormat::Binary8,
+        },
+    }
+}
+
+fn main() {
+
+    // Parse command line arguments.
+    let args = parse_args();
+
+    println!("tecplot file: {}", args.tecplot_file);
+    println!("output basename: {}", args.output_basename);
+
+    // We read the mesh along with the zones.
+    let mut mesh = create_mesh_from_tecplot(&args.tecplot_file);
+    mesh.compute_volume();
+    mesh.compute_spatial_index();
+
+    // Work out the bounding box of the mesh, unless the grid bounds are given.
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+    for vertex in &mesh.vertices {
+        for i in 0..3 {
+            min[i] = min[i].min(vertex[i]);
+            max[i] = max[i].max(vertex[i]);
+        }
+    }
+    let min = args.min.unwrap_or(min);
+    let max = args.max.unwrap_or(max);
+    if (0..3).any(|i| min[i] >= max[i]) {
+        println!("error: the grid bounds {:?} to {:?} are empty", min, max);
+        std::process::exit(1);
+    }
+
+    let grid = match args.cells {
+        Some(cells) => RegularGrid::new(min, max, cells),
+        None => {
+            let cell_size = args.cell_size.unwrap_or_else(|| {
+                (mesh.volume.unwrap().abs() / mesh.elements.len() as f64).cbrt()
+            });
+            RegularGrid::with_cell_size(min, max, cell_size)
+        }
+    };
+
+    println!("Grid cells:      {} x {} x {}", grid.dims[0], grid.dims[1], grid.dims[2]);
+    println!("Cell size:       {} x {} x {}", grid.cell_size[0], grid.cell_size[1], grid.cell_size[2]);
+
+    // The OVF files are written in metres.
+    let mut scaled_grid = grid.clone();
+    for i in 0..3 {
+        scaled_grid.min[i] *= args.scale;
+        scaled_grid.cell_size[i] *= args.scale;
+    }
+
+    for (index, field) in mesh.fields.iter().enumerate() {
+        let mut grid_field = field.resample(&mesh, &grid);
+        grid_field.grid = scaled_grid.clone();
+
+        let file_name = format!("{}_{:04}.ovf", args.output_basename, index + 1);
+        let inside = grid_field.mask.iter().filter(|inside| **inside).count();
+        println!(
+            "Writing {} ({} of {} cells inside the mesh)",
+            file_name, inside, grid_field.mask.len()
+        );
+        write_grid_field_to_ovf(&grid_field, &file_name, "m", args.format).unwrap();
+    }
+
+    println!("Done");
+
+}
@@ -0,0 +1,62 @@
+use tec2hdf5::abaqus_io::read_mesh_from_abaqus;
+use tec2hdf5::gmsh_io::read_mesh_from_gmsh;
+use tec2hdf5::patran_io::write_mesh_to_patran;
+use tec2hdf5::tecplot_parser::create_mesh_from_tecplot;
+
+use clap::{Arg, Command};
+
+struct CliArgs {
+    input_file: String,
+    output_file: String,
+}
+
+fn parse_args() -> CliArgs {
+    let matches = Command::new("tec2pat")
+        .version("1.0")
+        .author("Les Nagy <lesleisnagy@gmail.com>")
+        .about("A tool to write the geometry of a tecplot, Gmsh or Abaqus mesh as a PATRAN neutral file for MERRILL")
+        .arg(
+            Arg::new("input_file")
+                .help("The input tecplot file, or a Gmsh (.msh) or Abaqus (.inp) file")
+                .value_name("INPUT")
+                .required(true),
+        )
+        .arg(
+            Arg::new("output_file")
+                .help("The output PATRAN neutral file (e.g. '<NAME>.pat').")
+                .value_name("OUTPUT")
+                .required(true),
+        )
+        .get_matches();
+
+    CliArgs {
+        input_file: matches.get_one::<String>("input_file").unwrap().to_string(),
+        output_file: matches.get_one::<String>("output_file").unwrap().to_string(),
+    }
+}
+
+fn main() {
+
+    // Parse command line arguments.
+    let args = parse_args();
+
+    println!("input file: {}", args.input_file);
+    println!("output file: {}", args.output_file);
+
+    let input_file = args.input_file.to_lowercase();
+    let mesh = if input_file.ends_with(".msh") {
+        read_mesh_from_gmsh(&args.input_file).unwrap()
+    } else if input_file.ends_with(".inp") {
+        read_mesh_from_abaqus(&args.input_file).unwrap()
+    } else {
+        create_mesh_from_tecplot(&args.input_file)
+    };
+
+    println!("Vertices:        {}", mesh.vertices.len());
+    println!("Elements:        {}", mesh.elements.len());
+
+    write_mesh_to_patran(&mesh, &args.output_file).unwrap();
+
+    println!("Done");
+
+}
@@ -0,0 +1,83 @@
+use tec2hdf5::surface_io::{write_surface_to_obj, write_surface_to_ply, write_surface_to_stl};
+use tec2hdf5::tecplot_parser::create_mesh_from_tecplot;
+
+use clap::{Arg, Command};
+
+struct CliArgs {
+    tecplot_file: String,
+    output_basename: String,
+    format: String,
+}
+
+fn parse_args() -> CliArgs {
+    let matches = Command::new("tec2surf")
+        .version("1.0")
+        .author("Les Nagy <lesleisnagy@gmail.com>")
+        .about("A tool to extract the boundary surface of a tecplot mesh as STL, OBJ or PLY")
+        .arg(
+            Arg::new("tecplot_file")
+                .help("The input tecplot file")
+                .value_name("TECPLOT")
+                .required(true),
+        )
+        .arg(
+            Arg::new("output_basename")
+                .help("The base name for the output, '<BASENAME>.stl' for STL, otherwise one '<BASENAME>_<ZONE>.<FORMAT>' per zone coloured by the magnetization.")
+                .value_name("BASENAME")
+                .required(true),
+        )
+        .arg(
+            Arg::new("format")
+                .help("The surface file format.")
+                .long("format")
+                .value_name("FORMAT")
+                .default_value("ply")
+                .value_parser(["stl", "obj", "ply"]),
+        )
+        .get_matches();
+
+    CliArgs {
+        tecplot_file: matches.get_one::<String>("tecplot_file").unwrap().to_string(),
+        output_basename: matches.get_one::<String>("output_basename").unwrap().to_string(),
+        format: matches.get_one::<String>("format").unwrap().to_string(),
+    }
+}
+
+fn main() {
+
+    // Parse command line arguments.
+    let args = parse_args();
+
+    println!("tecplot file: {}", args.tecplot_file);
+    println!("output basename: {}", args.output_basename);
+
+    let mut mesh = create_mesh_from_tecplot(&args.tecplot_file);
+    mesh.compute_surface_area();
+
+    println!("Boundary faces:  {}", mesh.boundary_faces().len());
+    println!("Surface area:    {:E}", mesh.surface_area.unwrap());
+
+    // STL has no vertex attributes, so the geometry is written once.
+    if args.format == "stl" || mesh.fields.is_empty() {
+        let file_name = format!("{}.{}", args.output_basename, args.format);
+        println!("Writing {}", file_name);
+        match args.format.as_str() {
+            "stl" => write_surface_to_stl(&mesh, &file_name).unwrap(),
+            "obj" => write_surface_to_obj(&mesh, None, &file_name).unwrap(),
+            _ => write_surface_to_ply(&mesh, None, &file_name).unwrap(),
+        }
+    } else {
+        for (index, field) in mesh.fields.iter().enumerate() {
+            let file_name = format!("{}_{:04}.{}", args.output_basename, index + 1, args.format);
+            println!("Writing {}", file_name);
+            if args.format == "obj" {
+                write_surface_to_obj(&mesh, Some(field), &file_name).unwrap();
+            } else {
+                write_surface_to_ply(&mesh, Some(field), &file_name).unwrap();
+            }
+        }
+    }
+
+    println!("Done");
+
+}
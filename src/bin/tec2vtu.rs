@@ -0,0 +1,71 @@
+use tec2hdf5::tecplot_parser::create_mesh_from_tecplot;
+use tec2hdf5::vtk_io::{write_mesh_to_pvd, write_mesh_to_vtu, VtuFormat};
+
+use clap::{Arg, Command};
+
+struct CliArgs {
+    tecplot_file: String,
+    output_basename: String,
+    format: VtuFormat,
+}
+
+fn parse_args() -> CliArgs {
+    let matches = Command::new("tec2vtu")
+        .version("1.0")
+        .author("Les Nagy <lesleisnagy@gmail.com>")
+        .about("A tool to read tecplot files and produce VTK unstructured grid (.vtu) files")
+        .arg(
+            Arg::new("tecplot_file")
+                .help("The input tecplot file")
+                .value_name("TECPLOT")
+                .required(true),
+        )
+        .arg(
+            Arg::new("output_basename")
+                .help("The base name for the output, '<BASENAME>.vtu' is produced for a single zone, otherwise '<BASENAME>.pvd' with one '<BASENAME>_<ZONE>.vtu' per zone.")
+                .value_name("BASENAME")
+                .required(true),
+        )
+        .arg(
+            Arg::new("format")
+                .help("The encoding of the VTK data arrays.")
+                .long("format")
+                .value_name("FORMAT")
+                .default_value("binary")
+                .value_parser(["ascii", "binary", "zlib"]),
+        )
+        .get_matches();
+
+    CliArgs {
+        tecplot_file: matches.get_one::<String>("tecplot_file").unwrap().to_string(),
+        output_basename: matches.get_one::<String>("output_basename").unwrap().to_string(),
+        format: match matches.get_one::<String>("format").unwrap().as_str() {
+            "ascii" => VtuFormat::Ascii,
+            "zlib" => VtuFormat::CompressedBinary,
+            _ => VtuFormat::Binary,
+        },
+    }
+}
+
+fn main() {
+
+    // Parse command line arguments.
+    let args = parse_args();
+
+    println!("tecplot file: {}", args.tecplot_file);
+    println!("output basename: {}", args.output_basename);
+
+    // We read the mesh along with the zones.
+    let mesh = create_mesh_from_tecplot(&args.tecplot_file);
+
+    if mesh.fields.len() > 1 {
+        println!("Writing {}.pvd ({} zones)", args.output_basename, mesh.fields.len());
+        write_mesh_to_pvd(&mesh, &args.output_basename, args.format).unwrap();
+    } else {
+        println!("Writing {}.vtu", args.output_basename);
+        write_mesh_to_vtu(&mesh, &format!("{}.vtu", args.output_basename), args.format).unwrap();
+    }
+
+    println!("Done");
+
+}
@@ -0,0 +1,83 @@
+use tec2hdf5::tecplot_parser::create_mesh_from_tecplot;
+use tec2hdf5::vortex_core::extract_vortex_core;
+use tec2hdf5::vtk_io::{write_polyline_to_vtp, VtuFormat};
+
+use clap::{Arg, Command};
+
+struct CliArgs {
+    tecplot_file: String,
+    output_basename: String,
+    max_in_plane: f64,
+}
+
+fn parse_args() -> CliArgs {
+    let matches = Command::new("vortexcore")
+        .version("1.0")
+        .author("Les Nagy <lesleisnagy@gmail.com>")
+        .about("A tool to locate the vortex core of each zone in a tecplot file and write it as a VTK polyline")
+        .arg(
+            Arg::new("tecplot_file")
+                .help("The input tecplot file")
+                .value_name("TECPLOT")
+                .required(true),
+        )
+        .arg(
+            Arg::new("output_basename")
+                .help("The base name for the output, one '<BASENAME>_<ZONE>.vtp' file per zone with a core.")
+                .value_name("BASENAME")
+                .required(true),
+        )
+        .arg(
+            Arg::new("max_in_plane")
+                .help("The largest in-plane magnetization |m_perp|/|m| of a core element.")
+                .long("max-in-plane")
+                .value_name("RATIO")
+                .default_value("0.5")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .get_matches();
+
+    CliArgs {
+        tecplot_file: matches.get_one::<String>("tecplot_file").unwrap().to_string(),
+        output_basename: matches.get_one::<String>("output_basename").unwrap().to_string(),
+        max_in_plane: *matches.get_one::<f64>("max_in_plane").unwrap(),
+    }
+}
+
+fn main() {
+
+    // Parse command line arguments.
+    let args = parse_args();
+
+    println!("tecplot file: {}", args.tecplot_file);
+    println!("output basename: {}", args.output_basename);
+
+    let mut mesh = create_mesh_from_tecplot(&args.tecplot_file);
+    mesh.compute_helicities();
+
+    let principal = mesh.principal_axes();
+    for (moment, axis) in principal.moments.iter().zip(principal.axes.iter()) {
+        println!("Principal axis:  ({:.4}, {:.4}, {:.4}), moment {:E}", axis[0], axis[1], axis[2], moment);
+    }
+
+    let helicities = mesh.helicities.as_ref().unwrap();
+    for (index, field) in mesh.fields.iter().enumerate() {
+        println!("Zone {}: {}", index + 1, field.label);
+        println!("  Helicity:      {:E}", helicities[index]);
+        match extract_vortex_core(&mesh, field, args.max_in_plane) {
+            Some(core) => {
+                println!("  Core position: ({:E}, {:E}, {:E})", core.position[0], core.position[1], core.position[2]);
+                println!("  Core length:   {:E}", core.length);
+                println!("  Core tilt:     {:.2}, {:.2}, {:.2} degrees", core.tilt[0], core.tilt[1], core.tilt[2]);
+
+                let file_name = format!("{}_{:04}.vtp", args.output_basename, index + 1);
+                println!("  Writing {}", file_name);
+                write_polyline_to_vtp(&core.points, &file_name, VtuFormat::Ascii).unwrap();
+            }
+            None => println!("  No vortex core"),
+        }
+    }
+
+    println!("Done");
+
+}
@@ -0,0 +1,348 @@
+//
+// file: domain_state.rs
+// author: L. Nagy
+//
+
+use std::fmt;
+
+use crate::geometry::{tet_lin_vec_curl, tet_lin_vec_integral, tet_volume};
+use crate::linalg::{dot, norm};
+use crate::mesh::{Field, Mesh};
+
+///
+/// The sense in which the magnetization of a vortex circulates about its axis.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chirality {
+    /// Counter-clockwise when looking down the axis towards the origin (the curl is parallel to
+    /// the axis).
+    RightHanded,
+
+    /// Clockwise when looking down the axis towards the origin (the curl is anti-parallel to the
+    /// axis).
+    LeftHanded,
+}
+
+///
+/// The magnetic domain state of a field.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum DomainState {
+    /// An (almost) uniformly magnetized state.
+    SingleDomain,
+
+    /// A mostly uniform state whose magnetization splays out towards the surface.
+    Flower,
+
+    /// A vortex state.
+    ///
+    /// * `axis` - The unit vector along the vortex core, pointing along the core magnetization.
+    /// * `chirality` - The sense of circulation about `axis`.
+    /// * `core` - The point at which the magnetization is most closely aligned with the axis.
+    Vortex {
+        axis: [f64; 3],
+        chirality: Chirality,
+        core: [f64; 3],
+    },
+
+    /// Any other (non-uniform) state.
+    MultiDomain,
+}
+
+impl DomainState {
+    ///
+    /// A short name for the state, as used in the `quants` output.
+    ///
+    pub fn name(&self) -> &'static str {
+        match self {
+            DomainState::SingleDomain => "SD",
+            DomainState::Flower => "flower",
+            DomainState::Vortex { .. } => "vortex",
+            DomainState::MultiDomain => "MD",
+        }
+    }
+}
+
+impl fmt::Display for DomainState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+///
+/// The metrics, computed from a field on a mesh, that are used to classify its domain state.
+///
+/// # Fields
+///
+/// * `net_moment` - The normalised net moment `|int m dV| / int |m| dV`, one for a uniform state.
+/// * `helicity` - The normalised helicity `int m . (curl m) dV / int |m| |curl m| dV`, in
+///   [-1, 1].
+/// * `vorticity` - The mean magnitude of the curl times the particle size `V^(1/3)`, zero for a
+///   uniform state.
+/// * `curl_coherence` - How well the curl is aligned throughout the particle,
+///   `|int curl m dV| / int |curl m| dV`, close to one for a single vortex.
+/// * `axis` - The unit vector along the mean curl, oriented along the magnetization at `core`.
+/// * `core` - The vertex at which the magnetization is most closely aligned with `axis`.
+/// * `core_alignment` - The relative size of the magnetization perpendicular to `axis` at `core`,
+///   `|m_perp| / |m|`, zero at the centre of a vortex core.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainStateMetrics {
+    pub net_moment: f64,
+    pub helicity: f64,
+    pub vorticity: f64,
+    pub curl_coherence: f64,
+    pub axis: [f64; 3],
+    pub core: [f64; 3],
+    pub core_alignment: f64,
+}
+
+///
+/// The thresholds used to classify domain states.
+///
+/// # Fields
+///
+/// * `single_domain` - The smallest normalised net moment of a single domain state.
+/// * `flower` - The smallest normalised net moment of a flower state.
+/// * `vortex_curl_coherence` - The smallest curl coherence of a vortex state.
+/// * `vortex_core_alignment` - The largest core alignment (`|m_perp| / |m|`) of a vortex state.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainStateThresholds {
+    pub single_domain: f64,
+    pub flower: f64,
+    pub vortex_curl_coherence: f64,
+    pub vortex_core_alignment: f64,
+}
+
+impl Default for DomainStateThresholds {
+    fn default() -> Self {
+        DomainStateThresholds {
+            single_domain: 0.98,
+            flower: 0.6,
+            vortex_curl_coherence: 0.5,
+            vortex_core_alignment: 0.5,
+        }
+    }
+}
+
+///
+/// Computes the domain state metrics of a field on a mesh.
+///
+/// The curl is that of the linear interpolation of the field over each element (and is therefore
+/// constant per element), degenerate elements are skipped.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh on which the field is defined.
+/// * `field` - The (magnetization) field.
+///
+/// # Returns
+///
+/// The `DomainStateMetrics` of the field.
+///
+pub fn domain_state_metrics(mesh: &Mesh, field: &Field) -> DomainStateMetrics {
+    let mut moment = [0.0; 3];
+    let mut magnitude = 0.0;
+    let mut volume = 0.0;
+    let mut curl_integral = [0.0; 3];
+    let mut curl_magnitude = 0.0;
+    let mut helicity = 0.0;
+    let mut helicity_magnitude = 0.0;
+
+    for element in &mesh.elements {
+        let [v0, v1, v2, v3] = element.map(|vertex| mesh.vertices[vertex]);
+        let [f0, f1, f2, f3] = element.map(|vertex| field.vectors[vertex]);
+        let element_volume = tet_volume(v0, v1, v2, v3).abs();
+        let element_moment = tet_lin_vec_integral(v0, v1, v2, v3, f0, f1, f2, f3);
+        let mean_magnitude = 0.25 * (norm(f0) + norm(f1) + norm(f2) + norm(f3));
+
+        for i in 0..3 {
+            moment[i] += element_moment[i];
+        }
+        magnitude += element_volume * mean_magnitude;
+        volume += element_volume;
+
+        if let Some(curl) = tet_lin_vec_curl(v0, v1, v2, v3, f0, f1, f2, f3) {
+            for i in 0..3 {
+                curl_integral[i] += element_volume * curl[i];
+            }
+            curl_magnitude += element_volume * norm(curl);
+            helicity += dot(element_moment, curl);
+            helicity_magnitude += element_volume * mean_magnitude * norm(curl);
+        }
+    }
+
+    let ratio = |numerator: f64, denominator: f64| {
+        if denominator > 0.0 { numerator / denominator } else { 0.0 }
+    };
+
+    // The axis follows the mean curl, or the net moment if there is no curl at all.
+    let mut axis = if norm(curl_integral) > 0.0 { curl_integral } else { moment };
+    let length = norm(axis);
+    axis = if length > 0.0 { axis.map(|a| a / length) } else { [0.0, 0.0, 1.0] };
+
+    // The core is where the magnetization is most closely aligned with the axis.
+    let mut core = [0.0; 3];
+    let mut core_alignment = f64::INFINITY;
+    let mut polarity = 1.0;
+    for (vertex, m) in mesh.vertices.iter().zip(field.vectors.iter()) {
+        let m_norm = norm(*m);
+        if m_norm == 0.0 {
+            continue;
+        }
+        let m_parallel = dot(*m, axis);
+        let m_perp = [
+            m[0] - m_parallel * axis[0],
+            m[1] - m_parallel * axis[1],
+            m[2] - m_parallel * axis[2],
+        ];
+        let alignment = norm(m_perp) / m_norm;
+        if alignment < core_alignment {
+            core_alignment = alignment;
+            core = *vertex;
+            polarity = if m_parallel < 0.0 { -1.0 } else { 1.0 };
+        }
+    }
+    if core_alignment.is_infinite() {
+        core_alignment = 1.0;
+    }
+
+    DomainStateMetrics {
+        net_moment: ratio(norm(moment), magnitude),
+        helicity: ratio(helicity, helicity_magnitude),
+        vorticity: ratio(curl_magnitude, volume) * volume.cbrt(),
+        curl_coherence: ratio(norm(curl_integral), curl_magnitude),
+        axis: axis.map(|a| polarity * a),
+        core,
+        core_alignment,
+    }
+}
+
+///
+/// Classifies a domain state from its metrics.
+///
+/// A state is single domain if its normalised net moment reaches `single_domain`. Otherwise it
+/// is a vortex if its curl is coherent (at least `vortex_curl_coherence`) and there is a core at
+/// which the magnetization lines up with the curl (`core_alignment` at most
+/// `vortex_core_alignment`); the chirality is the sense of the curl about the core
+/// magnetization. Remaining states are flower states if the net moment reaches `flower`, and
+/// multi-domain states otherwise.
+///
+/// # Arguments
+///
+/// * `metrics` - The metrics of the field to classify.
+/// * `thresholds` - The classification thresholds.
+///
+/// # Returns
+///
+/// The `DomainState` of the field.
+///
+pub fn classify_domain_state(metrics: &DomainStateMetrics, thresholds: &DomainStateThresholds) -> DomainState {
+    if metrics.net_moment >= thresholds.single_domain {
+        return DomainState::SingleDomain;
+    }
+
+    if metrics.vorticity > 0.0
+        && metrics.curl_coherence >= thresholds.vortex_curl_coherence
+        && metrics.core_alignment <= thresholds.vortex_core_alignment
+    {
+        // The axis is oriented along the core magnetization, so a positive helicity means that
+        // the curl points along the axis.
+        let chirality = if metrics.helicity >= 0.0 { Chirality::RightHanded } else { Chirality::LeftHanded };
+        return DomainState::Vortex {
+            axis: metrics.axis,
+            chirality,
+            core: metrics.core,
+        };
+    }
+
+    if metrics.net_moment >= thresholds.flower {
+        DomainState::Flower
+    } else {
+        DomainState::MultiDomain
+    }
+}
+
+///
+/// Computes the metrics of a field and classifies its domain state.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh on which the field is defined.
+/// * `field` - The (magnetization) field.
+/// * `thresholds` - The classification thresholds.
+///
+/// # Returns
+///
+/// The `DomainState` of the field.
+///
+pub fn classify_field(mesh: &Mesh, field: &Field, thresholds: &DomainStateThresholds) -> DomainState {
+    classify_domain_state(&domain_state_metrics(mesh, field), thresholds)
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mesh::tests::{cube_mesh, unit_field};
+
+    fn cube_mesh_with<F: Fn([f64; 3]) -> [f64; 3]>(m: F) -> (Mesh, Field) {
+        let mesh = cube_mesh();
+        let field = unit_field(&mesh, m);
+        (mesh, field)
+    }
+
+    //..........................................................................................//
+    //. classify_field()                                                                       .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_classify_single_domain() {
+        let (mesh, field) = cube_mesh_with(|_| [0.0, 1.0, 1.0]);
+        let metrics = domain_state_metrics(&mesh, &field);
+
+        assert!((metrics.net_moment - 1.0).abs() < 1e-12);
+        assert!(metrics.vorticity.abs() < 1e-9);
+        assert_eq!(classify_domain_state(&metrics, &DomainStateThresholds::default()), DomainState::SingleDomain);
+    }
+
+    #[test]
+    fn test_classify_flower() {
+        let (mesh, field) = cube_mesh_with(|v| [60.0 * v[0], 60.0 * v[1], 1.0]);
+        let state = classify_field(&mesh, &field, &DomainStateThresholds::default());
+        assert_eq!(state, DomainState::Flower);
+    }
+
+    #[test]
+    fn test_classify_vortex() {
+        // A vortex about z with a core of radius ~0.004 magnetized along -z, circulating
+        // clockwise when looking down -z (i.e. counter-clockwise when looking down +z).
+        let (mesh, field) = cube_mesh_with(|v| [-v[1], v[0], -0.004]);
+        let metrics = domain_state_metrics(&mesh, &field);
+        assert!(metrics.curl_coherence > 0.9);
+        assert!(metrics.helicity < 0.0);
+
+        match classify_domain_state(&metrics, &DomainStateThresholds::default()) {
+            DomainState::Vortex { axis, chirality, core } => {
+                assert!((axis[2] + 1.0).abs() < 1e-3);
+                assert_eq!(chirality, Chirality::LeftHanded);
+                assert!((core[0] * core[0] + core[1] * core[1]).sqrt() < 0.005);
+            }
+            state => panic!("expected a vortex, found {}", state),
+        }
+    }
+
+    #[test]
+    fn test_classify_multi_domain() {
+        // Two anti-parallel domains, the wall has a coherent curl but there is no vortex core.
+        let (mesh, field) = cube_mesh_with(|v| [0.0, 0.0, if v[0] > 0.0 { 1.0 } else { -1.0 }]);
+        let state = classify_field(&mesh, &field, &DomainStateThresholds::default());
+        assert_eq!(state, DomainState::MultiDomain);
+        assert_eq!(state.to_string(), "MD");
+    }
+}
@@ -0,0 +1,225 @@
+//
+// file: field_transfer.rs
+// author: L. Nagy
+//
+
+use crate::geometry::triangle_closest_point;
+use crate::linalg::{norm, sub};
+use crate::mesh::{Field, Mesh};
+
+///
+/// A summary of a field transfer from one mesh to another.
+///
+/// # Fields
+///
+/// * `nodes` - The number of target nodes.
+/// * `fallback_nodes` - The number of target nodes that lie outside the source mesh and took
+///   their value from the nearest point on the source mesh's boundary.
+/// * `max_fallback_distance` - The largest distance from a fallback node to the source boundary.
+/// * `zero_vectors` - The number of transferred vectors (over all fields) that were zero and
+///   could not be renormalized.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferReport {
+    pub nodes: usize,
+    pub fallback_nodes: usize,
+    pub max_fallback_distance: f64,
+    pub zero_vectors: usize,
+}
+
+///
+/// The source vertices, and their weights, from which the value at a target node is
+/// interpolated.
+///
+type NodeWeights = Vec<(usize, f64)>;
+
+///
+/// Finds the interpolation weights of every target node, by point location in the source mesh or,
+/// for nodes outside the source mesh, from the nearest point on the source mesh's boundary.
+///
+fn node_weights(source: &Mesh, target: &Mesh) -> (Vec<NodeWeights>, TransferReport) {
+    let boundary = source.boundary_faces();
+    let mut report = TransferReport {
+        nodes: target.vertices.len(),
+        fallback_nodes: 0,
+        max_fallback_distance: 0.0,
+        zero_vectors: 0,
+    };
+
+    let weights = target
+        .vertices
+        .iter()
+        .map(|point| {
+            if let Some((element_index, l)) = source.locate(*point) {
+                let element = source.elements[element_index];
+                return element.iter().copied().zip(l.iter().copied()).collect();
+            }
+
+            // Outside the source mesh, use the nearest point on its boundary.
+            let mut nearest: NodeWeights = vec![];
+            let mut nearest_distance = f64::INFINITY;
+            for face in &boundary {
+                let [v0, v1, v2] = face.vertices.map(|vertex| source.vertices[vertex]);
+                let l = triangle_closest_point(v0, v1, v2, *point);
+                let closest = [0, 1, 2].map(|i| l[0] * v0[i] + l[1] * v1[i] + l[2] * v2[i]);
+                let distance = norm(sub(closest, *point));
+                if distance < nearest_distance {
+                    nearest_distance = distance;
+                    nearest = face.vertices.iter().copied().zip(l.iter().copied()).collect();
+                }
+            }
+            report.fallback_nodes += 1;
+            if nearest_distance.is_finite() {
+                report.max_fallback_distance = report.max_fallback_distance.max(nearest_distance);
+            }
+            nearest
+        })
+        .collect();
+
+    (weights, report)
+}
+
+///
+/// Interpolates a field at the target nodes from its source vertex weights, and renormalizes the
+/// result.
+///
+fn interpolate(field: &Field, weights: &[NodeWeights]) -> (Field, usize) {
+    let mut transferred = Field {
+        label: field.label.clone(),
+        vectors: weights
+            .iter()
+            .map(|node| {
+                let mut value = [0.0; 3];
+                for (vertex, weight) in node {
+                    for (v, f) in value.iter_mut().zip(field.vectors[*vertex].iter()) {
+                        *v += weight * f;
+                    }
+                }
+                value
+            })
+            .collect(),
+    };
+    let zero_vectors = transferred.normalize();
+    (transferred, zero_vectors)
+}
+
+///
+/// Transfers a field from one mesh to another, e.g. to carry a converged state across to a
+/// refined mesh of the same geometry as an initial guess.
+///
+/// Each target node takes the linearly interpolated value at its position in the source mesh;
+/// nodes that lie outside the source mesh take the value at the nearest point on the source
+/// mesh's boundary. The transferred vectors are renormalized to unit length.
+///
+/// Point location is much faster if `compute_spatial_index` has been called on the source mesh.
+///
+/// # Arguments
+///
+/// * `source` - The mesh on which the field is defined.
+/// * `field` - The field to transfer.
+/// * `target` - The mesh onto which the field is transferred.
+///
+/// # Returns
+///
+/// The transferred field, with one vector per target vertex, along with a `TransferReport`.
+///
+pub fn transfer_field(source: &Mesh, field: &Field, target: &Mesh) -> (Field, TransferReport) {
+    let (weights, mut report) = node_weights(source, target);
+    let (transferred, zero_vectors) = interpolate(field, &weights);
+    report.zero_vectors = zero_vectors;
+    (transferred, report)
+}
+
+///
+/// Transfers all fields of a source mesh onto a target mesh, replacing the target's fields (see
+/// `transfer_field`).
+///
+/// # Arguments
+///
+/// * `source` - The mesh on which the fields are defined.
+/// * `target` - The mesh onto which the fields are transferred.
+///
+/// # Returns
+///
+/// A `TransferReport`, the node counts are the same for every field.
+///
+pub fn transfer_fields(source: &Mesh, target: &mut Mesh) -> TransferReport {
+    let (weights, mut report) = node_weights(source, target);
+    let mut fields = Vec::with_capacity(source.fields.len());
+    for field in &source.fields {
+        let (transferred, zero_vectors) = interpolate(field, &weights);
+        report.zero_vectors += zero_vectors;
+        fields.push(transferred);
+    }
+    target.fields = fields;
+    report
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mesh::tests::cube_mesh;
+
+    //..........................................................................................//
+    //. transfer_field()                                                                       .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_transfer_field() {
+        let mut source = cube_mesh();
+        source.compute_spatial_index();
+        // A linear field is reproduced exactly (before renormalization).
+        let field = Field {
+            label: String::from("linear"),
+            vectors: source.vertices.iter().map(|v| [1.0, 10.0 * v[0], 10.0 * v[1]]).collect(),
+        };
+
+        // The target is the same cube shrunk a little, with one node pulled outside.
+        let mut target = cube_mesh();
+        for vertex in target.vertices.iter_mut() {
+            *vertex = vertex.map(|c| 0.9 * c);
+        }
+        target.vertices[0] = [0.02, 0.0, 0.0];
+
+        let (transferred, report) = transfer_field(&source, &field, &target);
+        assert_eq!(report.nodes, 70);
+        assert_eq!(report.fallback_nodes, 1);
+        assert!((report.max_fallback_distance - 0.01).abs() < 1e-12);
+        assert_eq!(report.zero_vectors, 0);
+
+        for (vertex, m) in target.vertices.iter().zip(transferred.vectors.iter()).skip(1) {
+            let expected = [1.0, 10.0 * vertex[0], 10.0 * vertex[1]];
+            let length = norm(expected);
+            assert!((0..3).all(|i| (m[i] - expected[i] / length).abs() < 1e-9));
+        }
+
+        // The outside node takes the value on the face x = 0.01.
+        let m = transferred.vectors[0];
+        let expected = [1.0, 0.1, 0.0].map(|c: f64| c / 1.01_f64.sqrt());
+        assert!((0..3).all(|i| (m[i] - expected[i]).abs() < 1e-9));
+    }
+
+    //..........................................................................................//
+    //. transfer_fields()                                                                      .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_transfer_fields() {
+        let source = cube_mesh();
+        let mut target = cube_mesh();
+        target.fields.clear();
+
+        let report = transfer_fields(&source, &mut target);
+        assert_eq!(report.fallback_nodes, 0);
+        assert_eq!(target.fields.len(), 2);
+        assert_eq!(target.fields[1].label, source.fields[1].label);
+        for (a, b) in target.fields[0].vectors.iter().zip(source.fields[0].vectors.iter()) {
+            assert!((0..3).all(|i| (a[i] - b[i]).abs() < 1e-6));
+        }
+    }
+}
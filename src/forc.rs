@@ -0,0 +1,368 @@
+//
+// file: forc.rs
+// author: L. Nagy
+//
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use crate::hysteresis_loops::{read_loop_file, MagneticLoopMeasurement};
+use crate::linalg::solve_linear_system;
+
+///
+/// A first-order reversal curve: after saturation the field is reduced to the reversal field
+/// Br, and the moment is recorded as the field Ba increases again.
+///
+/// # Fields
+///
+/// * `source_file` - The file the curve was read from.
+/// * `br` - The reversal field (T).
+/// * `fields` - The applied field Ba (T) at every step, increasing from `br`.
+/// * `moments` - The moment along the field (Am^2) at every step.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForcCurve {
+    pub source_file: String,
+    pub br: f64,
+    pub fields: Vec<f64>,
+    pub moments: Vec<f64>,
+}
+
+impl ForcCurve {
+    ///
+    /// Builds a reversal curve from measurements in the layout of a loop file. The sweep down to
+    /// the reversal field, if the file holds it, is skipped: the curve starts at the (last)
+    /// smallest field.
+    ///
+    /// # Errors
+    /// Returns an error if there are no measurements, or the field does not increase after the
+    /// reversal field.
+    ///
+    pub fn from_measurements(source_file: &str, measurements: &[MagneticLoopMeasurement]) -> io::Result<ForcCurve> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let reversal = measurements
+            .iter()
+            .enumerate()
+            .fold(None, |best: Option<(usize, f64)>, (index, measurement)| match best {
+                Some((_, b)) if b < measurement.b => best,
+                _ => Some((index, measurement.b)),
+            })
+            .map(|(index, _)| index)
+            .ok_or_else(|| invalid(format!("{}: no reversal curve data found.", source_file)))?;
+
+        let curve = &measurements[reversal..];
+        if curve.windows(2).any(|pair| pair[1].b <= pair[0].b) {
+            return Err(invalid(format!("{}: the field does not increase after the reversal field.", source_file)));
+        }
+        Ok(ForcCurve {
+            source_file: source_file.to_string(),
+            br: curve[0].b,
+            fields: curve.iter().map(|measurement| measurement.b).collect(),
+            moments: curve.iter().map(|measurement| measurement.parallel_moment()).collect(),
+        })
+    }
+}
+
+///
+/// A set of first-order reversal curves, in order of increasing reversal field.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForcData {
+    pub curves: Vec<ForcCurve>,
+}
+
+///
+/// A FORC distribution on a grid of applied and reversal fields.
+///
+/// # Fields
+///
+/// * `smoothing_factor` - The smoothing factor used to compute the distribution.
+/// * `ba` - The applied fields Ba (T) of the grid, increasing.
+/// * `br` - The reversal fields Br (T) of the grid, increasing.
+/// * `rho` - The distribution rho(Ba, Br) (Am^2/T^2), `rho[k][j]` at (`ba[j]`, `br[k]`); NaN at
+///   grid nodes that were not measured (Ba < Br) or where the fit failed.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForcDistribution {
+    pub smoothing_factor: usize,
+    pub ba: Vec<f64>,
+    pub br: Vec<f64>,
+    pub rho: Vec<Vec<f64>>,
+}
+
+///
+/// Converts (Ba, Br) to the coercivity and interaction field coordinates,
+/// `Bc = (Ba - Br) / 2` and `Bu = (Ba + Br) / 2`.
+///
+pub fn to_bc_bu(ba: f64, br: f64) -> (f64, f64) {
+    (0.5 * (ba - br), 0.5 * (ba + br))
+}
+
+impl ForcData {
+    ///
+    /// Collects reversal curves, sorting them by reversal field.
+    ///
+    pub fn new(curves: Vec<ForcCurve>) -> ForcData {
+        let mut curves = curves;
+        curves.sort_by(|a, b| a.br.total_cmp(&b.br));
+        ForcData { curves }
+    }
+
+    ///
+    /// The field spacing of the reversal curves, the median spacing of their reversal fields.
+    ///
+    pub fn field_step(&self) -> f64 {
+        let mut steps: Vec<f64> = self.curves.windows(2).map(|pair| pair[1].br - pair[0].br).collect();
+        if steps.is_empty() {
+            return 0.0;
+        }
+        steps.sort_by(|a, b| a.total_cmp(b));
+        steps[steps.len() / 2]
+    }
+
+    ///
+    /// Computes the FORC distribution rho(Ba, Br) = -1/2 d^2 M / dBa dBr.
+    ///
+    /// At every measured point, the moments of the measured points within `smoothing_factor`
+    /// field steps in both Ba and Br, a (2 SF + 1)^2 window, are fitted with the second order
+    /// polynomial `a1 + a2 Ba + a3 Ba^2 + a4 Br + a5 Br^2 + a6 Ba Br` and rho = -a6 / 2 (Pike et
+    /// al., 1999).
+    ///
+    /// # Arguments
+    ///
+    /// * `smoothing_factor` - The smoothing factor SF, at least 1.
+    ///
+    /// # Returns
+    ///
+    /// The `ForcDistribution`, on the grid of reversal fields and of all measured applied fields;
+    /// it is undefined (NaN) everywhere for fewer than two reversal curves.
+    ///
+    pub fn distribution(&self, smoothing_factor: usize) -> ForcDistribution {
+        let smoothing_factor = smoothing_factor.max(1);
+        let step = self.field_step();
+        let tolerance = 1e-6 * step;
+
+        // The grid of all measured applied fields.
+        let mut ba: Vec<f64> = self.curves.iter().flat_map(|curve| curve.fields.iter().copied()).collect();
+        ba.sort_by(|a, b| a.total_cmp(b));
+        ba.dedup_by(|a, b| (*a - *b).abs() <= tolerance);
+        let br: Vec<f64> = self.curves.iter().map(|curve| curve.br).collect();
+
+        let window = smoothing_factor as f64 * step + tolerance;
+        let mut rho = vec![vec![f64::NAN; ba.len()]; br.len()];
+        if step <= 0.0 {
+            // A single reversal curve has no spacing to fit over.
+            return ForcDistribution { smoothing_factor, ba, br, rho };
+        }
+        for (k, curve) in self.curves.iter().enumerate() {
+            for field in &curve.fields {
+                let j = ba.partition_point(|b| *b < field - tolerance);
+                rho[k][j] = self.fit_mixed_derivative(*field, curve.br, window, step).map_or(f64::NAN, |d| -0.5 * d);
+            }
+        }
+
+        ForcDistribution { smoothing_factor, ba, br, rho }
+    }
+
+    ///
+    /// Fits the local second order polynomial around (`ba0`, `br0`) and returns its mixed
+    /// derivative d^2 M / dBa dBr, or `None` if the fit is underdetermined.
+    ///
+    fn fit_mixed_derivative(&self, ba0: f64, br0: f64, window: f64, step: f64) -> Option<f64> {
+        // The normal equations of the least squares fit, in coordinates scaled by the field step.
+        let mut normal = [[0.0; 6]; 6];
+        let mut rhs = [0.0; 6];
+        let mut count = 0;
+        for curve in self.curves.iter().filter(|curve| (curve.br - br0).abs() <= window) {
+            let y = (curve.br - br0) / step;
+            for (field, moment) in curve.fields.iter().zip(curve.moments.iter()) {
+                if (field - ba0).abs() > window {
+                    continue;
+                }
+                let x = (field - ba0) / step;
+                let terms = [1.0, x, x * x, y, y * y, x * y];
+                for (row, ti) in normal.iter_mut().zip(terms.iter()) {
+                    for (value, tj) in row.iter_mut().zip(terms.iter()) {
+                        *value += ti * tj;
+                    }
+                }
+                for (value, ti) in rhs.iter_mut().zip(terms.iter()) {
+                    *value += ti * moment;
+                }
+                count += 1;
+            }
+        }
+        if count < 6 {
+            return None;
+        }
+        solve_linear_system(normal, rhs).map(|a| a[5] / (step * step))
+    }
+}
+
+///
+/// Reads a set of first-order reversal curves, one per file in the layout of a loop file (see
+/// `read_loop_file` and `ForcCurve::from_measurements`).
+///
+/// # Arguments
+///
+/// * `file_names` - The reversal curve files.
+///
+/// # Returns
+///
+/// The `ForcData`, with the curves in order of increasing reversal field.
+///
+/// # Errors
+/// Returns an error if there are no files, or a file cannot be read or holds no reversal curve.
+///
+pub fn read_forc_files(file_names: &[String]) -> io::Result<ForcData> {
+    if file_names.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "No FORC files found."));
+    }
+    let mut curves = Vec::with_capacity(file_names.len());
+    for file_name in file_names {
+        let measurements = read_loop_file(file_name)?;
+        curves.push(ForcCurve::from_measurements(file_name, &measurements)?);
+    }
+    Ok(ForcData::new(curves))
+}
+
+///
+/// Writes a FORC distribution as comma separated values with the header `Ba,Br,Bc,Bu,rho`, one
+/// row per grid node at which the distribution is defined, preceded by the smoothing factor as a
+/// `#` comment line.
+///
+/// The nodes are those of the (Ba, Br) grid; the `Bc` and `Bu` columns give the coordinates of
+/// each node, which form the (Ba, Br) grid rotated by 45 degrees rather than a regular (Bc, Bu)
+/// grid.
+///
+/// # Arguments
+///
+/// * `file_name` - The name of the file to create.
+/// * `distribution` - The FORC distribution.
+///
+/// # Errors
+/// Returns an error if the file cannot be created or written.
+///
+pub fn write_forc_distribution_to_csv(file_name: &str, distribution: &ForcDistribution) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(file_name)?);
+    writeln!(writer, "# smoothing factor: {}", distribution.smoothing_factor)?;
+    writeln!(writer, "Ba,Br,Bc,Bu,rho")?;
+    for (br, row) in distribution.br.iter().zip(distribution.rho.iter()) {
+        for (ba, rho) in distribution.ba.iter().zip(row.iter()) {
+            if rho.is_nan() {
+                continue;
+            }
+            let (bc, bu) = to_bc_bu(*ba, *br);
+            writeln!(writer, "{:E},{:E},{:E},{:E},{:E}", ba, br, bc, bu, rho)?;
+        }
+    }
+    writer.flush()
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    fn synthetic_forc_data() -> ForcData {
+        // M = 3 Ba Br + Ba^2 + Br, so rho = -3/2 everywhere; reversal fields from -0.1 to 0.1 T
+        // with curves (given in decreasing Br) sweeping back up to 0.1 T in steps of 0.01 T.
+        let curves = (0..=20)
+            .rev()
+            .map(|k| {
+                let br = -0.1 + 0.01 * k as f64;
+                let fields: Vec<f64> = (0..=(20 - k)).map(|j| br + 0.01 * j as f64).collect();
+                ForcCurve {
+                    source_file: format!("forc{}", k),
+                    br,
+                    moments: fields.iter().map(|ba| 3.0 * ba * br + ba * ba + br).collect(),
+                    fields,
+                }
+            })
+            .collect();
+        ForcData::new(curves)
+    }
+
+    //..........................................................................................//
+    //. ForcData::distribution()                                                               .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_forc_distribution() {
+        let data = synthetic_forc_data();
+        assert!(data.curves.windows(2).all(|pair| pair[0].br < pair[1].br));
+        assert!((data.field_step() - 0.01).abs() < 1e-12);
+
+        let distribution = data.distribution(2);
+        assert_eq!(distribution.br.len(), 21);
+        assert_eq!(distribution.ba.len(), 21);
+
+        let mut defined = 0;
+        for (k, row) in distribution.rho.iter().enumerate() {
+            for (j, rho) in row.iter().enumerate() {
+                if j < k {
+                    // Ba < Br is never measured.
+                    assert!(rho.is_nan());
+                } else if !rho.is_nan() {
+                    assert!((rho + 1.5).abs() < 1e-6);
+                    defined += 1;
+                }
+            }
+        }
+        // The distribution is defined at every measured point.
+        assert_eq!(defined, 21 * 22 / 2);
+        assert_eq!(to_bc_bu(0.5, -0.25), (0.375, 0.125));
+    }
+
+    //..........................................................................................//
+    //. read_forc_files()                                                                      .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_read_forc_files() {
+        let root_file_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join("forc");
+        let file_names: Vec<String> = ["forc_3.loop", "forc_1.loop", "forc_2.loop"]
+            .iter()
+            .map(|name| root_file_path.join(name).to_str().unwrap().to_string())
+            .collect();
+
+        let data = read_forc_files(&file_names).unwrap();
+        let brs: Vec<f64> = data.curves.iter().map(|curve| curve.br).collect();
+        assert_eq!(brs, vec![-0.1, -0.05, 0.0]);
+        // The sweep down to the reversal field is skipped.
+        assert_eq!(data.curves[0].fields, vec![-0.1, -0.05, 0.0, 0.05, 0.1]);
+        assert_eq!(data.curves[2].fields, vec![0.0, 0.05, 0.1]);
+        assert_eq!(data.curves[2].moments[0], 2.4e-17);
+
+        assert!(read_forc_files(&[]).is_err());
+    }
+
+    //..........................................................................................//
+    //. write_forc_distribution_to_csv()                                                       .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_write_forc_distribution_to_csv() {
+        let distribution = synthetic_forc_data().distribution(2);
+        let file_name = env::temp_dir().join("tec2hdf5_test_forc.csv");
+        let file_name = file_name.to_str().unwrap();
+        write_forc_distribution_to_csv(file_name, &distribution).unwrap();
+
+        let contents = fs::read_to_string(file_name).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[0], "# smoothing factor: 2");
+        assert_eq!(lines[1], "Ba,Br,Bc,Bu,rho");
+        assert_eq!(lines.len(), 2 + 21 * 22 / 2);
+        let values: Vec<f64> = lines[2].split(',').map(|value| value.parse().unwrap()).collect();
+        assert!((values[2] - 0.5 * (values[0] - values[1])).abs() < 1e-15);
+        assert!((values[3] - 0.5 * (values[0] + values[1])).abs() < 1e-15);
+    }
+}
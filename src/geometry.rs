@@ -1,4 +1,4 @@
-use crate::linalg::Determinant;
+use crate::linalg::{dot, edge_matrix, norm, sub, Adjugate, Determinant};
 
 /// Calculates the volume of a tetrahedron defined by four vertices in 3D space.
 ///
@@ -156,6 +156,504 @@ pub fn tet_lin_vec_integral(
     integral
 }
 
+///
+/// A quadrature rule over a tetrahedron, with the quadrature points given in barycentric
+/// coordinates.
+///
+/// # Fields
+///
+/// * `points` - The quadrature points as barycentric coordinates `[l0, l1, l2, l3]`, where `li`
+///   is the weight of the vertex `vi`.
+/// * `weights` - The quadrature weights, normalised so that they sum to one; the integral over
+///   a tetrahedron is the weighted sum multiplied by the tetrahedron's volume.
+/// * `order` - The polynomial degree that the rule integrates exactly.
+///
+pub struct TetQuadratureRule {
+    pub points: Vec<[f64; 4]>,
+    pub weights: Vec<f64>,
+    pub order: usize,
+}
+
+///
+/// The highest polynomial degree for which `tet_quadrature_rule` provides a rule.
+///
+pub const MAX_TET_QUADRATURE_ORDER: usize = 6;
+
+///
+/// Retrieves a quadrature rule that integrates polynomials of (at least) the given degree exactly
+/// over a tetrahedron.
+///
+/// Degrees 0 to 2 use Keast's rules (the centroid rule and the symmetric four point rule), all of
+/// which have positive weights. Degrees 3 to 6 use Grundmann–Möller rules, which are exact to
+/// degree `2s + 1` and contain negative weights.
+///
+/// # Parameters
+///
+/// - `order`: The polynomial degree that should be integrated exactly.
+///
+/// # Returns
+///
+/// A `TetQuadratureRule` in barycentric coordinates with weights summing to one.
+///
+/// # Panics
+///
+/// Panics if `order` is larger than `MAX_TET_QUADRATURE_ORDER`.
+///
+pub fn tet_quadrature_rule(order: usize) -> TetQuadratureRule {
+    assert!(
+        order <= MAX_TET_QUADRATURE_ORDER,
+        "Tetrahedral quadrature rules are available up to order {}, requested {}",
+        MAX_TET_QUADRATURE_ORDER, order
+    );
+    match order {
+        0 | 1 => TetQuadratureRule {
+            points: vec![[0.25, 0.25, 0.25, 0.25]],
+            weights: vec![1.0],
+            order: 1,
+        },
+        2 => {
+            let a: f64 = (5.0 + 3.0 * 5.0_f64.sqrt()) / 20.0;
+            let b: f64 = (5.0 - 5.0_f64.sqrt()) / 20.0;
+            TetQuadratureRule {
+                points: vec![[a, b, b, b], [b, a, b, b], [b, b, a, b], [b, b, b, a]],
+                weights: vec![0.25; 4],
+                order: 2,
+            }
+        }
+        _ => grundmann_moeller_rule(order / 2),
+    }
+}
+
+///
+/// Builds the Grundmann–Möller rule with index `s` for a tetrahedron, this rule integrates
+/// polynomials of degree `2s + 1` exactly.
+///
+/// The rule on the standard n-simplex (here n = 3) is
+/// ```text
+/// Q[f] = sum_{i=0}^{s} (-1)^i 2^(-2s) (d + n - 2i)^d / (i! (d + n - i)!)
+///            sum_{|b| = s - i} f((2b0 + 1) / (d + n - 2i), ..., (2bn + 1) / (d + n - 2i))
+/// ```
+/// with `d = 2s + 1`; the weights are multiplied by `n!` to normalise them to the unit volume.
+///
+fn grundmann_moeller_rule(s: usize) -> TetQuadratureRule {
+    const N: usize = 3;
+    let d = 2 * s + 1;
+
+    let factorial = |k: usize| -> f64 { (1..=k).map(|j| j as f64).product() };
+
+    let mut points: Vec<[f64; 4]> = Vec::new();
+    let mut weights: Vec<f64> = Vec::new();
+
+    for i in 0..=s {
+        let denominator = (d + N - 2 * i) as f64;
+        let sign = if i % 2 == 0 { 1.0 } else { -1.0 };
+        let weight = sign * 2.0_f64.powi(-2 * s as i32) * denominator.powi(d as i32)
+            / (factorial(i) * factorial(d + N - i))
+            * factorial(N);
+
+        // Enumerate every b = [b0, b1, b2, b3] with b0 + b1 + b2 + b3 = s - i.
+        let total = s - i;
+        for b0 in 0..=total {
+            for b1 in 0..=(total - b0) {
+                for b2 in 0..=(total - b0 - b1) {
+                    let b3 = total - b0 - b1 - b2;
+                    points.push([
+                        (2 * b0 + 1) as f64 / denominator,
+                        (2 * b1 + 1) as f64 / denominator,
+                        (2 * b2 + 1) as f64 / denominator,
+                        (2 * b3 + 1) as f64 / denominator,
+                    ]);
+                    weights.push(weight);
+                }
+            }
+        }
+    }
+
+    TetQuadratureRule {
+        points,
+        weights,
+        order: d,
+    }
+}
+
+///
+/// Maps barycentric coordinates on a tetrahedron to a point in 3D space, this is the mapping
+/// `x = M x~ + x0` of `doc/main.tex` written in terms of the barycentric coordinates.
+///
+/// # Parameters
+///
+/// - `v0`, `v1`, `v2`, `v3`: Vertices of the tetrahedron as 3D points, each given as `[f64; 3]`.
+/// - `l`: The barycentric coordinates `[l0, l1, l2, l3]` of the point.
+///
+/// # Returns
+///
+/// The cartesian coordinates of the point as `[f64; 3]`.
+///
+pub fn tet_barycentric_to_cartesian(
+    v0: [f64; 3],
+    v1: [f64; 3],
+    v2: [f64; 3],
+    v3: [f64; 3],
+    l: [f64; 4],
+) -> [f64; 3] {
+    let mut x = [0.0; 3];
+    for i in 0..3 {
+        x[i] = l[0] * v0[i] + l[1] * v1[i] + l[2] * v2[i] + l[3] * v3[i];
+    }
+    x
+}
+
+///
+/// Computes the barycentric coordinates of a point with respect to a tetrahedron, this is the
+/// inverse mapping `l = M^-1 (x - x0)` of `doc/main.tex`.
+///
+/// # Parameters
+///
+/// - `v0`, `v1`, `v2`, `v3`: Vertices of the tetrahedron as 3D points, each given as `[f64; 3]`.
+/// - `p`: The point whose barycentric coordinates are required.
+///
+/// # Returns
+///
+/// The barycentric coordinates `[l0, l1, l2, l3]` of `p`, or `None` if the tetrahedron is
+/// degenerate. The point lies inside the tetrahedron if all coordinates are non-negative.
+///
+/// # Examples
+///
+/// ```
+/// use tec2hdf5::geometry::tet_barycentric;
+///
+/// let v0 = [0.0, 0.0, 0.0];
+/// let v1 = [1.0, 0.0, 0.0];
+/// let v2 = [0.0, 1.0, 0.0];
+/// let v3 = [0.0, 0.0, 1.0];
+///
+/// let l = tet_barycentric(v0, v1, v2, v3, [0.25, 0.25, 0.25]).unwrap();
+/// assert!((l[0] - 0.25).abs() < 1e-12);
+/// ```
+pub fn tet_barycentric(
+    v0: [f64; 3],
+    v1: [f64; 3],
+    v2: [f64; 3],
+    v3: [f64; 3],
+    p: [f64; 3],
+) -> Option<[f64; 4]> {
+    let m = edge_matrix(v0, v1, v2, v3);
+    let det = m.determinant();
+
+    // Compare the determinant against the cube of the longest edge so that the degeneracy test
+    // does not depend on the length unit of the mesh.
+    let scale = m
+        .iter()
+        .flatten()
+        .fold(0.0_f64, |acc, value| acc.max(value.abs()));
+    if det == 0.0 || det.abs() < 1e-12 * scale * scale * scale {
+        return None;
+    }
+
+    let adj = m.adj();
+    let d = sub(p, v0);
+    let mut l = [0.0; 4];
+    for i in 0..3 {
+        l[i + 1] = dot(adj[i], d) / det;
+    }
+    l[0] = 1.0 - l[1] - l[2] - l[3];
+    Some(l)
+}
+
+///
+/// Computes the gradients of the four linear (barycentric) basis functions of a tetrahedron,
+/// these are constant over the tetrahedron. The gradients of `l1`, `l2` and `l3` are the rows
+/// of `M^-1` (see `tet_barycentric`), and the gradient of `l0` is minus their sum.
+///
+/// # Parameters
+///
+/// - `v0`, `v1`, `v2`, `v3`: Vertices of the tetrahedron as 3D points, each given as `[f64; 3]`.
+///
+/// # Returns
+///
+/// The gradients `[grad l0, grad l1, grad l2, grad l3]`, or `None` if the tetrahedron is
+/// degenerate.
+///
+pub fn tet_basis_gradients(
+    v0: [f64; 3],
+    v1: [f64; 3],
+    v2: [f64; 3],
+    v3: [f64; 3],
+) -> Option<[[f64; 3]; 4]> {
+    let m = edge_matrix(v0, v1, v2, v3);
+    let det = m.determinant();
+
+    let scale = m
+        .iter()
+        .flatten()
+        .fold(0.0_f64, |acc, value| acc.max(value.abs()));
+    if det == 0.0 || det.abs() < 1e-12 * scale * scale * scale {
+        return None;
+    }
+
+    let adj = m.adj();
+    let mut gradients = [[0.0; 3]; 4];
+    for i in 0..3 {
+        for j in 0..3 {
+            gradients[i + 1][j] = adj[i][j] / det;
+            gradients[0][j] -= adj[i][j] / det;
+        }
+    }
+    Some(gradients)
+}
+
+///
+/// Calculates the divergence of a linear vector field over a tetrahedron, this is constant over
+/// the tetrahedron.
+///
+/// # Parameters
+///
+/// - `v0`, `v1`, `v2`, `v3`: Vertices of the tetrahedron as 3D points, each given as `[f64; 3]`.
+/// - `f0`, `f1`, `f2`, `f3`: Values of the vector field at the respective vertices of the
+///   tetrahedron.
+///
+/// # Returns
+///
+/// The divergence `sum_i f_i . grad l_i`, or `None` if the tetrahedron is degenerate.
+///
+#[allow(clippy::too_many_arguments)]
+pub fn tet_lin_vec_divergence(
+    v0: [f64; 3],
+    v1: [f64; 3],
+    v2: [f64; 3],
+    v3: [f64; 3],
+    f0: [f64; 3],
+    f1: [f64; 3],
+    f2: [f64; 3],
+    f3: [f64; 3],
+) -> Option<f64> {
+    let gradients = tet_basis_gradients(v0, v1, v2, v3)?;
+    let mut divergence = 0.0;
+    for (f, gradient) in [f0, f1, f2, f3].iter().zip(gradients.iter()) {
+        divergence += dot(*f, *gradient);
+    }
+    Some(divergence)
+}
+
+///
+/// Calculates the curl of a linear vector field over a tetrahedron, this is constant over the
+/// tetrahedron.
+///
+/// # Parameters
+///
+/// - `v0`, `v1`, `v2`, `v3`: Vertices of the tetrahedron as 3D points, each given as `[f64; 3]`.
+/// - `f0`, `f1`, `f2`, `f3`: Values of the vector field at the respective vertices of the
+///   tetrahedron.
+///
+/// # Returns
+///
+/// The curl `sum_i grad l_i x f_i`, or `None` if the tetrahedron is degenerate.
+///
+#[allow(clippy::too_many_arguments)]
+pub fn tet_lin_vec_curl(
+    v0: [f64; 3],
+    v1: [f64; 3],
+    v2: [f64; 3],
+    v3: [f64; 3],
+    f0: [f64; 3],
+    f1: [f64; 3],
+    f2: [f64; 3],
+    f3: [f64; 3],
+) -> Option<[f64; 3]> {
+    let gradients = tet_basis_gradients(v0, v1, v2, v3)?;
+    let mut curl = [0.0; 3];
+    for (f, g) in [f0, f1, f2, f3].iter().zip(gradients.iter()) {
+        curl[0] += g[1] * f[2] - g[2] * f[1];
+        curl[1] += g[2] * f[0] - g[0] * f[2];
+        curl[2] += g[0] * f[1] - g[1] * f[0];
+    }
+    Some(curl)
+}
+
+///
+/// Calculates the integral of an arbitrary scalar function over a tetrahedron in 3D space using a
+/// quadrature rule that is exact for polynomials up to the given degree.
+///
+/// # Parameters
+///
+/// - `v0`, `v1`, `v2`, `v3`: Vertices of the tetrahedron as 3D points, each given as `[f64; 3]`.
+/// - `f`: The function to integrate, it is evaluated at cartesian points inside the tetrahedron.
+/// - `order`: The polynomial degree that should be integrated exactly (at most
+///   `MAX_TET_QUADRATURE_ORDER`).
+///
+/// # Returns
+///
+/// The approximate integral of `f` over the tetrahedron as an `f64`.
+///
+/// # Formula
+///
+/// The quadrature points are mapped from barycentric to cartesian coordinates and
+/// ```text
+/// Integral = Volume * sum_k w_k f(x_k)
+/// ```
+/// where `Volume` is the absolute value of the tetrahedron's volume.
+///
+/// # Examples
+///
+/// ```
+/// use tec2hdf5::geometry::integrate_over_tet;
+///
+/// let v0 = [0.0, 0.0, 0.0];
+/// let v1 = [1.0, 0.0, 0.0];
+/// let v2 = [0.0, 1.0, 0.0];
+/// let v3 = [0.0, 0.0, 1.0];
+///
+/// // The integral of x^2 over the standard tetrahedron is 2! / 5! = 1 / 60.
+/// let integral = integrate_over_tet(v0, v1, v2, v3, |x| x[0] * x[0], 2);
+/// assert!((integral - 1.0 / 60.0).abs() < 1e-12);
+/// ```
+pub fn integrate_over_tet<F>(
+    v0: [f64; 3],
+    v1: [f64; 3],
+    v2: [f64; 3],
+    v3: [f64; 3],
+    f: F,
+    order: usize,
+) -> f64
+where
+    F: Fn([f64; 3]) -> f64,
+{
+    let v: f64 = tet_volume(v0, v1, v2, v3).abs();
+    let rule = tet_quadrature_rule(order);
+    let mut sum = 0.0;
+    for (point, weight) in rule.points.iter().zip(rule.weights.iter()) {
+        sum += weight * f(tet_barycentric_to_cartesian(v0, v1, v2, v3, *point));
+    }
+    v * sum
+}
+
+///
+/// Calculates the area vector of a triangle, i.e. the normal to the triangle whose length is the
+/// triangle's area.
+///
+/// # Parameters
+///
+/// - `v0`, `v1`, `v2`: Vertices of the triangle as 3D points, each given as `[f64; 3]`.
+///
+/// # Returns
+///
+/// The vector `0.5 * (v1 - v0) x (v2 - v0)`, which follows the right hand rule with respect to
+/// the order of the vertices.
+///
+pub fn triangle_area_vector(v0: [f64; 3], v1: [f64; 3], v2: [f64; 3]) -> [f64; 3] {
+    let a = [v1[0] - v0[0], v1[1] - v0[1], v1[2] - v0[2]];
+    let b = [v2[0] - v0[0], v2[1] - v0[1], v2[2] - v0[2]];
+    [
+        0.5 * (a[1] * b[2] - a[2] * b[1]),
+        0.5 * (a[2] * b[0] - a[0] * b[2]),
+        0.5 * (a[0] * b[1] - a[1] * b[0]),
+    ]
+}
+
+///
+/// Calculates the area of a triangle.
+///
+/// # Parameters
+///
+/// - `v0`, `v1`, `v2`: Vertices of the triangle as 3D points, each given as `[f64; 3]`.
+///
+/// # Returns
+///
+/// The area of the triangle, this is always positive.
+///
+pub fn triangle_area(v0: [f64; 3], v1: [f64; 3], v2: [f64; 3]) -> f64 {
+    norm(triangle_area_vector(v0, v1, v2))
+}
+
+///
+/// Calculates the unit normal of a triangle.
+///
+/// # Parameters
+///
+/// - `v0`, `v1`, `v2`: Vertices of the triangle as 3D points, each given as `[f64; 3]`.
+///
+/// # Returns
+///
+/// The unit normal following the right hand rule with respect to the order of the vertices, or a
+/// zero vector if the triangle is degenerate.
+///
+pub fn triangle_normal(v0: [f64; 3], v1: [f64; 3], v2: [f64; 3]) -> [f64; 3] {
+    let n = triangle_area_vector(v0, v1, v2);
+    let length = norm(n);
+    if length > 0.0 {
+        [n[0] / length, n[1] / length, n[2] / length]
+    } else {
+        [0.0; 3]
+    }
+}
+
+///
+/// Finds the point of a triangle that is closest to a given point.
+///
+/// # Parameters
+///
+/// - `v0`, `v1`, `v2`: Vertices of the triangle as 3D points, each given as `[f64; 3]`.
+/// - `p`: The point.
+///
+/// # Returns
+///
+/// The barycentric coordinates `[l0, l1, l2]` (non-negative, summing to one) of the closest
+/// point, i.e. the point `l0 v0 + l1 v1 + l2 v2`.
+///
+pub fn triangle_closest_point(v0: [f64; 3], v1: [f64; 3], v2: [f64; 3], p: [f64; 3]) -> [f64; 3] {
+    // The Voronoi region tests of Ericson, Real-Time Collision Detection, section 5.1.5.
+    let ab = sub(v1, v0);
+    let ac = sub(v2, v0);
+
+    let ap = sub(p, v0);
+    let d1 = dot(ab, ap);
+    let d2 = dot(ac, ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return [1.0, 0.0, 0.0];
+    }
+
+    let bp = sub(p, v1);
+    let d3 = dot(ab, bp);
+    let d4 = dot(ac, bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return [0.0, 1.0, 0.0];
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let t = d1 / (d1 - d3);
+        return [1.0 - t, t, 0.0];
+    }
+
+    let cp = sub(p, v2);
+    let d5 = dot(ab, cp);
+    let d6 = dot(ac, cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return [0.0, 0.0, 1.0];
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let t = d2 / (d2 - d6);
+        return [1.0 - t, 0.0, t];
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        let t = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return [0.0, 1.0 - t, t];
+    }
+
+    let denominator = va + vb + vc;
+    if denominator == 0.0 {
+        // A degenerate triangle, with all regions empty.
+        return [1.0, 0.0, 0.0];
+    }
+    let v = vb / denominator;
+    let w = vc / denominator;
+    [1.0 - v - w, v, w]
+}
+
 //----------------------------------------------------------------------------------------------//
 //- Tests                                                                                      -//
 //----------------------------------------------------------------------------------------------//
@@ -298,4 +796,248 @@ mod tests {
             );
         }
     }
+
+    //..........................................................................................//
+    //. tet_quadrature_rule()                                                                  .//
+    //..........................................................................................//
+
+    fn factorial(k: u32) -> f64 {
+        (1..=k).map(|j| j as f64).product()
+    }
+
+    #[test]
+    fn test_tet_quadrature_rule_weights_sum_to_one() {
+        for order in 0..=MAX_TET_QUADRATURE_ORDER {
+            let rule = tet_quadrature_rule(order);
+            assert!(rule.order >= order);
+            assert_eq!(rule.points.len(), rule.weights.len());
+
+            let sum: f64 = rule.weights.iter().sum();
+            assert!((sum - 1.0).abs() < 1e-12, "Order {}: weights sum to {}", order, sum);
+
+            for point in &rule.points {
+                let l_sum: f64 = point.iter().sum();
+                assert!((l_sum - 1.0).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tet_quadrature_rule_order_too_large() {
+        tet_quadrature_rule(MAX_TET_QUADRATURE_ORDER + 1);
+    }
+
+    //..........................................................................................//
+    //. tet_barycentric()                                                                      .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_tet_barycentric_round_trip() {
+        let v0 = [0.1, -0.2, 0.3];
+        let v1 = [1.5, 0.1, -0.4];
+        let v2 = [0.2, 2.0, 0.1];
+        let v3 = [-0.3, 0.4, 1.7];
+
+        let l = [0.1, 0.2, 0.3, 0.4];
+        let p = tet_barycentric_to_cartesian(v0, v1, v2, v3, l);
+        let result = tet_barycentric(v0, v1, v2, v3, p).unwrap();
+        for i in 0..4 {
+            assert!((result[i] - l[i]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_tet_barycentric_small_scale() {
+        // Coordinates in metres for a nanometre sized tetrahedron.
+        let v0 = [0.0, 0.0, 0.0];
+        let v1 = [1e-9, 0.0, 0.0];
+        let v2 = [0.0, 1e-9, 0.0];
+        let v3 = [0.0, 0.0, 1e-9];
+
+        let l = tet_barycentric(v0, v1, v2, v3, [0.5e-9, 0.25e-9, 0.0]).unwrap();
+        assert!((l[0] - 0.25).abs() < 1e-12);
+        assert!((l[1] - 0.5).abs() < 1e-12);
+        assert!((l[2] - 0.25).abs() < 1e-12);
+        assert!(l[3].abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_tet_barycentric_degenerate() {
+        let v0 = [0.0, 0.0, 0.0];
+        let v1 = [1.0, 0.0, 0.0];
+        let v2 = [0.0, 1.0, 0.0];
+        let v3 = [1.0, 1.0, 0.0];
+
+        assert!(tet_barycentric(v0, v1, v2, v3, [0.2, 0.2, 0.0]).is_none());
+    }
+
+    //..........................................................................................//
+    //. integrate_over_tet()                                                                   .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_integrate_over_tet_monomials() {
+        // Reference tetrahedron, the integral of x^a y^b z^c is a! b! c! / (a + b + c + 3)!
+        let v0 = [0.0, 0.0, 0.0];
+        let v1 = [1.0, 0.0, 0.0];
+        let v2 = [0.0, 1.0, 0.0];
+        let v3 = [0.0, 0.0, 1.0];
+
+        for order in 0..=MAX_TET_QUADRATURE_ORDER as u32 {
+            for a in 0..=order {
+                for b in 0..=(order - a) {
+                    for c in 0..=(order - a - b) {
+                        let integral = integrate_over_tet(
+                            v0, v1, v2, v3,
+                            |x| x[0].powi(a as i32) * x[1].powi(b as i32) * x[2].powi(c as i32),
+                            order as usize,
+                        );
+                        let expected = factorial(a) * factorial(b) * factorial(c)
+                            / factorial(a + b + c + 3);
+                        assert!(
+                            (integral - expected).abs() < 1e-12,
+                            "Order {}, monomial x^{} y^{} z^{}: expected {}, got {}",
+                            order, a, b, c, expected, integral
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_integrate_over_tet_linear_field() {
+        // A linear field on a general tetrahedron should agree with tet_lin_scal_integral().
+        let v0 = [0.1, -0.2, 0.3];
+        let v1 = [1.5, 0.1, -0.4];
+        let v2 = [0.2, 2.0, 0.1];
+        let v3 = [-0.3, 0.4, 1.7];
+
+        let s = |x: [f64; 3]| 1.0 + 2.0 * x[0] - 3.0 * x[1] + 0.5 * x[2];
+
+        let expected = tet_lin_scal_integral(v0, v1, v2, v3, s(v0), s(v1), s(v2), s(v3));
+        for order in 1..=MAX_TET_QUADRATURE_ORDER {
+            let integral = integrate_over_tet(v0, v1, v2, v3, s, order);
+            assert!((integral - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_integrate_over_tet_quadratic_on_mapped_tet() {
+        // Scaling the standard tetrahedron by 2 maps x^2 y^2 to 2^7 times its reference value.
+        let v0 = [0.0, 0.0, 0.0];
+        let v1 = [2.0, 0.0, 0.0];
+        let v2 = [0.0, 2.0, 0.0];
+        let v3 = [0.0, 0.0, 2.0];
+
+        let integral = integrate_over_tet(v0, v1, v2, v3, |x| x[0] * x[0] * x[1] * x[1], 4);
+        let expected = 128.0 * 4.0 / factorial(7);
+        assert!((integral - expected).abs() < 1e-12);
+    }
+
+    //..........................................................................................//
+    //. triangle_area(), triangle_normal()                                                     .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_triangle_area_and_normal() {
+        let v0 = [1.0, 1.0, 1.0];
+        let v1 = [3.0, 1.0, 1.0];
+        let v2 = [1.0, 1.0, 4.0];
+
+        assert!((triangle_area(v0, v1, v2) - 3.0).abs() < 1e-12);
+
+        // (v1 - v0) x (v2 - v0) = (2, 0, 0) x (0, 0, 3) points along -y.
+        let n = triangle_normal(v0, v1, v2);
+        assert!((n[0]).abs() < 1e-12);
+        assert!((n[1] + 1.0).abs() < 1e-12);
+        assert!((n[2]).abs() < 1e-12);
+
+        let a = triangle_area_vector(v0, v2, v1);
+        assert!((a[1] - 3.0).abs() < 1e-12);
+
+        assert_eq!(triangle_normal(v0, v1, [5.0, 1.0, 1.0]), [0.0; 3]);
+    }
+
+    //..........................................................................................//
+    //. triangle_closest_point()                                                               .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_triangle_closest_point() {
+        let v0 = [0.0, 0.0, 0.0];
+        let v1 = [1.0, 0.0, 0.0];
+        let v2 = [0.0, 1.0, 0.0];
+
+        // Above the interior, the point projects straight down.
+        let l = triangle_closest_point(v0, v1, v2, [0.25, 0.25, 3.0]);
+        assert!((l[0] - 0.5).abs() < 1e-12 && (l[1] - 0.25).abs() < 1e-12 && (l[2] - 0.25).abs() < 1e-12);
+
+        // Beyond a vertex.
+        assert_eq!(triangle_closest_point(v0, v1, v2, [-1.0, -1.0, 0.5]), [1.0, 0.0, 0.0]);
+        assert_eq!(triangle_closest_point(v0, v1, v2, [2.0, -0.5, 0.0]), [0.0, 1.0, 0.0]);
+
+        // Beyond the hypotenuse.
+        let l = triangle_closest_point(v0, v1, v2, [1.0, 1.0, 0.0]);
+        assert!(l[0].abs() < 1e-12 && (l[1] - 0.5).abs() < 1e-12 && (l[2] - 0.5).abs() < 1e-12);
+
+        // Beyond the edge v0-v2.
+        let l = triangle_closest_point(v0, v1, v2, [-1.0, 0.75, 0.0]);
+        assert!((l[0] - 0.25).abs() < 1e-12 && l[1].abs() < 1e-12 && (l[2] - 0.75).abs() < 1e-12);
+    }
+
+    //..........................................................................................//
+    //. tet_basis_gradients(), tet_lin_vec_divergence()                                        .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_tet_basis_gradients() {
+        let v0 = [0.5, 0.0, 0.1];
+        let v1 = [2.0, 0.3, 0.0];
+        let v2 = [0.2, 1.7, 0.4];
+        let v3 = [0.1, 0.2, 1.9];
+        let gradients = tet_basis_gradients(v0, v1, v2, v3).unwrap();
+
+        // Each basis function is one at its own vertex and zero at the others.
+        let vertices = [v0, v1, v2, v3];
+        for (i, gradient) in gradients.iter().enumerate() {
+            for (j, vertex) in vertices.iter().enumerate() {
+                let d = (0..3).map(|k| gradient[k] * (vertex[k] - vertices[i][k])).sum::<f64>();
+                let expected = if i == j { 0.0 } else { -1.0 };
+                assert!((d - expected).abs() < 1e-12);
+            }
+        }
+
+        // A flat tetrahedron (v3 in the plane of the other vertices) has no gradients.
+        assert!(tet_basis_gradients(v0, v1, v2, [1.7, 2.0, 0.3]).is_none());
+    }
+
+    #[test]
+    fn test_tet_lin_vec_divergence() {
+        let v0 = [0.5, 0.0, 0.1];
+        let v1 = [2.0, 0.3, 0.0];
+        let v2 = [0.2, 1.7, 0.4];
+        let v3 = [0.1, 0.2, 1.9];
+
+        // f = (2x + y, 3y - z, x - 4z) has divergence 2 + 3 - 4 = 1.
+        let f = |v: [f64; 3]| [2.0 * v[0] + v[1], 3.0 * v[1] - v[2], v[0] - 4.0 * v[2]];
+        let divergence = tet_lin_vec_divergence(v0, v1, v2, v3, f(v0), f(v1), f(v2), f(v3)).unwrap();
+        assert!((divergence - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_tet_lin_vec_curl() {
+        let v0 = [0.5, 0.0, 0.1];
+        let v1 = [2.0, 0.3, 0.0];
+        let v2 = [0.2, 1.7, 0.4];
+        let v3 = [0.1, 0.2, 1.9];
+
+        // f = (-y + z, x, 3x) has curl (0, 1 - 3, 1 + 1) = (0, -2, 2).
+        let f = |v: [f64; 3]| [-v[1] + v[2], v[0], 3.0 * v[0]];
+        let curl = tet_lin_vec_curl(v0, v1, v2, v3, f(v0), f(v1), f(v2), f(v3)).unwrap();
+        assert!(curl[0].abs() < 1e-12);
+        assert!((curl[1] + 2.0).abs() < 1e-12);
+        assert!((curl[2] - 2.0).abs() < 1e-12);
+    }
 }
@@ -0,0 +1,624 @@
+//
+// file: gmsh_io.rs
+// author: L. Nagy
+//
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::mesh::Mesh;
+
+/// The Gmsh element type of a 4-node tetrahedron.
+const GMSH_TETRAHEDRON: i64 = 4;
+
+///
+/// The number of nodes of each (standard) Gmsh element type, this is needed to skip over elements
+/// that are not tetrahedra.
+///
+fn nodes_per_element(element_type: i64) -> Option<usize> {
+    let nodes = match element_type {
+        1 => 2,   // 2-node line
+        2 => 3,   // 3-node triangle
+        3 => 4,   // 4-node quadrangle
+        4 => 4,   // 4-node tetrahedron
+        5 => 8,   // 8-node hexahedron
+        6 => 6,   // 6-node prism
+        7 => 5,   // 5-node pyramid
+        8 => 3,   // 3-node second order line
+        9 => 6,   // 6-node second order triangle
+        10 => 9,  // 9-node second order quadrangle
+        11 => 10, // 10-node second order tetrahedron
+        12 => 27, // 27-node second order hexahedron
+        13 => 18, // 18-node second order prism
+        14 => 14, // 14-node second order pyramid
+        15 => 1,  // 1-node point
+        16 => 8,  // 8-node second order quadrangle
+        17 => 20, // 20-node second order hexahedron
+        18 => 15, // 15-node second order prism
+        19 => 13, // 13-node second order pyramid
+        20 => 9,  // 9-node third order incomplete triangle
+        21 => 10, // 10-node third order triangle
+        22 => 12, // 12-node fourth order incomplete triangle
+        23 => 15, // 15-node fourth order triangle
+        24 => 15, // 15-node fifth order incomplete triangle
+        25 => 21, // 21-node fifth order complete triangle
+        26 => 4,  // 4-node third order edge
+        27 => 5,  // 5-node fourth order edge
+        28 => 6,  // 6-node fifth order edge
+        29 => 20, // 20-node third order tetrahedron
+        30 => 35, // 35-node fourth order tetrahedron
+        31 => 56, // 56-node fifth order tetrahedron
+        _ => return None,
+    };
+    Some(nodes)
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+///
+/// The MSH file format versions that can be read.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MshVersion {
+    V2,
+    V4,
+}
+
+///
+/// A cursor over the contents of an MSH file. Numbers are read either as whitespace separated
+/// text tokens (ASCII files) or as raw values (binary files), so that each section can be parsed
+/// with the same code regardless of the file type.
+///
+struct MshReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    binary: bool,
+    little_endian: bool,
+    size_t_bytes: usize,
+}
+
+impl<'a> MshReader<'a> {
+    fn new(bytes: &'a [u8]) -> MshReader<'a> {
+        MshReader {
+            bytes,
+            position: 0,
+            binary: false,
+            little_endian: true,
+            size_t_bytes: 8,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.position < self.bytes.len() && self.bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    ///
+    /// The next whitespace separated text token, or `None` at the end of the file.
+    ///
+    fn token(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let start = self.position;
+        while self.position < self.bytes.len() && !self.bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+        if start == self.position {
+            None
+        } else {
+            Some(String::from_utf8_lossy(&self.bytes[start..self.position]).to_string())
+        }
+    }
+
+    fn parse_token<T: std::str::FromStr>(&mut self, what: &str) -> io::Result<T> {
+        let token = self
+            .token()
+            .ok_or_else(|| invalid(format!("Unexpected end of MSH file reading {}", what)))?;
+        token
+            .parse::<T>()
+            .map_err(|_| invalid(format!("Invalid {} in MSH file: '{}'", what, token)))
+    }
+
+    ///
+    /// Moves past the end of the current line, binary data always starts on a new line.
+    ///
+    fn skip_line(&mut self) {
+        while self.position < self.bytes.len() && self.bytes[self.position] != b'\n' {
+            self.position += 1;
+        }
+        self.position = (self.position + 1).min(self.bytes.len());
+    }
+
+    fn raw<const N: usize>(&mut self, what: &str) -> io::Result<[u8; N]> {
+        if self.position + N > self.bytes.len() {
+            return Err(invalid(format!("Unexpected end of MSH file reading {}", what)));
+        }
+        let mut raw = [0u8; N];
+        raw.copy_from_slice(&self.bytes[self.position..self.position + N]);
+        self.position += N;
+        if !self.little_endian {
+            raw.reverse();
+        }
+        Ok(raw)
+    }
+
+    /// Reads an `int` value.
+    fn int(&mut self, what: &str) -> io::Result<i64> {
+        if self.binary {
+            Ok(i32::from_le_bytes(self.raw::<4>(what)?) as i64)
+        } else {
+            self.parse_token(what)
+        }
+    }
+
+    /// Reads a `size_t` value (MSH 4 only).
+    fn size(&mut self, what: &str) -> io::Result<usize> {
+        if !self.binary {
+            self.parse_token(what)
+        } else if self.size_t_bytes == 4 {
+            Ok(u32::from_le_bytes(self.raw::<4>(what)?) as usize)
+        } else {
+            Ok(u64::from_le_bytes(self.raw::<8>(what)?) as usize)
+        }
+    }
+
+    /// Reads a `double` value.
+    fn float(&mut self, what: &str) -> io::Result<f64> {
+        if self.binary {
+            Ok(f64::from_le_bytes(self.raw::<8>(what)?))
+        } else {
+            self.parse_token(what)
+        }
+    }
+
+    ///
+    /// Checks that the section `name` is closed by its `$End<name>` marker.
+    ///
+    fn expect_end(&mut self, name: &str) -> io::Result<()> {
+        let expected = format!("$End{}", name);
+        match self.token() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(invalid(format!("Expected '{}' in MSH file, found '{}'", expected, token))),
+            None => Err(invalid(format!("Expected '{}' in MSH file, found end of file", expected))),
+        }
+    }
+
+    ///
+    /// Skips over the contents of a section that is not needed to build a mesh.
+    ///
+    fn skip_section(&mut self, name: &str) -> io::Result<()> {
+        let marker = format!("$End{}", name);
+        let marker = marker.as_bytes();
+        match self.bytes[self.position..].windows(marker.len()).position(|window| window == marker) {
+            Some(offset) => {
+                self.position += offset + marker.len();
+                Ok(())
+            }
+            None => Err(invalid(format!("Section '${}' is not terminated in MSH file", name))),
+        }
+    }
+}
+
+///
+/// The contents of an MSH file that are needed to assemble a tetrahedral mesh.
+///
+#[derive(Default)]
+struct MshContents {
+    vertices: Vec<[f64; 3]>,
+    node_indices: HashMap<usize, usize>,
+    tetrahedra: Vec<([usize; 4], usize)>,
+    volume_physical_tags: HashMap<i64, i64>,
+}
+
+impl MshContents {
+    fn add_node(&mut self, tag: usize, position: [f64; 3]) {
+        self.node_indices.insert(tag, self.vertices.len());
+        self.vertices.push(position);
+    }
+}
+
+///
+/// Reads the `$Entities` section of an MSH 4.1 file, keeping the (first) physical tag of each
+/// volume.
+///
+fn read_entities(reader: &mut MshReader, contents: &mut MshContents) -> io::Result<()> {
+    let no_of_points = reader.size("number of points")?;
+    let no_of_curves = reader.size("number of curves")?;
+    let no_of_surfaces = reader.size("number of surfaces")?;
+    let no_of_volumes = reader.size("number of volumes")?;
+
+    for _ in 0..no_of_points {
+        reader.int("point tag")?;
+        for _ in 0..3 {
+            reader.float("point coordinate")?;
+        }
+        let no_of_physical_tags = reader.size("number of physical tags")?;
+        for _ in 0..no_of_physical_tags {
+            reader.int("physical tag")?;
+        }
+    }
+
+    for dimension in 1..=3 {
+        let no_of_entities = match dimension {
+            1 => no_of_curves,
+            2 => no_of_surfaces,
+            _ => no_of_volumes,
+        };
+        for _ in 0..no_of_entities {
+            let tag = reader.int("entity tag")?;
+            for _ in 0..6 {
+                reader.float("bounding box")?;
+            }
+            let no_of_physical_tags = reader.size("number of physical tags")?;
+            for index in 0..no_of_physical_tags {
+                let physical_tag = reader.int("physical tag")?;
+                if dimension == 3 && index == 0 {
+                    contents.volume_physical_tags.insert(tag, physical_tag);
+                }
+            }
+            let no_of_bounding_entities = reader.size("number of bounding entities")?;
+            for _ in 0..no_of_bounding_entities {
+                reader.int("bounding entity tag")?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+///
+/// Reads the `$Nodes` section of an MSH 2.2 or 4.1 file.
+///
+fn read_nodes(reader: &mut MshReader, version: MshVersion, contents: &mut MshContents) -> io::Result<()> {
+    match version {
+        MshVersion::V2 => {
+            // The number of nodes is always written as text.
+            let no_of_nodes: usize = reader.parse_token("number of nodes")?;
+            if reader.binary {
+                reader.skip_line();
+            }
+            for _ in 0..no_of_nodes {
+                let tag = reader.int("node tag")?;
+                let position = [
+                    reader.float("node coordinate")?,
+                    reader.float("node coordinate")?,
+                    reader.float("node coordinate")?,
+                ];
+                contents.add_node(tag as usize, position);
+            }
+        }
+        MshVersion::V4 => {
+            let no_of_blocks = reader.size("number of node blocks")?;
+            reader.size("number of nodes")?;
+            reader.size("minimum node tag")?;
+            reader.size("maximum node tag")?;
+            for _ in 0..no_of_blocks {
+                let dimension = reader.int("entity dimension")?;
+                reader.int("entity tag")?;
+                let parametric = reader.int("parametric flag")?;
+                let no_of_block_nodes = reader.size("number of nodes in block")?;
+
+                let mut tags = Vec::with_capacity(no_of_block_nodes);
+                for _ in 0..no_of_block_nodes {
+                    tags.push(reader.size("node tag")?);
+                }
+
+                // Parametric coordinates follow x, y and z and are discarded.
+                let no_of_parametric = if parametric != 0 { dimension.max(0) as usize } else { 0 };
+                for tag in tags {
+                    let position = [
+                        reader.float("node coordinate")?,
+                        reader.float("node coordinate")?,
+                        reader.float("node coordinate")?,
+                    ];
+                    for _ in 0..no_of_parametric {
+                        reader.float("parametric coordinate")?;
+                    }
+                    contents.add_node(tag, position);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+///
+/// Reads the node tags of an element, keeping them only if the element is a tetrahedron.
+///
+fn read_element_nodes(
+    reader: &mut MshReader,
+    version: MshVersion,
+    element_type: i64,
+) -> io::Result<Option<[usize; 4]>> {
+    let no_of_nodes = nodes_per_element(element_type)
+        .ok_or_else(|| invalid(format!("Unsupported Gmsh element type {}", element_type)))?;
+
+    let mut tags = Vec::with_capacity(no_of_nodes);
+    for _ in 0..no_of_nodes {
+        let tag = match version {
+            MshVersion::V2 => reader.int("element node tag")? as usize,
+            MshVersion::V4 => reader.size("element node tag")?,
+        };
+        tags.push(tag);
+    }
+
+    if element_type == GMSH_TETRAHEDRON {
+        Ok(Some([tags[0], tags[1], tags[2], tags[3]]))
+    } else {
+        Ok(None)
+    }
+}
+
+///
+/// Reads the `$Elements` section of an MSH 2.2 or 4.1 file, keeping only the tetrahedra.
+///
+fn read_elements(reader: &mut MshReader, version: MshVersion, contents: &mut MshContents) -> io::Result<()> {
+    // The subdomain of a tetrahedron is its physical tag, or its elementary tag if the element
+    // does not belong to a physical group.
+    let subdomain = |physical: i64, elementary: i64| -> usize {
+        if physical > 0 { physical as usize } else { elementary.max(1) as usize }
+    };
+
+    match version {
+        MshVersion::V2 => {
+            // The number of elements is always written as text.
+            let no_of_elements: usize = reader.parse_token("number of elements")?;
+            let mut no_read = 0;
+            if reader.binary {
+                reader.skip_line();
+                // Binary elements are written in blocks of the same type.
+                while no_read < no_of_elements {
+                    let element_type = reader.int("element type")?;
+                    let no_of_block_elements = reader.int("number of elements in block")?.max(0) as usize;
+                    let no_of_tags = reader.int("number of element tags")?.max(0) as usize;
+                    for _ in 0..no_of_block_elements {
+                        reader.int("element tag")?;
+                        let mut tags = vec![0; no_of_tags];
+                        for tag in tags.iter_mut() {
+                            *tag = reader.int("element tag")?;
+                        }
+                        if let Some(nodes) = read_element_nodes(reader, version, element_type)? {
+                            let physical = tags.first().copied().unwrap_or(0);
+                            let elementary = tags.get(1).copied().unwrap_or(0);
+                            contents.tetrahedra.push((nodes, subdomain(physical, elementary)));
+                        }
+                    }
+                    no_read += no_of_block_elements;
+                }
+            } else {
+                while no_read < no_of_elements {
+                    reader.int("element tag")?;
+                    let element_type = reader.int("element type")?;
+                    let no_of_tags = reader.int("number of element tags")?.max(0) as usize;
+                    let mut tags = vec![0; no_of_tags];
+                    for tag in tags.iter_mut() {
+                        *tag = reader.int("element tag")?;
+                    }
+                    if let Some(nodes) = read_element_nodes(reader, version, element_type)? {
+                        let physical = tags.first().copied().unwrap_or(0);
+                        let elementary = tags.get(1).copied().unwrap_or(0);
+                        contents.tetrahedra.push((nodes, subdomain(physical, elementary)));
+                    }
+                    no_read += 1;
+                }
+            }
+        }
+        MshVersion::V4 => {
+            let no_of_blocks = reader.size("number of element blocks")?;
+            reader.size("number of elements")?;
+            reader.size("minimum element tag")?;
+            reader.size("maximum element tag")?;
+            for _ in 0..no_of_blocks {
+                let dimension = reader.int("entity dimension")?;
+                let entity_tag = reader.int("entity tag")?;
+                let element_type = reader.int("element type")?;
+                let no_of_block_elements = reader.size("number of elements in block")?;
+
+                let physical = if dimension == 3 {
+                    contents.volume_physical_tags.get(&entity_tag).copied().unwrap_or(0)
+                } else {
+                    0
+                };
+                for _ in 0..no_of_block_elements {
+                    reader.size("element tag")?;
+                    if let Some(nodes) = read_element_nodes(reader, version, element_type)? {
+                        contents.tetrahedra.push((nodes, subdomain(physical, entity_tag)));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+///
+/// Reads a tetrahedral mesh from a Gmsh MSH file.
+///
+/// MSH 2.2 and 4.1 files are supported, in both ASCII and binary form. Only the 4-node
+/// tetrahedra are kept, all other elements (e.g. boundary triangles) are ignored. The physical
+/// volume tag of each tetrahedron becomes its submesh index; tetrahedra that do not belong to a
+/// physical volume take the tag of their elementary (geometrical) volume instead. Node tags need
+/// not be contiguous, they are renumbered in the order in which the nodes appear in the file.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the MSH file.
+///
+/// # Returns
+///
+/// A `Mesh` without any fields, labelled with the file stem of `filename`.
+///
+/// # Errors
+/// Returns an `InvalidData` error if the file is not an MSH file, uses an unsupported version or
+/// element type, is truncated, references unknown nodes, or contains no tetrahedra.
+///
+pub fn read_mesh_from_gmsh(filename: &str) -> io::Result<Mesh> {
+    let bytes = fs::read(filename)?;
+    let mut reader = MshReader::new(&bytes);
+
+    let mut version: Option<MshVersion> = None;
+    let mut contents = MshContents::default();
+
+    while let Some(section) = reader.token() {
+        let name = section
+            .strip_prefix('$')
+            .ok_or_else(|| invalid(format!("Expected a section in MSH file, found '{}'", section)))?
+            .to_string();
+
+        if name == "MeshFormat" {
+            let version_number = reader.token().unwrap_or_default();
+            version = if version_number.starts_with("2.") {
+                Some(MshVersion::V2)
+            } else if version_number == "4.1" {
+                Some(MshVersion::V4)
+            } else {
+                return Err(invalid(format!("Unsupported MSH version: '{}'", version_number)));
+            };
+            let file_type: i64 = reader.parse_token("file type")?;
+            let data_size: usize = reader.parse_token("data size")?;
+            if file_type == 1 {
+                reader.skip_line();
+                // The integer one is written in binary to detect the byte order.
+                let one = reader.raw::<4>("byte order")?;
+                if i32::from_le_bytes(one) == 1 {
+                    reader.little_endian = true;
+                } else if i32::from_be_bytes(one) == 1 {
+                    reader.little_endian = false;
+                } else {
+                    return Err(invalid(String::from("Could not determine the byte order of MSH file.")));
+                }
+                reader.binary = true;
+                reader.size_t_bytes = data_size;
+            }
+            reader.expect_end(&name)?;
+            continue;
+        }
+
+        let version = version.ok_or_else(|| invalid(String::from("MSH file is missing '$MeshFormat'.")))?;
+        match name.as_str() {
+            "Entities" if version == MshVersion::V4 => {
+                if reader.binary {
+                    reader.skip_line();
+                }
+                read_entities(&mut reader, &mut contents)?;
+                reader.expect_end(&name)?;
+            }
+            "Nodes" => {
+                if reader.binary && version == MshVersion::V4 {
+                    reader.skip_line();
+                }
+                read_nodes(&mut reader, version, &mut contents)?;
+                reader.expect_end(&name)?;
+            }
+            "Elements" => {
+                if reader.binary && version == MshVersion::V4 {
+                    reader.skip_line();
+                }
+                read_elements(&mut reader, version, &mut contents)?;
+                reader.expect_end(&name)?;
+            }
+            _ => reader.skip_section(&name)?,
+        }
+    }
+
+    if version.is_none() {
+        return Err(invalid(format!("Not an MSH file: '{}'", filename)));
+    }
+    if contents.tetrahedra.is_empty() {
+        return Err(invalid(format!("No tetrahedral elements found in MSH file: '{}'", filename)));
+    }
+
+    let mut elements = Vec::with_capacity(contents.tetrahedra.len());
+    let mut submesh_indices = Vec::with_capacity(contents.tetrahedra.len());
+    for (tags, subdomain) in &contents.tetrahedra {
+        let mut element = [0usize; 4];
+        for (index, tag) in element.iter_mut().zip(tags.iter()) {
+            *index = *contents
+                .node_indices
+                .get(tag)
+                .ok_or_else(|| invalid(format!("Element references unknown node {}", tag)))?;
+        }
+        elements.push(element);
+        submesh_indices.push(*subdomain);
+    }
+
+    let label = Path::new(filename)
+        .file_stem()
+        .map_or(String::from("mesh"), |stem| stem.to_string_lossy().to_string());
+
+    Ok(Mesh::new(label, contents.vertices, elements, submesh_indices, vec![]))
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn gmsh_test_file(name: &str) -> String {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join("gmsh_two_tets")
+            .join(name)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn assert_two_tets(mesh: &Mesh) {
+        assert_eq!(mesh.vertices.len(), 5);
+        assert_eq!(mesh.vertices[1], [1.0, 0.0, 0.0]);
+        assert_eq!(mesh.vertices[4], [1.0, 1.0, 1.0]);
+
+        // The boundary triangle is dropped and the node tags (10, 20, ...) are renumbered.
+        assert_eq!(mesh.elements, vec![[0, 1, 2, 3], [1, 2, 3, 4]]);
+        assert_eq!(mesh.submesh_indices, vec![1, 2]);
+        assert!(mesh.fields.is_empty());
+    }
+
+    //..........................................................................................//
+    //. read_mesh_from_gmsh()                                                                  .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_read_mesh_from_gmsh_v22_ascii() {
+        let mesh = read_mesh_from_gmsh(&gmsh_test_file("two_tets_v22_ascii.msh")).unwrap();
+        assert_two_tets(&mesh);
+        assert_eq!(mesh.label, "two_tets_v22_ascii");
+    }
+
+    #[test]
+    fn test_read_mesh_from_gmsh_v22_binary() {
+        let mesh = read_mesh_from_gmsh(&gmsh_test_file("two_tets_v22_binary.msh")).unwrap();
+        assert_two_tets(&mesh);
+    }
+
+    #[test]
+    fn test_read_mesh_from_gmsh_v41_ascii() {
+        let mesh = read_mesh_from_gmsh(&gmsh_test_file("two_tets_v41_ascii.msh")).unwrap();
+        assert_two_tets(&mesh);
+    }
+
+    #[test]
+    fn test_read_mesh_from_gmsh_v41_binary() {
+        let mesh = read_mesh_from_gmsh(&gmsh_test_file("two_tets_v41_binary.msh")).unwrap();
+        assert_two_tets(&mesh);
+    }
+
+    #[test]
+    fn test_read_mesh_from_gmsh_unsupported_version() {
+        let file_name = env::temp_dir().join("tec2hdf5_test_gmsh_v30.msh");
+        fs::write(&file_name, "$MeshFormat\n3.0 0 8\n$EndMeshFormat\n").unwrap();
+
+        match read_mesh_from_gmsh(file_name.to_str().unwrap()) {
+            Err(error) => assert_eq!(error.kind(), io::ErrorKind::InvalidData),
+            Ok(_) => panic!("MSH version 3.0 should be rejected"),
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//
+// file: grain_ensemble.rs
+// author: L. Nagy
+//
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use crate::hysteresis_loops::AveragedMagneticLoopMeasurement;
+
+///
+/// How the averaged loops of different grain sizes are combined into an ensemble.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnsembleWeighting {
+    /// Every grain size is equally abundant, so each loop contributes in proportion to its total
+    /// moment Ms V.
+    Moment,
+
+    /// The grain sizes follow a lognormal distribution of the equivalent sphere diameter.
+    Lognormal {
+        /// The median diameter (m).
+        median: f64,
+        /// The standard deviation of the natural logarithm of the diameter.
+        sigma: f64,
+    },
+
+    /// User supplied number fractions, the relative abundance of each grain size.
+    User(Vec<f64>),
+}
+
+///
+/// One field step of an ensemble loop.
+///
+/// # Fields
+///
+/// * `b` - The applied field (T).
+/// * `m_normalized` - The ensemble magnetization as a fraction of its saturation value, M/Ms.
+/// * `m` - The mean moment per grain of the ensemble (Am^2).
+/// * `m_saturation` - The mean saturation moment Ms V per grain of the ensemble (Am^2).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnsembleLoopMeasurement {
+    pub b: f64,
+    pub m_normalized: f64,
+    pub m: f64,
+    pub m_saturation: f64,
+}
+
+///
+/// An ensemble loop built from the averaged loops of several grain sizes.
+///
+/// # Fields
+///
+/// * `diameters` - The equivalent sphere diameter (m) of each grain size.
+/// * `number_fractions` - The fraction of grains of each size, summing to one.
+/// * `moment_fractions` - The fraction of the ensemble's saturation moment carried by each size,
+///   summing to one.
+/// * `measurements` - The ensemble loop.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnsembleLoop {
+    pub diameters: Vec<f64>,
+    pub number_fractions: Vec<f64>,
+    pub moment_fractions: Vec<f64>,
+    pub measurements: Vec<EnsembleLoopMeasurement>,
+}
+
+///
+/// The diameter of a sphere with the given volume.
+///
+pub fn equivalent_diameter(volume: f64) -> f64 {
+    (6.0 * volume / std::f64::consts::PI).cbrt()
+}
+
+///
+/// Computes the number fractions of a set of grain sizes drawn from a lognormal distribution.
+/// Each size stands for a bin in ln(d) reaching half way to its neighbours (the outer bins are
+/// symmetric about their size), and its fraction is the lognormal density in ln(d) times the bin
+/// width.
+///
+/// # Arguments
+///
+/// * `diameters` - The grain diameters (m).
+/// * `median` - The median diameter (m).
+/// * `sigma` - The standard deviation of ln(d).
+///
+/// # Returns
+///
+/// One number fraction per diameter, summing to one.
+///
+pub fn lognormal_fractions(diameters: &[f64], median: f64, sigma: f64) -> Vec<f64> {
+    let n = diameters.len();
+    if n == 1 {
+        return vec![1.0];
+    }
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|a, b| diameters[*a].total_cmp(&diameters[*b]));
+    let logs: Vec<f64> = order.iter().map(|i| diameters[*i].ln()).collect();
+
+    let mut fractions = vec![0.0; n];
+    for (k, index) in order.iter().enumerate() {
+        let lower = if k > 0 { 0.5 * (logs[k] - logs[k - 1]) } else { 0.5 * (logs[1] - logs[0]) };
+        let upper = if k + 1 < n { 0.5 * (logs[k + 1] - logs[k]) } else { 0.5 * (logs[k] - logs[k - 1]) };
+        let z = (logs[k] - median.ln()) / sigma;
+        fractions[*index] = (-0.5 * z * z).exp() * (lower + upper);
+    }
+    let total: f64 = fractions.iter().sum();
+    fractions.iter().map(|f| f / total).collect()
+}
+
+///
+/// Combines the averaged loops of several grain sizes into an ensemble loop,
+///
+///   M(B) = sum_i n_i M_i(B),   Ms V = sum_i n_i Ms_i V_i,
+///
+/// where `n_i` is the number fraction of grain size `i` and `M_i` its (per grain) averaged moment.
+///
+/// # Arguments
+///
+/// * `loops` - The averaged loop of each grain size, all on the same field grid.
+/// * `weighting` - How the grain sizes are weighted.
+///
+/// # Returns
+///
+/// The `EnsembleLoop`.
+///
+/// # Errors
+/// Returns an error if there are no loops, the loops are not on the same field grid, a loop has
+/// no volume, or the weights do not hold one non-negative number fraction per loop.
+///
+pub fn ensemble_average(
+    loops: &[Vec<AveragedMagneticLoopMeasurement>],
+    weighting: &EnsembleWeighting,
+) -> io::Result<EnsembleLoop> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    if loops.is_empty() || loops[0].is_empty() {
+        return Err(invalid(String::from("No loops to combine.")));
+    }
+
+    // All loops must share the field grid.
+    let first = &loops[0];
+    let field_scale = first.iter().map(|value| value.b.abs()).fold(0.0, f64::max).max(f64::MIN_POSITIVE);
+    for (index, average_loop) in loops.iter().enumerate() {
+        if average_loop.len() != first.len() {
+            return Err(invalid(format!(
+                "Loop {} has {} field steps, expected {}.", index + 1, average_loop.len(), first.len()
+            )));
+        }
+        if average_loop.iter().zip(first.iter()).any(|(a, b)| (a.b - b.b).abs() > 1e-9 * field_scale) {
+            return Err(invalid(format!("Loop {} is not on the same field grid as loop 1.", index + 1)));
+        }
+        if average_loop[0].vol <= 0.0 {
+            return Err(invalid(format!("Loop {} has no volume.", index + 1)));
+        }
+    }
+
+    let diameters: Vec<f64> = loops.iter().map(|average_loop| equivalent_diameter(average_loop[0].vol)).collect();
+    let number_fractions = match weighting {
+        EnsembleWeighting::Moment => vec![1.0; loops.len()],
+        EnsembleWeighting::Lognormal { median, sigma } => {
+            if *median <= 0.0 || *sigma <= 0.0 {
+                return Err(invalid(String::from("The lognormal median and sigma must be positive.")));
+            }
+            lognormal_fractions(&diameters, *median, *sigma)
+        }
+        EnsembleWeighting::User(fractions) => {
+            if fractions.len() != loops.len() {
+                return Err(invalid(format!(
+                    "Expected {} number fractions, found {}.", loops.len(), fractions.len()
+                )));
+            }
+            if fractions.iter().any(|f| *f < 0.0 || !f.is_finite()) {
+                return Err(invalid(String::from("Number fractions must be non-negative.")));
+            }
+            fractions.clone()
+        }
+    };
+    let total: f64 = number_fractions.iter().sum();
+    if total.is_nan() || total <= 0.0 {
+        return Err(invalid(String::from("Number fractions sum to zero.")));
+    }
+    let number_fractions: Vec<f64> = number_fractions.iter().map(|n| n / total).collect();
+
+    let saturation_moments: Vec<f64> = loops.iter().map(|average_loop| average_loop[0].ms * average_loop[0].vol).collect();
+    let m_saturation: f64 = number_fractions.iter().zip(saturation_moments.iter()).map(|(n, m)| n * m).sum();
+    let moment_fractions = number_fractions
+        .iter()
+        .zip(saturation_moments.iter())
+        .map(|(n, m)| if m_saturation > 0.0 { n * m / m_saturation } else { 0.0 })
+        .collect();
+
+    let measurements = (0..first.len())
+        .map(|step| {
+            let m: f64 = loops.iter().zip(number_fractions.iter()).map(|(average_loop, n)| n * average_loop[step].m).sum();
+            EnsembleLoopMeasurement {
+                b: first[step].b,
+                m_normalized: if m_saturation > 0.0 { m / m_saturation } else { 0.0 },
+                m,
+                m_saturation,
+            }
+        })
+        .collect();
+
+    Ok(EnsembleLoop { diameters, number_fractions, moment_fractions, measurements })
+}
+
+///
+/// Writes an ensemble loop as comma separated values with the header `B,M_Ms,M,MsV`, preceded
+/// by `#` comment lines describing each grain size.
+///
+/// # Arguments
+///
+/// * `file_name` - The name of the file to create.
+/// * `ensemble` - The ensemble loop.
+/// * `source_files` - The averaged loop file of each grain size.
+///
+/// # Errors
+/// Returns an error if the file cannot be created or written.
+///
+pub fn write_ensemble_loop_to_csv(file_name: &str, ensemble: &EnsembleLoop, source_files: &[String]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(file_name)?);
+    writeln!(writer, "# grain sizes: {}", ensemble.diameters.len())?;
+    for (index, source_file) in source_files.iter().enumerate() {
+        writeln!(
+            writer,
+            "# source file: {}, diameter (m): {:E}, number fraction: {:E}, moment fraction: {:E}",
+            source_file, ensemble.diameters[index], ensemble.number_fractions[index], ensemble.moment_fractions[index]
+        )?;
+    }
+    writeln!(writer, "B,M_Ms,M,MsV")?;
+    for value in &ensemble.measurements {
+        writeln!(writer, "{:E},{:E},{:E},{:E}", value.b, value.m_normalized, value.m, value.m_saturation)?;
+    }
+    writer.flush()
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    fn grain_loop(volume: f64, scale: f64) -> Vec<AveragedMagneticLoopMeasurement> {
+        // A loop of a grain with the given volume whose normalised moment is `scale * b`.
+        let ms = 480e3;
+        [0.1, 0.0, -0.1]
+            .iter()
+            .map(|b| AveragedMagneticLoopMeasurement { b: *b, m: scale * b * ms * volume, ms, vol: volume })
+            .collect()
+    }
+
+    //..........................................................................................//
+    //. lognormal_fractions()                                                                  .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_lognormal_fractions() {
+        // Sizes evenly spaced in ln(d) and symmetric about the median.
+        let median: f64 = 100e-9;
+        let diameters: Vec<f64> = [-2.0, -1.0, 0.0, 1.0, 2.0].iter().map(|k: &f64| median * (0.2 * k).exp()).collect();
+        let fractions = lognormal_fractions(&diameters, median, 0.2);
+
+        assert!((fractions.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!((fractions[0] - fractions[4]).abs() < 1e-12);
+        assert!((fractions[1] - fractions[3]).abs() < 1e-12);
+        assert!((fractions[2] / fractions[1] - 0.5_f64.exp()).abs() < 1e-12);
+        assert_eq!(lognormal_fractions(&[50e-9], median, 0.2), vec![1.0]);
+    }
+
+    //..........................................................................................//
+    //. ensemble_average()                                                                     .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_ensemble_average() {
+        let small = grain_loop(1e-24, 2.0);
+        let large = grain_loop(8e-24, 5.0);
+        let loops = vec![small, large];
+
+        // With equal numbers the large grains carry 8/9 of the moment.
+        let ensemble = ensemble_average(&loops, &EnsembleWeighting::Moment).unwrap();
+        assert_eq!(ensemble.number_fractions, vec![0.5, 0.5]);
+        assert!((ensemble.moment_fractions[1] - 8.0 / 9.0).abs() < 1e-12);
+        assert!((ensemble.diameters[1] / ensemble.diameters[0] - 2.0).abs() < 1e-12);
+        let expected = 0.1 * (2.0 + 8.0 * 5.0) / 9.0;
+        assert!((ensemble.measurements[0].m_normalized - expected).abs() < 1e-12);
+        assert!((ensemble.measurements[0].m_saturation - 0.5 * 480e3 * 9e-24).abs() < 1e-30);
+        assert!((ensemble.measurements[0].m - expected * ensemble.measurements[0].m_saturation).abs() < 1e-30);
+
+        // Number fractions of 8 small grains to one large one share the moment equally.
+        let ensemble = ensemble_average(&loops, &EnsembleWeighting::User(vec![8.0, 1.0])).unwrap();
+        assert!((ensemble.moment_fractions[0] - 0.5).abs() < 1e-12);
+        assert!((ensemble.measurements[2].m_normalized + 0.1 * 3.5).abs() < 1e-12);
+
+        // A lognormal centred on the large grains favours them.
+        let median = ensemble.diameters[1];
+        let ensemble = ensemble_average(&loops, &EnsembleWeighting::Lognormal { median, sigma: 0.3 }).unwrap();
+        assert!(ensemble.number_fractions[1] > ensemble.number_fractions[0]);
+
+        assert!(ensemble_average(&loops, &EnsembleWeighting::User(vec![1.0])).is_err());
+        let mut shifted = grain_loop(1e-24, 2.0);
+        shifted[1].b = 0.05;
+        assert!(ensemble_average(&[shifted, grain_loop(1e-24, 2.0)], &EnsembleWeighting::Moment).is_err());
+    }
+
+    //..........................................................................................//
+    //. write_ensemble_loop_to_csv()                                                           .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_write_ensemble_loop_to_csv() {
+        let loops = vec![grain_loop(1e-24, 2.0), grain_loop(8e-24, 5.0)];
+        let ensemble = ensemble_average(&loops, &EnsembleWeighting::Moment).unwrap();
+        let file_name = env::temp_dir().join("tec2hdf5_test_ensemble.csv");
+        let file_name = file_name.to_str().unwrap();
+        let source_files = vec![String::from("small.loop"), String::from("large.loop")];
+        write_ensemble_loop_to_csv(file_name, &ensemble, &source_files).unwrap();
+
+        let contents = fs::read_to_string(file_name).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[0], "# grain sizes: 2");
+        assert!(lines[2].starts_with("# source file: large.loop, diameter (m): "));
+        assert_eq!(lines[3], "B,M_Ms,M,MsV");
+        assert_eq!(lines.len(), 4 + 3);
+        assert!(lines[5].starts_with("0E0,0E0,0E0,"));
+    }
+}
@@ -2,17 +2,27 @@ use crate::mesh::{
     Mesh,
     Field
 };
+use crate::hysteresis_loops::{
+    AveragedMagneticLoopMeasurement,
+    LoopMetadata
+};
+use crate::forc::{
+    to_bc_bu,
+    ForcDistribution
+};
 
 use hdf5::{
     File,
     Result,
-    types::FixedAscii
+    types::{FixedAscii, VarLenUnicode}
 };
 
 use ndarray::{
     Array2,
 };
 
+use std::path::Path;
+
 /// Writes a `Mesh` object to an HDF5 file.
 ///
 /// The function saves the mesh's vertices, elements, submesh indices, and fields data to the
@@ -71,7 +81,11 @@ use ndarray::{
 ///     submesh_indices: vec![1],
 ///     fields,
 ///     volume: None,
-///     net_moments: None
+///     net_moments: None,
+///     spatial_index: None,
+///     surface_area: None,
+///     charges: None,
+///     helicities: None
 /// };
 ///
 /// write_mesh_to_hdf5(&mesh, "test.h5").unwrap();
@@ -143,14 +157,202 @@ pub fn write_mesh_to_hdf5(mesh: &Mesh, filename: &str) -> Result<()> {
     Ok(())
 }
 
+/// Reads a `Mesh` object from an HDF5 file written by `write_mesh_to_hdf5`.
+///
+/// The mesh is labelled with the file name (without its extension) and holds one field per entry
+/// in `/fields/labels`, read from `/fields/field{}/vectors`.
+///
+/// # Parameters
+/// - `filename`: The name of the HDF5 file to read.
+///
+/// # Returns
+/// - `Result<Mesh>`: The mesh along with its fields, or an error if the file could not be read.
+///
+/// # Errors
+/// This function will return an error if:
+/// - The HDF5 file cannot be opened.
+/// - Any of the `/mesh` datasets is missing or has the wrong shape.
+/// - A field listed in `/fields/labels` is missing or does not hold one vector per vertex.
+///
 pub fn read_mesh_from_hdf5(filename: &str) -> Result<Mesh> {
+    const MAX_STR_LEN: usize = 64;
+    let file = File::open(filename)?;
+
+    let vertices: Vec<[f64; 3]> = file
+        .dataset("/mesh/vertices")?
+        .read_2d::<f64>()?
+        .rows()
+        .into_iter()
+        .map(|row| {
+            if row.len() == 3 {
+                Ok([row[0], row[1], row[2]])
+            } else {
+                Err(hdf5::Error::from("'/mesh/vertices' must have three columns"))
+            }
+        })
+        .collect::<Result<_>>()?;
+
+    let elements: Vec<[usize; 4]> = file
+        .dataset("/mesh/elements")?
+        .read_2d::<usize>()?
+        .rows()
+        .into_iter()
+        .map(|row| {
+            if row.len() == 4 {
+                Ok([row[0], row[1], row[2], row[3]])
+            } else {
+                Err(hdf5::Error::from("'/mesh/elements' must have four columns"))
+            }
+        })
+        .collect::<Result<_>>()?;
+
+    let submesh_indices = file.dataset("/mesh/submesh")?.read_raw::<usize>()?;
+    if submesh_indices.len() != elements.len() {
+        return Err(hdf5::Error::from("'/mesh/submesh' must hold one index per element"));
+    }
+
+    let labels: Vec<FixedAscii<MAX_STR_LEN>> = if file.link_exists("/fields/labels") {
+        file.dataset("/fields/labels")?.read_raw::<FixedAscii<MAX_STR_LEN>>()?
+    } else {
+        vec![]
+    };
+
+    let mut fields = Vec::with_capacity(labels.len());
+    for (field_index, label) in labels.iter().enumerate() {
+        let field_name = format!("/fields/field{}/vectors", field_index);
+        let field_data = file.dataset(field_name.as_str())?.read_2d::<f64>()?;
+        if field_data.shape() != [vertices.len(), 3] {
+            return Err(hdf5::Error::from(format!("'{}' must hold one vector per vertex", field_name)));
+        }
+        fields.push(Field {
+            label: label.as_str().to_string(),
+            vectors: field_data.rows().into_iter().map(|row| [row[0], row[1], row[2]]).collect(),
+        });
+    }
+
+    let label = Path::new(filename)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(filename)
+        .to_string();
+
+    Ok(Mesh::new(label, vertices, elements, submesh_indices, fields))
+}
+
+/// Writes an averaged hysteresis loop, along with its metadata, to an HDF5 file.
+///
+/// The data is organised as follows:
+/// - `/loop/b`, `/loop/m`, `/loop/ms`, `/loop/volume`: One `f64` dataset per column.
+/// - `/metadata/source_files`: The averaged loop files as variable length strings.
+/// - `/metadata/no_of_hysteresis_loops`, `/metadata/field_start`, `/metadata/field_end` and
+///   `/metadata/field_step`: Scalar datasets.
+///
+/// # Parameters
+/// - `filename`: The name of the HDF5 file to create.
+/// - `average_loop`: The averaged loop.
+/// - `metadata`: The metadata describing the averaged loops.
+///
+/// # Returns
+/// - `Result<()>`: Returns `Ok(())` on success or an error if the file could not be written.
+///
+pub fn write_averaged_loop_to_hdf5(
+    filename: &str,
+    average_loop: &[AveragedMagneticLoopMeasurement],
+    metadata: &LoopMetadata,
+) -> Result<()> {
+    let file = File::create(filename)?;
+
+    let columns: [(&str, Vec<f64>); 4] = [
+        ("/loop/b", average_loop.iter().map(|value| value.b).collect()),
+        ("/loop/m", average_loop.iter().map(|value| value.m).collect()),
+        ("/loop/ms", average_loop.iter().map(|value| value.ms).collect()),
+        ("/loop/volume", average_loop.iter().map(|value| value.vol).collect()),
+    ];
+    for (name, values) in &columns {
+        file.new_dataset::<f64>()
+            .shape(values.len())
+            .create(*name)?
+            .write(values)?;
+    }
+
+    let source_files: Vec<VarLenUnicode> = metadata
+        .source_files
+        .iter()
+        .map(|source_file| source_file.parse::<VarLenUnicode>().unwrap())
+        .collect();
+    file.new_dataset::<VarLenUnicode>()
+        .shape(source_files.len())
+        .create("/metadata/source_files")?
+        .write(&source_files)?;
+
+    file.new_dataset::<usize>()
+        .create("/metadata/no_of_hysteresis_loops")?
+        .write_scalar(&metadata.no_of_hysteresis_loops)?;
+    for (name, value) in [
+        ("/metadata/field_start", metadata.field_start),
+        ("/metadata/field_end", metadata.field_end),
+        ("/metadata/field_step", metadata.field_step),
+    ] {
+        file.new_dataset::<f64>()
+            .create(name)?
+            .write_scalar(&value)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a FORC distribution to an HDF5 file.
+///
+/// The data is organised as follows:
+/// - `/forc/ba`, `/forc/br`: The applied and reversal fields of the grid.
+/// - `/forc/rho`: The distribution as a (`br`, `ba`) grid, NaN where it is undefined.
+/// - `/forc/bc`, `/forc/bu`: The coercivity and interaction field of every grid node, with the
+///   shape of `/forc/rho`; these are the (`br`, `ba`) grid rotated by 45 degrees, not a regular
+///   (Bc, Bu) grid.
+/// - `/metadata/smoothing_factor`: A scalar dataset.
+///
+/// # Parameters
+/// - `filename`: The name of the HDF5 file to create.
+/// - `distribution`: The FORC distribution.
+///
+/// # Returns
+/// - `Result<()>`: Returns `Ok(())` on success or an error if the file could not be written.
+///
+pub fn write_forc_distribution_to_hdf5(filename: &str, distribution: &ForcDistribution) -> Result<()> {
+    let file = File::create(filename)?;
+    let shape = (distribution.br.len(), distribution.ba.len());
+
+    file.new_dataset::<f64>()
+        .shape(distribution.ba.len())
+        .create("/forc/ba")?
+        .write(&distribution.ba)?;
+    file.new_dataset::<f64>()
+        .shape(distribution.br.len())
+        .create("/forc/br")?
+        .write(&distribution.br)?;
 
+    let rho: Array2<f64> = Array2::from_shape_vec(shape, distribution.rho.iter().flatten().copied().collect())?;
+    let bc: Array2<f64> = Array2::from_shape_fn(shape, |(k, j)| to_bc_bu(distribution.ba[j], distribution.br[k]).0);
+    let bu: Array2<f64> = Array2::from_shape_fn(shape, |(k, j)| to_bc_bu(distribution.ba[j], distribution.br[k]).1);
+    for (name, values) in [("/forc/rho", &rho), ("/forc/bc", &bc), ("/forc/bu", &bu)] {
+        file.new_dataset::<f64>()
+            .shape(shape)
+            .create(name)?
+            .write(values)?;
+    }
+
+    file.new_dataset::<usize>()
+        .create("/metadata/smoothing_factor")?
+        .write_scalar(&distribution.smoothing_factor)?;
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use std::env;
 
     //......................................................................................//
     //. write_mesh_to_hdf5()                                                               .//
@@ -192,7 +394,11 @@ mod tests {
             submesh_indices: vec![1],
             fields,
             volume: None,
-            net_moments: None
+            net_moments: None,
+            spatial_index: None,
+            surface_area: None,
+            charges: None,
+            helicities: None
         };
 
         write_mesh_to_hdf5(&mesh, "test.h5").unwrap();
@@ -200,4 +406,105 @@ mod tests {
         assert!(true);
     }
 
+    //......................................................................................//
+    //. read_mesh_from_hdf5()                                                              .//
+    //......................................................................................//
+
+    #[test]
+    fn test_read_mesh_from_hdf5() {
+        let file_name = env::temp_dir().join("tec2hdf5_test_read.h5");
+        let file_name = file_name.to_str().unwrap();
+
+        let mesh = Mesh::new(
+            String::from("My mesh"),
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            vec![[0, 1, 2, 3]],
+            vec![2],
+            vec![Field {
+                label: String::from("\"400.0000 mT\""),
+                vectors: vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0], [10.0, 11.0, 12.0]],
+            }],
+        );
+        write_mesh_to_hdf5(&mesh, file_name).unwrap();
+
+        let read = read_mesh_from_hdf5(file_name).unwrap();
+        assert_eq!(read.label, "tec2hdf5_test_read");
+        assert_eq!(read.vertices, mesh.vertices);
+        assert_eq!(read.elements, mesh.elements);
+        assert_eq!(read.submesh_indices, mesh.submesh_indices);
+        assert_eq!(read.fields.len(), 1);
+        assert_eq!(read.fields[0].label, "\"400.0000 mT\"");
+        assert_eq!(read.fields[0].vectors, mesh.fields[0].vectors);
+    }
+
+    //......................................................................................//
+    //. write_averaged_loop_to_hdf5()                                                      .//
+    //......................................................................................//
+
+    #[test]
+    fn test_write_averaged_loop_to_hdf5() {
+        let file_name = env::temp_dir().join("tec2hdf5_test_average.h5");
+        let file_name = file_name.to_str().unwrap();
+
+        let average_loop: Vec<AveragedMagneticLoopMeasurement> = [0.2, 0.1, 0.0]
+            .iter()
+            .map(|b| AveragedMagneticLoopMeasurement { b: *b, m: 2.0 * b, ms: 4.8e5, vol: 1e-21 })
+            .collect();
+        let metadata = LoopMetadata {
+            source_files: vec![String::from("loop1.loop"), String::from("loop2.loop")],
+            no_of_hysteresis_loops: 2,
+            field_start: 0.2,
+            field_end: 0.0,
+            field_step: -0.1,
+        };
+        write_averaged_loop_to_hdf5(file_name, &average_loop, &metadata).unwrap();
+
+        let file = File::open(file_name).unwrap();
+        for name in ["/loop/b", "/loop/m", "/loop/ms", "/loop/volume"] {
+            assert_eq!(file.dataset(name).unwrap().shape(), vec![average_loop.len()]);
+        }
+        assert_eq!(file.dataset("/loop/b").unwrap().read_raw::<f64>().unwrap(), vec![0.2, 0.1, 0.0]);
+        assert_eq!(file.dataset("/loop/m").unwrap().read_raw::<f64>().unwrap(), vec![0.4, 0.2, 0.0]);
+
+        let source_files = file.dataset("/metadata/source_files").unwrap().read_raw::<VarLenUnicode>().unwrap();
+        let source_files: Vec<&str> = source_files.iter().map(|name| name.as_str()).collect();
+        assert_eq!(source_files, vec!["loop1.loop", "loop2.loop"]);
+        assert_eq!(file.dataset("/metadata/no_of_hysteresis_loops").unwrap().read_scalar::<usize>().unwrap(), 2);
+        assert_eq!(file.dataset("/metadata/field_step").unwrap().read_scalar::<f64>().unwrap(), -0.1);
+    }
+
+    //......................................................................................//
+    //. write_forc_distribution_to_hdf5()                                                  .//
+    //......................................................................................//
+
+    #[test]
+    fn test_write_forc_distribution_to_hdf5() {
+        let file_name = env::temp_dir().join("tec2hdf5_test_forc.h5");
+        let file_name = file_name.to_str().unwrap();
+
+        let distribution = ForcDistribution {
+            smoothing_factor: 3,
+            ba: vec![-0.5, 0.0, 0.5],
+            br: vec![-0.5, 0.0],
+            rho: vec![vec![1.0, 2.0, 3.0], vec![f64::NAN, 4.0, 5.0]],
+        };
+        write_forc_distribution_to_hdf5(file_name, &distribution).unwrap();
+
+        let file = File::open(file_name).unwrap();
+        assert_eq!(file.dataset("/forc/ba").unwrap().read_raw::<f64>().unwrap(), distribution.ba);
+        assert_eq!(file.dataset("/forc/br").unwrap().read_raw::<f64>().unwrap(), distribution.br);
+        for name in ["/forc/rho", "/forc/bc", "/forc/bu"] {
+            assert_eq!(file.dataset(name).unwrap().shape(), vec![distribution.br.len(), distribution.ba.len()]);
+        }
+
+        let rho = file.dataset("/forc/rho").unwrap().read_2d::<f64>().unwrap();
+        assert_eq!(rho[[0, 2]], 3.0);
+        assert!(rho[[1, 0]].is_nan());
+        assert_eq!(rho[[1, 2]], 5.0);
+        let bc = file.dataset("/forc/bc").unwrap().read_2d::<f64>().unwrap();
+        let bu = file.dataset("/forc/bu").unwrap().read_2d::<f64>().unwrap();
+        assert_eq!((bc[[0, 2]], bu[[0, 2]]), to_bc_bu(0.5, -0.5));
+        assert_eq!(file.dataset("/metadata/smoothing_factor").unwrap().read_scalar::<usize>().unwrap(), 3);
+    }
+
 }
\ No newline at end of file
@@ -1,5 +1,9 @@
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+
+use crate::linalg::{dot, norm, symmetric_eigen};
 
 #[derive(Debug, Clone)]
 pub struct MagneticLoopMeasurement {
@@ -22,8 +26,279 @@ pub struct AveragedMagneticLoopMeasurement {
     pub vol: f64,
 }
 
+///
+/// Statistics of a quantity over the loops of a stack at one field step.
+///
+/// # Fields
+///
+/// * `mean` - The mean.
+/// * `std_dev` - The (sample) standard deviation, zero for a single loop.
+/// * `std_error` - The standard error of the mean.
+/// * `min` - The smallest value.
+/// * `max` - The largest value.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepStatistics {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub std_error: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl StepStatistics {
+    ///
+    /// Computes the statistics of a set of samples.
+    ///
+    pub fn from_samples(samples: &[f64]) -> StepStatistics {
+        StepStatistics::from_weighted_samples(samples, &vec![1.0; samples.len()])
+    }
+
+    ///
+    /// Computes the statistics of a set of weighted samples. The standard deviation is that of
+    /// reliability weights, `sqrt(sum w (x - mean)^2 / (V1 - V2 / V1))` with `V1 = sum w` and
+    /// `V2 = sum w^2`, and the standard error uses the effective number of samples `V1^2 / V2`;
+    /// for equal weights these are the usual sample standard deviation and standard error.
+    ///
+    pub fn from_weighted_samples(samples: &[f64], weights: &[f64]) -> StepStatistics {
+        let v1: f64 = weights.iter().sum();
+        let v2: f64 = weights.iter().map(|w| w * w).sum();
+        if samples.is_empty() || v1 <= 0.0 {
+            return StepStatistics { mean: 0.0, std_dev: 0.0, std_error: 0.0, min: 0.0, max: 0.0 };
+        }
+        let mean = samples.iter().zip(weights.iter()).map(|(x, w)| w * x).sum::<f64>() / v1;
+        let denominator = v1 - v2 / v1;
+        let std_dev = if denominator > 1e-12 * v1 {
+            let squares: f64 = samples.iter().zip(weights.iter()).map(|(x, w)| w * (x - mean).powi(2)).sum();
+            (squares / denominator).sqrt()
+        } else {
+            0.0
+        };
+        StepStatistics {
+            mean,
+            std_dev,
+            std_error: std_dev * v2.sqrt() / v1,
+            min: samples.iter().copied().fold(f64::INFINITY, f64::min),
+            max: samples.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+///
+/// The vector average of a stack of loops at one field step, along with statistics over the
+/// loops.
+///
+/// # Fields
+///
+/// * `b` - The mean applied field (T).
+/// * `mx`, `my`, `mz` - The components of the moment (Am^2).
+/// * `m_parallel` - The moment along each loop's field direction, `B^ . M` (Am^2).
+/// * `m_perpendicular` - The size of the moment perpendicular to each loop's field direction,
+///   the transverse moment `|M - (B^ . M) B^|` (Am^2).
+/// * `ms` - The mean saturation magnetization (A/m).
+/// * `vol` - The mean volume (m^3).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorAveragedMagneticLoopMeasurement {
+    pub b: f64,
+    pub mx: StepStatistics,
+    pub my: StepStatistics,
+    pub mz: StepStatistics,
+    pub m_parallel: StepStatistics,
+    pub m_perpendicular: StepStatistics,
+    pub ms: f64,
+    pub vol: f64,
+}
+
+///
+/// The unit field direction of a measurement, zero if the direction is zero.
+///
+fn unit_field_direction(measurement: &MagneticLoopMeasurement) -> [f64; 3] {
+    let direction = [measurement.bx, measurement.by, measurement.bz];
+    let length = norm(direction);
+    if length > 0.0 { direction.map(|d| d / length) } else { [0.0; 3] }
+}
+
+///
+/// The moment of a measurement split into its components parallel and perpendicular to the
+/// field direction.
+///
+fn parallel_perpendicular(measurement: &MagneticLoopMeasurement) -> (f64, f64) {
+    let moment = [measurement.mx, measurement.my, measurement.mz];
+    let unit = unit_field_direction(measurement);
+    let parallel = dot(unit, moment);
+    let perpendicular = [0, 1, 2].map(|i| moment[i] - parallel * unit[i]);
+    (parallel, norm(perpendicular))
+}
+
+impl MagneticLoopMeasurement {
+    ///
+    /// The moment along the field direction, `B^ . M` (Am^2).
+    ///
+    pub fn parallel_moment(&self) -> f64 {
+        parallel_perpendicular(self).0
+    }
+}
+
+///
+/// How the loops of a stack are weighted when averaged.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoopWeighting {
+    /// Every loop has the same weight.
+    Uniform,
+
+    /// Each loop is weighted by the solid angle of its field axis' Voronoi cell on the unit
+    /// sphere, i.e. the fraction of all directions that are closer to its axis than to any other
+    /// (see `solid_angle_weights`).
+    SolidAngle,
+
+    /// User supplied weights, one per loop.
+    User(Vec<f64>),
+}
+
+///
+/// How well a set of field directions covers the unit sphere. A loop along `d` is the same loop
+/// as one along `-d`, since the field sweeps from `+B` to `-B`, so the directions are treated as
+/// axes.
+///
+/// # Fields
+///
+/// * `no_of_directions` - The number of directions.
+/// * `max_gap` - The largest angle (in degrees, at most 90) from any direction on the sphere to
+///   the closest field axis; small for a set that covers the sphere well.
+/// * `weight_ratio` - The ratio of the largest to the smallest solid angle weight, one for an
+///   evenly spread set.
+/// * `anisotropy` - The difference between the largest and the smallest eigenvalue of the
+///   orientation tensor `<d d^T>`, zero for an isotropic set and one for a single axis.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectionCoverage {
+    pub no_of_directions: usize,
+    pub max_gap: f64,
+    pub weight_ratio: f64,
+    pub anisotropy: f64,
+}
+
+///
+/// The number of points used to sample the unit sphere when computing solid angles.
+///
+const SPHERE_SAMPLES: usize = 20000;
+
+///
+/// Nearly uniformly distributed points on the unit sphere (a Fibonacci lattice).
+///
+fn fibonacci_sphere(n: usize) -> Vec<[f64; 3]> {
+    let golden_angle = std::f64::consts::PI * (3.0 - 5.0_f64.sqrt());
+    (0..n)
+        .map(|i| {
+            let z = 1.0 - (2.0 * i as f64 + 1.0) / n as f64;
+            let r = (1.0 - z * z).sqrt();
+            let phi = golden_angle * i as f64;
+            [r * phi.cos(), r * phi.sin(), z]
+        })
+        .collect()
+}
+
+///
+/// The index of the direction whose axis is closest to a point on the sphere, along with the
+/// (absolute) cosine of the angle between them.
+///
+fn closest_direction(directions: &[[f64; 3]], point: [f64; 3]) -> (usize, f64) {
+    directions
+        .iter()
+        .enumerate()
+        .map(|(index, d)| (index, dot(*d, point).abs()))
+        .fold((0, f64::NEG_INFINITY), |best, candidate| if candidate.1 > best.1 { candidate } else { best })
+}
+
+///
+/// Computes the solid angle weight of each direction, the area of the (spherical) Voronoi cell
+/// of its axis as a fraction of the whole sphere, estimated by sampling the sphere. The
+/// directions are axes, `d` and `-d` are the same loop, so a set sampled on a hemisphere covers
+/// the sphere. Repeated axes share their cell equally.
+///
+/// # Arguments
+///
+/// * `directions` - Unit directions.
+///
+/// # Returns
+///
+/// One weight per direction, summing to one.
+///
+pub fn solid_angle_weights(directions: &[[f64; 3]]) -> Vec<f64> {
+    let mut counts = vec![0usize; directions.len()];
+    if directions.is_empty() {
+        return vec![];
+    }
+    for point in fibonacci_sphere(SPHERE_SAMPLES) {
+        counts[closest_direction(directions, point).0] += 1;
+    }
+
+    // Repeated axes share their cell, which is counted against the first of them.
+    let same = |a: &[f64; 3], b: &[f64; 3]| dot(*a, *b).abs() > 1.0 - 1e-12;
+    directions
+        .iter()
+        .map(|direction| {
+            let first = directions.iter().position(|other| same(direction, other)).unwrap();
+            let repeats = directions.iter().filter(|other| same(direction, other)).count();
+            counts[first] as f64 / (repeats * SPHERE_SAMPLES) as f64
+        })
+        .collect()
+}
+
+///
+/// Checks how well a set of field directions covers the unit sphere.
+///
+/// # Arguments
+///
+/// * `directions` - Unit directions.
+///
+/// # Returns
+///
+/// The `DirectionCoverage` of the directions.
+///
+pub fn direction_coverage(directions: &[[f64; 3]]) -> DirectionCoverage {
+    let n = directions.len();
+    if n == 0 {
+        return DirectionCoverage {
+            no_of_directions: 0,
+            max_gap: 90.0,
+            weight_ratio: f64::INFINITY,
+            anisotropy: 0.0,
+        };
+    }
+
+    let max_gap = fibonacci_sphere(SPHERE_SAMPLES)
+        .into_iter()
+        .map(|point| closest_direction(directions, point).1.clamp(-1.0, 1.0).acos().to_degrees())
+        .fold(0.0, f64::max);
+
+    let weights = solid_angle_weights(directions);
+    let largest = weights.iter().copied().fold(0.0, f64::max);
+    let smallest = weights.iter().copied().fold(f64::INFINITY, f64::min);
+
+    let mut tensor = [[0.0; 3]; 3];
+    for d in directions {
+        for i in 0..3 {
+            for j in 0..3 {
+                tensor[i][j] += d[i] * d[j] / n as f64;
+            }
+        }
+    }
+    let (eigenvalues, _) = symmetric_eigen(tensor);
+
+    DirectionCoverage {
+        no_of_directions: n,
+        max_gap,
+        weight_ratio: if smallest > 0.0 { largest / smallest } else { f64::INFINITY },
+        anisotropy: eigenvalues[2] - eigenvalues[0],
+    }
+}
+
 #[derive(Debug)]
 pub struct MagneticLoopStack {
+    pub source_files: Vec<String>,
     pub hysteresis_loops: Vec<Vec<MagneticLoopMeasurement>>,
     pub no_of_hysteresis_loops: usize,
     pub steps_per_hysteresis_loop: usize,
@@ -32,7 +307,386 @@ pub struct MagneticLoopStack {
     pub field_step: f64
 }
 
+///
+/// The provenance of an averaged loop, written alongside it.
+///
+/// # Fields
+///
+/// * `source_files` - The loop files that were averaged.
+/// * `no_of_hysteresis_loops` - The number of loops that were averaged.
+/// * `field_start` - The first applied field (T).
+/// * `field_end` - The last applied field (T).
+/// * `field_step` - The applied field step (T) of the first branch.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoopMetadata {
+    pub source_files: Vec<String>,
+    pub no_of_hysteresis_loops: usize,
+    pub field_start: f64,
+    pub field_end: f64,
+    pub field_step: f64,
+}
+
+///
+/// The file formats in which an averaged loop can be written.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopFileFormat {
+    /// The comma separated, fixed width nine column layout of MERRILL's `.loop` files.
+    Loop,
+
+    /// Plain comma separated values.
+    Csv,
+
+    /// A JSON object holding the metadata and one array per column.
+    Json,
+
+    /// An HDF5 file (see `hdf5_io::write_averaged_loop_to_hdf5`).
+    Hdf5,
+}
+
+impl LoopFileFormat {
+    ///
+    /// Parses a format name, one of `loop`, `csv`, `json` or `hdf5` (or `h5`).
+    ///
+    pub fn from_name(name: &str) -> Option<LoopFileFormat> {
+        match name.to_lowercase().as_str() {
+            "loop" => Some(LoopFileFormat::Loop),
+            "csv" => Some(LoopFileFormat::Csv),
+            "json" => Some(LoopFileFormat::Json),
+            "hdf5" | "h5" => Some(LoopFileFormat::Hdf5),
+            _ => None,
+        }
+    }
+
+    ///
+    /// Chooses a format from a file's extension, anything that is not `.csv`, `.json`, `.h5` or
+    /// `.hdf5` is written as a `.loop` file.
+    ///
+    pub fn from_file_name(file_name: &str) -> LoopFileFormat {
+        Path::new(file_name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(LoopFileFormat::from_name)
+            .unwrap_or(LoopFileFormat::Loop)
+    }
+}
+
+///
+/// How loops are interpolated when they are resampled onto a common field grid.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopInterpolation {
+    /// Piecewise linear interpolation.
+    Linear,
+
+    /// Monotone (Fritsch-Carlson) piecewise cubic Hermite interpolation, which does not
+    /// overshoot between measurements.
+    MonotoneCubic,
+}
+
+///
+/// A summary of resampling a stack of loops onto a common field grid.
+///
+/// # Fields
+///
+/// * `no_of_branches` - The number of branches (runs of monotonically decreasing or increasing
+///   field) of the common grid.
+/// * `clipped_loops` - The number of loops that extend beyond the common grid, either past the
+///   overlapping field range of a branch or by additional branches, and were clipped.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResampleReport {
+    pub no_of_branches: usize,
+    pub clipped_loops: usize,
+}
+
+///
+/// Splits a sequence of fields into branches of strictly decreasing or increasing field, given
+/// as indices into the sequence; the turning point belongs to both of its branches and repeated
+/// field values are dropped.
+///
+fn field_branches(fields: &[f64]) -> Vec<Vec<usize>> {
+    let mut branches: Vec<Vec<usize>> = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+    let mut direction = 0.0;
+    for (index, b) in fields.iter().enumerate() {
+        let Some(last) = current.last().copied() else {
+            current.push(index);
+            continue;
+        };
+        let step = b - fields[last];
+        if step == 0.0 {
+            continue;
+        }
+        if direction != 0.0 && step.signum() != direction {
+            branches.push(std::mem::take(&mut current));
+            current.push(last);
+        }
+        direction = step.signum();
+        current.push(index);
+    }
+    if current.len() > 1 || branches.is_empty() {
+        branches.push(current);
+    }
+    branches
+}
+
+///
+/// Splits a loop into branches of strictly decreasing or increasing field (see
+/// `field_branches`).
+///
+fn loop_branches(hysteresis_loop: &[MagneticLoopMeasurement]) -> Vec<Vec<MagneticLoopMeasurement>> {
+    let fields: Vec<f64> = hysteresis_loop.iter().map(|measurement| measurement.b).collect();
+    field_branches(&fields)
+        .iter()
+        .map(|branch| branch.iter().map(|index| hysteresis_loop[*index].clone()).collect())
+        .collect()
+}
+
+///
+/// The derivative at `xs[i]` of the monotone piecewise cubic Hermite interpolant of the points
+/// (`xs`, `ys`), with `xs` strictly increasing.
+///
+fn monotone_slope(xs: &[f64], ys: &[f64], i: usize) -> f64 {
+    let n = xs.len();
+    let secant = |j: usize| (ys[j + 1] - ys[j]) / (xs[j + 1] - xs[j]);
+    if n < 2 {
+        return 0.0;
+    }
+    if i == 0 {
+        return secant(0);
+    }
+    if i == n - 1 {
+        return secant(n - 2);
+    }
+    let (d0, d1) = (secant(i - 1), secant(i));
+    if d0 * d1 <= 0.0 {
+        return 0.0;
+    }
+    // The weighted harmonic mean of Fritsch and Butland.
+    let (h0, h1) = (xs[i] - xs[i - 1], xs[i + 1] - xs[i]);
+    let (w0, w1) = (2.0 * h1 + h0, h1 + 2.0 * h0);
+    (w0 + w1) / (w0 / d0 + w1 / d1)
+}
+
+///
+/// Interpolates the points (`xs`, `ys`), with `xs` strictly increasing, at `x` (which is
+/// clamped to the range of `xs`).
+///
+fn interpolate_loop_value(xs: &[f64], ys: &[f64], x: f64, interpolation: LoopInterpolation) -> f64 {
+    let n = xs.len();
+    if n == 1 {
+        return ys[0];
+    }
+    let i = xs.partition_point(|xi| *xi <= x).clamp(1, n - 1) - 1;
+    let h = xs[i + 1] - xs[i];
+    let t = ((x - xs[i]) / h).clamp(0.0, 1.0);
+    match interpolation {
+        LoopInterpolation::Linear => ys[i] + t * (ys[i + 1] - ys[i]),
+        LoopInterpolation::MonotoneCubic => {
+            let (m0, m1) = (monotone_slope(xs, ys, i), monotone_slope(xs, ys, i + 1));
+            let (t2, t3) = (t * t, t * t * t);
+            (2.0 * t3 - 3.0 * t2 + 1.0) * ys[i]
+                + (t3 - 2.0 * t2 + t) * h * m0
+                + (-2.0 * t3 + 3.0 * t2) * ys[i + 1]
+                + (t3 - t2) * h * m1
+        }
+    }
+}
+
+///
+/// Resamples a branch at the given fields.
+///
+fn resample_branch(
+    branch: &[MagneticLoopMeasurement],
+    fields: &[f64],
+    interpolation: LoopInterpolation,
+) -> Vec<MagneticLoopMeasurement> {
+    // Interpolation works on increasing fields.
+    let mut ordered: Vec<&MagneticLoopMeasurement> = branch.iter().collect();
+    if ordered.len() > 1 && ordered[0].b > ordered[1].b {
+        ordered.reverse();
+    }
+    let xs: Vec<f64> = ordered.iter().map(|measurement| measurement.b).collect();
+    let column = |value: fn(&MagneticLoopMeasurement) -> f64| -> Vec<f64> {
+        ordered.iter().map(|measurement| value(measurement)).collect()
+    };
+    let columns = [
+        column(|m| m.bx),
+        column(|m| m.by),
+        column(|m| m.bz),
+        column(|m| m.mx),
+        column(|m| m.my),
+        column(|m| m.mz),
+        column(|m| m.ms),
+        column(|m| m.vol),
+    ];
+
+    fields
+        .iter()
+        .map(|b| {
+            let v = columns.each_ref().map(|ys| interpolate_loop_value(&xs, ys, *b, interpolation));
+            MagneticLoopMeasurement {
+                b: *b,
+                bx: v[0],
+                by: v[1],
+                bz: v[2],
+                mx: v[3],
+                my: v[4],
+                mz: v[5],
+                ms: v[6],
+                vol: v[7],
+            }
+        })
+        .collect()
+}
+
 impl MagneticLoopStack {
+    ///
+    /// Builds a stack from loops measured on different field grids by resampling each loop onto
+    /// a common grid.
+    ///
+    /// Each loop is split into branches of decreasing or increasing field, the loops must agree
+    /// on the direction of the branches they share. Only branches present in every loop are
+    /// kept, and each branch is restricted to the field range covered by all loops; the grid
+    /// runs over that range with a fixed step, starting from the branch's first field.
+    ///
+    /// # Arguments
+    ///
+    /// * `source_files` - The files the loops were read from.
+    /// * `hysteresis_loops` - The loops.
+    /// * `interpolation` - How the loops are interpolated onto the grid.
+    /// * `field_step` - The magnitude of the grid's field step, by default the coarsest (median)
+    ///   step of any loop.
+    ///
+    /// # Returns
+    ///
+    /// The resampled stack, along with a `ResampleReport`.
+    ///
+    /// # Errors
+    /// Returns an error if there are no loops, the branches of the loops run in different
+    /// directions or the loops do not overlap.
+    ///
+    pub fn resampled(
+        source_files: Vec<String>,
+        hysteresis_loops: Vec<Vec<MagneticLoopMeasurement>>,
+        interpolation: LoopInterpolation,
+        field_step: Option<f64>,
+    ) -> io::Result<(MagneticLoopStack, ResampleReport)> {
+        const TOLERANCE: f64 = 1e-12;
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        if hysteresis_loops.is_empty() || hysteresis_loops.iter().any(|l| l.is_empty()) {
+            return Err(invalid("No loop data found."));
+        }
+        let branches: Vec<Vec<Vec<MagneticLoopMeasurement>>> =
+            hysteresis_loops.iter().map(|l| loop_branches(l)).collect();
+        let no_of_branches = branches.iter().map(|b| b.len()).min().unwrap();
+
+        // The direction of each branch, +1 for increasing and -1 for decreasing field.
+        let direction = |branch: &[MagneticLoopMeasurement]| {
+            if branch.len() > 1 { (branch[1].b - branch[0].b).signum() } else { 0.0 }
+        };
+        for k in 0..no_of_branches {
+            let expected = direction(&branches[0][k]);
+            if branches.iter().any(|loop_branches| direction(&loop_branches[k]) != expected) {
+                return Err(invalid("The branches of the loops run in different directions."));
+            }
+        }
+
+        let field_step = match field_step {
+            Some(step) => step.abs(),
+            None => hysteresis_loops
+                .iter()
+                .map(|l| {
+                    let mut steps: Vec<f64> = l.windows(2).map(|w| (w[1].b - w[0].b).abs()).filter(|s| *s > 0.0).collect();
+                    steps.sort_by(|a, b| a.total_cmp(b));
+                    steps.get(steps.len() / 2).copied().unwrap_or(0.0)
+                })
+                .fold(0.0, f64::max),
+        };
+
+        let mut clipped = vec![false; hysteresis_loops.len()];
+        for (loop_index, loop_branches) in branches.iter().enumerate() {
+            if loop_branches.len() > no_of_branches {
+                clipped[loop_index] = true;
+            }
+        }
+
+        let mut resampled: Vec<Vec<MagneticLoopMeasurement>> = vec![Vec::new(); hysteresis_loops.len()];
+        for k in 0..no_of_branches {
+            let range = |branch: &[MagneticLoopMeasurement]| {
+                branch.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), m| (lo.min(m.b), hi.max(m.b)))
+            };
+            let lo = branches.iter().map(|b| range(&b[k]).0).fold(f64::NEG_INFINITY, f64::max);
+            let hi = branches.iter().map(|b| range(&b[k]).1).fold(f64::INFINITY, f64::min);
+            if lo > hi + TOLERANCE {
+                return Err(invalid("The loops do not overlap."));
+            }
+            for (loop_index, loop_branches) in branches.iter().enumerate() {
+                let (branch_lo, branch_hi) = range(&loop_branches[k]);
+                if branch_lo < lo - TOLERANCE || branch_hi > hi + TOLERANCE {
+                    clipped[loop_index] = true;
+                }
+            }
+
+            // The grid starts from the branch's first field and steps towards its last.
+            let decreasing = direction(&branches[0][k]) < 0.0;
+            let n = if field_step > 0.0 { ((hi - lo) / field_step + 1e-9).floor() as usize + 1 } else { 1 };
+            let mut fields: Vec<f64> = (0..n)
+                .map(|i| if decreasing { hi - i as f64 * field_step } else { lo + i as f64 * field_step })
+                .collect();
+
+            // Consecutive branches share their turning point.
+            if k > 0 {
+                let previous = resampled[0].last().map(|m| m.b);
+                if previous.is_some_and(|b| (fields[0] - b).abs() <= TOLERANCE) {
+                    fields.remove(0);
+                }
+            }
+
+            for (loop_index, loop_branches) in branches.iter().enumerate() {
+                resampled[loop_index].extend(resample_branch(&loop_branches[k], &fields, interpolation));
+            }
+        }
+
+        let steps_per_hysteresis_loop = resampled[0].len();
+        let field_start = resampled[0][0].b;
+        let field_end = resampled[0][steps_per_hysteresis_loop - 1].b;
+        let first_direction = direction(&branches[0][0]);
+        let no_of_hysteresis_loops = resampled.len();
+
+        Ok((
+            MagneticLoopStack {
+                source_files,
+                hysteresis_loops: resampled,
+                no_of_hysteresis_loops,
+                steps_per_hysteresis_loop,
+                field_start,
+                field_end,
+                field_step: if first_direction < 0.0 { -field_step } else { field_step },
+            },
+            ResampleReport {
+                no_of_branches,
+                clipped_loops: clipped.iter().filter(|c| **c).count(),
+            },
+        ))
+    }
+
+    ///
+    /// The metadata describing this stack, to be carried along with its average.
+    ///
+    pub fn metadata(&self) -> LoopMetadata {
+        LoopMetadata {
+            source_files: self.source_files.clone(),
+            no_of_hysteresis_loops: self.no_of_hysteresis_loops,
+            field_start: self.field_start,
+            field_end: self.field_end,
+            field_step: self.field_step,
+        }
+    }
+
     pub fn average(&self) -> Vec<AveragedMagneticLoopMeasurement> {
         let mut average_loop: Vec<AveragedMagneticLoopMeasurement> = vec![
             AveragedMagneticLoopMeasurement {
@@ -70,31 +724,396 @@ impl MagneticLoopStack {
 
         average_loop
     }
+
+    ///
+    /// Averages the stack, keeping the moment as a vector, with statistics over the loops at
+    /// every field step.
+    ///
+    /// # Returns
+    ///
+    /// One `VectorAveragedMagneticLoopMeasurement` per field step.
+    ///
+    pub fn vector_average(&self) -> Vec<VectorAveragedMagneticLoopMeasurement> {
+        self.weighted_vector_average(&vec![1.0; self.hysteresis_loops.len()])
+    }
+
+    ///
+    /// Averages the stack as `average` does, weighting each loop.
+    ///
+    /// # Arguments
+    ///
+    /// * `weights` - One (non-negative) weight per loop, e.g. from `loop_weights`; the weights
+    ///   need not be normalised.
+    ///
+    pub fn weighted_average(&self, weights: &[f64]) -> Vec<AveragedMagneticLoopMeasurement> {
+        let total: f64 = weights.iter().sum();
+        (0..self.steps_per_hysteresis_loop)
+            .map(|step| {
+                let mut average = AveragedMagneticLoopMeasurement { b: 0.0, m: 0.0, ms: 0.0, vol: 0.0 };
+                for (hysteresis_loop, weight) in self.hysteresis_loops.iter().zip(weights.iter()) {
+                    let measurement = &hysteresis_loop[step];
+                    let w = weight / total;
+                    average.b += w * measurement.b;
+                    average.m += w * dot(
+                        [measurement.bx, measurement.by, measurement.bz],
+                        [measurement.mx, measurement.my, measurement.mz],
+                    );
+                    average.ms += w * measurement.ms;
+                    average.vol += w * measurement.vol;
+                }
+                average
+            })
+            .collect()
+    }
+
+    ///
+    /// Averages the stack as `vector_average` does, weighting each loop.
+    ///
+    /// # Arguments
+    ///
+    /// * `weights` - One (non-negative) weight per loop, e.g. from `loop_weights`; the weights
+    ///   need not be normalised.
+    ///
+    pub fn weighted_vector_average(&self, weights: &[f64]) -> Vec<VectorAveragedMagneticLoopMeasurement> {
+        let total: f64 = weights.iter().sum();
+        (0..self.steps_per_hysteresis_loop)
+            .map(|step| {
+                let measurements: Vec<&MagneticLoopMeasurement> =
+                    self.hysteresis_loops.iter().map(|hysteresis_loop| &hysteresis_loop[step]).collect();
+                let column = |value: fn(&MagneticLoopMeasurement) -> f64| -> Vec<f64> {
+                    measurements.iter().map(|measurement| value(measurement)).collect()
+                };
+                let mean = |values: Vec<f64>| -> f64 {
+                    values.iter().zip(weights.iter()).map(|(x, w)| w * x).sum::<f64>() / total
+                };
+                let (parallel, perpendicular): (Vec<f64>, Vec<f64>) =
+                    measurements.iter().map(|measurement| parallel_perpendicular(measurement)).unzip();
+
+                VectorAveragedMagneticLoopMeasurement {
+                    b: mean(column(|m| m.b)),
+                    mx: StepStatistics::from_weighted_samples(&column(|m| m.mx), weights),
+                    my: StepStatistics::from_weighted_samples(&column(|m| m.my), weights),
+                    mz: StepStatistics::from_weighted_samples(&column(|m| m.mz), weights),
+                    m_parallel: StepStatistics::from_weighted_samples(&parallel, weights),
+                    m_perpendicular: StepStatistics::from_weighted_samples(&perpendicular, weights),
+                    ms: mean(column(|m| m.ms)),
+                    vol: mean(column(|m| m.vol)),
+                }
+            })
+            .collect()
+    }
+
+    ///
+    /// The unit field direction of each loop, taken from its first measurement.
+    ///
+    pub fn field_directions(&self) -> Vec<[f64; 3]> {
+        self.hysteresis_loops
+            .iter()
+            .map(|hysteresis_loop| hysteresis_loop.first().map_or([0.0; 3], unit_field_direction))
+            .collect()
+    }
+
+    ///
+    /// Computes the weight of each loop for the given weighting scheme.
+    ///
+    /// # Returns
+    ///
+    /// One weight per loop, normalised to sum to one.
+    ///
+    /// # Errors
+    /// Returns an error if user supplied weights do not hold one non-negative weight per loop, or
+    /// sum to zero.
+    ///
+    pub fn loop_weights(&self, weighting: &LoopWeighting) -> io::Result<Vec<f64>> {
+        let n = self.hysteresis_loops.len();
+        let weights = match weighting {
+            LoopWeighting::Uniform => vec![1.0; n],
+            LoopWeighting::SolidAngle => solid_angle_weights(&self.field_directions()),
+            LoopWeighting::User(weights) => {
+                if weights.len() != n {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Expected {} loop weights, found {}.", n, weights.len()),
+                    ));
+                }
+                if weights.iter().any(|w| *w < 0.0 || !w.is_finite()) {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "Loop weights must be non-negative."));
+                }
+                weights.clone()
+            }
+        };
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Loop weights sum to zero."));
+        }
+        Ok(weights.iter().map(|w| w / total).collect())
+    }
+}
+
+///
+/// The errors that can occur while reading a loop file.
+///
+#[derive(Debug)]
+pub enum LoopFileError {
+    /// The file could not be opened or read.
+    Io { file: String, source: io::Error },
+
+    /// The file holds no header line.
+    MissingHeader { file: String },
+
+    /// A required column is not present in the header.
+    MissingColumn { file: String, column: &'static str },
+
+    /// A header column has a unit that cannot be converted.
+    UnknownUnit { file: String, column: String, unit: String },
+
+    /// A data row could not be parsed, `line` counts from one.
+    MalformedRow { file: String, line: usize, message: String },
+}
+
+impl fmt::Display for LoopFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoopFileError::Io { file, source } => write!(f, "{}: {}", file, source),
+            LoopFileError::MissingHeader { file } => write!(f, "{}: no header line found", file),
+            LoopFileError::MissingColumn { file, column } => {
+                write!(f, "{}: the header has no '{}' column", file, column)
+            }
+            LoopFileError::UnknownUnit { file, column, unit } => {
+                write!(f, "{}: unknown unit '{}' for column '{}'", file, unit, column)
+            }
+            LoopFileError::MalformedRow { file, line, message } => {
+                write!(f, "{} line {}: {}", file, line, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoopFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoopFileError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<LoopFileError> for io::Error {
+    fn from(error: LoopFileError) -> io::Error {
+        match error {
+            LoopFileError::Io { source, .. } => source,
+            error => io::Error::new(io::ErrorKind::InvalidData, error.to_string()),
+        }
+    }
+}
+
+///
+/// How strictly the data rows of a loop file are parsed.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopParseMode {
+    /// A malformed row is an error.
+    Strict,
+
+    /// Malformed rows are skipped and reported.
+    Lenient,
+}
+
+///
+/// A data row that was skipped while reading a loop file in lenient mode.
+///
+/// # Fields
+///
+/// * `file` - The loop file.
+/// * `line` - The line number, counting from one.
+/// * `reason` - Why the row was skipped.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedRow {
+    pub file: String,
+    pub line: usize,
+    pub reason: String,
+}
+
+///
+/// The quantities held in a loop file, in the order of the fields of `MagneticLoopMeasurement`,
+/// along with the names under which they may appear in the header.
+///
+const LOOP_COLUMNS: [(&str, &[&str]); 9] = [
+    ("B", &["b"]),
+    ("Bx", &["bx"]),
+    ("By", &["by"]),
+    ("Bz", &["bz"]),
+    ("<Mx>", &["mx"]),
+    ("<My>", &["my"]),
+    ("<Mz>", &["mz"]),
+    ("Ms", &["ms"]),
+    ("Volume", &["volume", "vol", "v"]),
+];
+
+///
+/// The factor that converts a value in the given unit to SI, for the column with the given
+/// index in `LOOP_COLUMNS`; columns without a unit are taken to be in SI.
+///
+fn unit_scale(column: usize, unit: &str) -> Option<f64> {
+    let unit = unit.to_lowercase().replace(' ', "");
+    if unit.is_empty() {
+        return Some(1.0);
+    }
+    match column {
+        0 => match unit.as_str() {
+            "tesla" | "t" => Some(1.0),
+            "millitesla" | "mt" => Some(1e-3),
+            _ => None,
+        },
+        1..=3 => None,
+        4..=6 => match unit.as_str() {
+            "am^2" | "am2" | "a.m^2" => Some(1.0),
+            _ => None,
+        },
+        7 => match unit.as_str() {
+            "a/m" => Some(1.0),
+            "ka/m" => Some(1e3),
+            _ => None,
+        },
+        _ => match unit.as_str() {
+            "m^3" | "m3" => Some(1.0),
+            "um^3" | "um3" | "µm^3" => Some(1e-18),
+            "nm^3" | "nm3" => Some(1e-27),
+            _ => None,
+        },
+    }
+}
+
+///
+/// Maps the header of a loop file onto the quantities of `LOOP_COLUMNS`, e.g. `B (Tesla)`,
+/// `Bx`, `<Mx> (Am^2)`, ..., `Volume (m^3)`. Columns may appear in any order and unknown columns
+/// are ignored.
+///
+/// # Returns
+///
+/// For each quantity of `LOOP_COLUMNS`, its column index in the file and its unit scale factor.
+///
+fn map_loop_columns(file_name: &str, header: &str) -> Result<[(usize, f64); 9], LoopFileError> {
+    let mut mapping: [Option<(usize, f64)>; 9] = [None; 9];
+    for (index, cell) in header.split(',').enumerate() {
+        let cell = cell.trim();
+        let (name, unit) = match cell.split_once('(') {
+            Some((name, unit)) => (name.trim(), unit.trim_end_matches(')').trim()),
+            None => (cell, ""),
+        };
+        let name = name.trim_matches(|c| c == '<' || c == '>').to_lowercase();
+        let Some(column) = LOOP_COLUMNS.iter().position(|(_, names)| names.contains(&name.as_str())) else {
+            continue;
+        };
+        let scale = unit_scale(column, unit).ok_or_else(|| LoopFileError::UnknownUnit {
+            file: file_name.to_string(),
+            column: cell.to_string(),
+            unit: unit.to_string(),
+        })?;
+        mapping[column].get_or_insert((index, scale));
+    }
+
+    let mut columns = [(0, 1.0); 9];
+    for (column, mapped) in mapping.iter().enumerate() {
+        columns[column] = mapped.ok_or(LoopFileError::MissingColumn {
+            file: file_name.to_string(),
+            column: LOOP_COLUMNS[column].0,
+        })?;
+    }
+    Ok(columns)
+}
+
+///
+/// Reads a MERRILL hysteresis loop file, see `read_loop_file_with_mode`, in strict mode.
+///
+/// # Errors
+/// Returns a `LoopFileError` if the file cannot be read, its header lacks a required column or
+/// any data row is malformed.
+///
+pub fn read_loop_file(file_name: &str) -> Result<Vec<MagneticLoopMeasurement>, LoopFileError> {
+    read_loop_file_with_mode(file_name, LoopParseMode::Strict).map(|(data, _)| data)
+}
+
+///
+/// Reads a MERRILL hysteresis loop file.
+///
+/// Blank lines and `#` comment lines are ignored; the first other line is the header, which
+/// maps the comma separated columns onto the quantities by name and unit (see
+/// `map_loop_columns`). Values are converted to SI units.
+///
+/// # Arguments
+///
+/// * `file_name` - The loop file.
+/// * `mode` - Whether malformed rows are an error or are skipped.
+///
+/// # Returns
+///
+/// The measurements, along with the rows that were skipped (always empty in strict mode).
+///
+/// # Errors
+/// Returns a `LoopFileError` if the file cannot be read or its header lacks a required column,
+/// and in strict mode if a data row is malformed.
+///
+pub fn read_loop_file_with_mode(
+    file_name: &str,
+    mode: LoopParseMode,
+) -> Result<(Vec<MagneticLoopMeasurement>, Vec<SkippedRow>), LoopFileError> {
+    let (rows, skipped) = read_loop_rows(file_name, mode)?;
+    Ok((rows.into_iter().map(|(_, measurement)| measurement).collect(), skipped))
 }
 
-pub fn read_loop_file(file_name: &str) -> io::Result<Vec<MagneticLoopMeasurement>> {
-    let file = File::open(file_name)?;
+///
+/// The measurements of a loop file, each paired with its line number, along with the rows
+/// that were skipped.
+///
+type LoopRows = (Vec<(usize, MagneticLoopMeasurement)>, Vec<SkippedRow>);
+
+///
+/// Reads a MERRILL hysteresis loop file as `read_loop_file_with_mode` does, pairing each
+/// measurement with its line number, counting from one.
+///
+fn read_loop_rows(file_name: &str, mode: LoopParseMode) -> Result<LoopRows, LoopFileError> {
+    let io_error = |source| LoopFileError::Io { file: file_name.to_string(), source };
+    let file = File::open(file_name).map_err(io_error)?;
     let reader = io::BufReader::new(file);
-    let mut data: Vec<MagneticLoopMeasurement> = Vec::new();
+    let mut columns: Option<[(usize, f64); 9]> = None;
+    let mut data: Vec<(usize, MagneticLoopMeasurement)> = Vec::new();
+    let mut skipped: Vec<SkippedRow> = Vec::new();
 
     for (i, line) in reader.lines().enumerate() {
-        let line = line?;
-
-        // Skip header
-        if i == 0 {
+        let line = line.map_err(io_error)?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
             continue;
         }
 
+        // The first line holds the header.
+        let Some(columns) = columns else {
+            columns = Some(map_loop_columns(file_name, trimmed)?);
+            continue;
+        };
+
         // Parse line
-        let values: Vec<f64> = line
-            .split(",")
-            .map(|s| s.trim().parse::<f64>())
-            .filter_map(Result::ok)
-            .collect();
+        let cells: Vec<&str> = trimmed.split(',').map(|s| s.trim()).collect();
+        let mut values = [0.0; 9];
+        let mut problem = None;
+        for (value, (index, scale)) in values.iter_mut().zip(columns.iter()) {
+            match cells.get(*index).map(|cell| cell.parse::<f64>()) {
+                Some(Ok(parsed)) => *value = parsed * scale,
+                Some(Err(_)) => {
+                    problem = Some(format!("cannot parse '{}' in column {}", cells[*index], index + 1));
+                    break;
+                }
+                None => {
+                    problem = Some(format!("expected at least {} values, found {}", index + 1, cells.len()));
+                    break;
+                }
+            }
+        }
 
-        // Ensure that 9 values exist
-        if values.len() == 9 {
-            data.push(MagneticLoopMeasurement{
+        match problem {
+            None => data.push((i + 1, MagneticLoopMeasurement{
                 b: values[0],
                 bx: values[1],
                 by: values[2],
@@ -104,18 +1123,83 @@ pub fn read_loop_file(file_name: &str) -> io::Result<Vec<MagneticLoopMeasurement
                 mz: values[6],
                 ms: values[7],
                 vol: values[8],
-            });
+            })),
+            Some(message) => match mode {
+                LoopParseMode::Strict => {
+                    return Err(LoopFileError::MalformedRow { file: file_name.to_string(), line: i + 1, message })
+                }
+                LoopParseMode::Lenient => {
+                    skipped.push(SkippedRow { file: file_name.to_string(), line: i + 1, reason: message })
+                }
+            },
         }
     }
 
-    Ok(data)
+    if columns.is_none() {
+        return Err(LoopFileError::MissingHeader { file: file_name.to_string() });
+    }
+
+    Ok((data, skipped))
 }
 
 pub fn read_loop_files(file_names: &Vec<String>) -> io::Result<MagneticLoopStack> {
+    read_loop_files_with_mode(file_names, LoopParseMode::Strict).map(|(loop_stack, _)| loop_stack)
+}
+
+///
+/// Reads a stack of loop files (see `read_loop_file_with_mode`) that share the same field
+/// steps. A turning field repeated between two branches is read once: the first measurement
+/// is kept and the repeat is reported among the skipped rows.
+///
+/// # Returns
+///
+/// The stack, along with the rows of all files that were skipped in lenient mode and the
+/// repeated turning points that were dropped.
+///
+/// # Errors
+/// Returns an error if a file cannot be read or has no data rows, if a field other than a
+/// turning field is repeated, or if the loops do not share the same field steps.
+///
+pub fn read_loop_files_with_mode(
+    file_names: &Vec<String>,
+    mode: LoopParseMode,
+) -> io::Result<(MagneticLoopStack, Vec<SkippedRow>)> {
     let mut loop_stack: Vec<Vec<MagneticLoopMeasurement>> = Vec::new();
+    let mut skipped: Vec<SkippedRow> = Vec::new();
     for file_name in file_names {
-        let file_data = read_loop_file(file_name)?;
-        loop_stack.push(file_data);
+        let (rows, file_skipped) = read_loop_rows(file_name, mode)?;
+        if rows.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}: no data rows", file_name)));
+        }
+        skipped.extend(file_skipped);
+
+        // The turning field between branches may be repeated, other fields may not; the repeated
+        // turning points are dropped so that loops with and without them can be stacked.
+        let fields: Vec<f64> = rows.iter().map(|(_, measurement)| measurement.b).collect();
+        let branches = field_branches(&fields);
+        let turning_points: Vec<usize> = branches[..branches.len() - 1]
+            .iter()
+            .filter_map(|branch| branch.last().copied())
+            .collect();
+        let mut loop_data = vec![rows[0].1.clone()];
+        for (j, pair) in rows.windows(2).enumerate() {
+            let (line, measurement) = &pair[1];
+            if measurement.b != pair[0].1.b {
+                loop_data.push(measurement.clone());
+            } else if turning_points.contains(&j) {
+                skipped.push(SkippedRow {
+                    file: file_name.to_string(),
+                    line: *line,
+                    reason: format!("repeated turning field {} T, the first measurement is kept", measurement.b),
+                });
+            } else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} line {}: the field value {} T is repeated", file_name, line, measurement.b),
+                ));
+            }
+        }
+        loop_stack.push(loop_data);
     }
 
     // Grab the first loop from our loop-stack.
@@ -134,17 +1218,35 @@ pub fn read_loop_files(file_names: &Vec<String>) -> io::Result<MagneticLoopStack
         }
     }
 
-    // Check that each hysteresis loop has the same field step.
-    let field_step = first_loop[1].b - first_loop[0].b;
-    for i in 0..loop_stack.len() {
-        let loop_data = &loop_stack[i];
-        for j in 0..loop_data.len() - 1 {
-            let sub_step = loop_data[j+1].b - loop_data[j].b;
-            if (sub_step - field_step).abs() > 1e-12 {
-                return Err(io::Error::new(io::ErrorKind::InvalidData, "A field step was too large"))
-            };
+    // Check that each branch of each hysteresis loop has a constant field step, which is the
+    // same in every loop; the step changes sign at the turning points between branches.
+    let first_fields: Vec<f64> = first_loop.iter().map(|measurement| measurement.b).collect();
+    let first_branches = field_branches(&first_fields);
+    let branch_step = |fields: &[f64], branch: &[usize]| {
+        if branch.len() > 1 { fields[branch[1]] - fields[branch[0]] } else { 0.0 }
+    };
+    for loop_data in &loop_stack {
+        let fields: Vec<f64> = loop_data.iter().map(|measurement| measurement.b).collect();
+        let branches = field_branches(&fields);
+        if branches.len() != first_branches.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Loop files do not have the same number of branches.",
+            ));
+        }
+        for (k, (branch, first_branch)) in branches.iter().zip(first_branches.iter()).enumerate() {
+            let step = branch_step(&fields, branch);
+            if (step - branch_step(&first_fields, first_branch)).abs() > 1e-12
+                || branch.windows(2).any(|pair| (fields[pair[1]] - fields[pair[0]] - step).abs() > 1e-12)
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("A field step on branch {} was too large", k + 1),
+                ));
+            }
         }
     }
+    let field_step = branch_step(&first_fields, &first_branches[0]);
 
     // Check that each hysteresis loop has the same start/end field.
     let field_start = first_loop[0].b;
@@ -167,21 +1269,853 @@ pub fn read_loop_files(file_names: &Vec<String>) -> io::Result<MagneticLoopStack
 
     let no_of_hysteresis_loops = loop_stack.len();
 
-    Ok(MagneticLoopStack{
-        hysteresis_loops: loop_stack,
-        no_of_hysteresis_loops,
-        steps_per_hysteresis_loop: loop_length,
-        field_start,
-        field_end,
-        field_step,
+    Ok((
+        MagneticLoopStack{
+            source_files: file_names.clone(),
+            hysteresis_loops: loop_stack,
+            no_of_hysteresis_loops,
+            steps_per_hysteresis_loop: loop_length,
+            field_start,
+            field_end,
+            field_step,
+        },
+        skipped,
+    ))
+
+}
+
+///
+/// Reads a stack of loop files (see `read_loop_file_with_mode`) that may have been measured on
+/// different field grids, and resamples them onto a common grid (see
+/// `MagneticLoopStack::resampled`).
+///
+/// # Returns
+///
+/// The stack, the rows of all files that were skipped in lenient mode and a `ResampleReport`.
+///
+pub fn read_loop_files_resampled(
+    file_names: &Vec<String>,
+    mode: LoopParseMode,
+    interpolation: LoopInterpolation,
+    field_step: Option<f64>,
+) -> io::Result<(MagneticLoopStack, Vec<SkippedRow>, ResampleReport)> {
+    let mut loop_stack: Vec<Vec<MagneticLoopMeasurement>> = Vec::new();
+    let mut skipped: Vec<SkippedRow> = Vec::new();
+    for file_name in file_names {
+        let (file_data, file_skipped) = read_loop_file_with_mode(file_name, mode)?;
+        loop_stack.push(file_data);
+        skipped.extend(file_skipped);
+    }
+
+    let (stack, report) = MagneticLoopStack::resampled(file_names.clone(), loop_stack, interpolation, field_step)?;
+    Ok((stack, skipped, report))
+}
+
+///
+/// Reads an averaged loop, as written by `write_averaged_loop_to_loop_file` or
+/// `write_averaged_loop_to_csv` (e.g. by `loopavg`). A file in the nine column `.loop` layout
+/// is read with `read_loop_file` and its moment is taken along the field direction. Otherwise
+/// blank lines and `#` comment lines are skipped, the first remaining line is the header and
+/// every following line holds the four columns B, M, Ms and Volume.
+///
+/// # Arguments
+///
+/// * `file_name` - The averaged loop file.
+///
+/// # Returns
+///
+/// The averaged loop.
+///
+/// # Errors
+/// Returns an error if the file cannot be read, or a row does not hold the columns of its layout.
+///
+pub fn read_averaged_loop_file(file_name: &str) -> io::Result<Vec<AveragedMagneticLoopMeasurement>> {
+    match read_loop_file(file_name) {
+        Ok(hysteresis_loop) => {
+            return Ok(hysteresis_loop
+                .iter()
+                .map(|measurement| AveragedMagneticLoopMeasurement {
+                    b: measurement.b,
+                    m: measurement.parallel_moment(),
+                    ms: measurement.ms,
+                    vol: measurement.vol,
+                })
+                .collect())
+        }
+        Err(LoopFileError::MissingColumn { .. }) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let reader = io::BufReader::new(File::open(file_name)?);
+    let mut average_loop = Vec::new();
+    let mut header_seen = false;
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !header_seen {
+            header_seen = true;
+            continue;
+        }
+        let values: Vec<f64> = trimmed
+            .split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{} line {}: {}", file_name, index + 1, e))
+            })?;
+        if values.len() != 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} line {}: expected 4 columns, found {}", file_name, index + 1, values.len()),
+            ));
+        }
+        average_loop.push(AveragedMagneticLoopMeasurement { b: values[0], m: values[1], ms: values[2], vol: values[3] });
+    }
+    Ok(average_loop)
+}
+
+///
+/// Formats a value in the Fortran ES16.8 style used by MERRILL's loop files, e.g.
+/// `  2.00000000E-01`.
+///
+fn fortran_es16_8(value: f64) -> String {
+    let formatted = format!("{:.8E}", value);
+    let (mantissa, exponent) = formatted.split_once('E').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    format!("{:>16}", format!("{}E{}{:02}", mantissa, if exponent < 0 { '-' } else { '+' }, exponent.abs()))
+}
+
+///
+/// The metadata as `#` comment lines, which the loop file reader skips.
+///
+fn metadata_comments(metadata: &LoopMetadata) -> Vec<String> {
+    let mut lines = vec![
+        format!("# hysteresis loops: {}", metadata.no_of_hysteresis_loops),
+        format!("# field start (T): {}", metadata.field_start),
+        format!("# field end (T): {}", metadata.field_end),
+        format!("# field step (T): {}", metadata.field_step),
+    ];
+    for source_file in &metadata.source_files {
+        lines.push(format!("# source file: {}", source_file));
+    }
+    lines
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn json_number(value: f64) -> String {
+    // JSON has no representation of NaN or infinity.
+    if value.is_finite() { format!("{:E}", value) } else { String::from("null") }
+}
+
+///
+/// Writes an averaged loop in the nine column layout of MERRILL's `.loop` files, B, Bx, By,
+/// Bz, `<Mx>`, `<My>`, `<Mz>`, Ms and Volume, preceded by the metadata as `#` comment lines, so
+/// that it can be read back with `read_loop_file`. The averaged loops may have been measured
+/// along different field directions, so the loop is written along the reference direction x:
+/// the field direction is (1, 0, 0), `<Mx>` is the averaged moment along the field,
+/// `<B^ . M>`, and `<My>` and `<Mz>` are zero.
+///
+/// # Arguments
+///
+/// * `file_name` - The name of the file to create.
+/// * `average_loop` - The averaged loop.
+/// * `metadata` - The metadata describing the averaged loops.
+///
+/// # Errors
+/// Returns an error if the file cannot be created or written.
+///
+pub fn write_averaged_loop_to_loop_file(
+    file_name: &str,
+    average_loop: &[AveragedMagneticLoopMeasurement],
+    metadata: &LoopMetadata,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(file_name)?);
+    for line in metadata_comments(metadata) {
+        writeln!(writer, "{}", line)?;
+    }
+    writeln!(
+        writer,
+        "{:>16},{:>16},{:>16},{:>16},{:>16},{:>16},{:>16},{:>16},{:>16}",
+        "B (Tesla)", "Bx", "By", "Bz", "<Mx> (Am^2)", "<My> (Am^2)", "<Mz> (Am^2)", "Ms (A/m)", "Volume (m^3)"
+    )?;
+    for value in average_loop {
+        let row = [value.b, 1.0, 0.0, 0.0, value.m, 0.0, 0.0, value.ms, value.vol];
+        writeln!(writer, "{}", row.map(fortran_es16_8).join(","))?;
+    }
+    writer.flush()
+}
+
+///
+/// Writes an averaged loop as comma separated values with the header `B,M,Ms,Volume`,
+/// preceded by the metadata as `#` comment lines.
+///
+/// # Arguments
+///
+/// * `file_name` - The name of the file to create.
+/// * `average_loop` - The averaged loop.
+/// * `metadata` - The metadata describing the averaged loops.
+///
+/// # Errors
+/// Returns an error if the file cannot be created or written.
+///
+pub fn write_averaged_loop_to_csv(
+    file_name: &str,
+    average_loop: &[AveragedMagneticLoopMeasurement],
+    metadata: &LoopMetadata,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(file_name)?);
+    for line in metadata_comments(metadata) {
+        writeln!(writer, "{}", line)?;
+    }
+    writeln!(writer, "B,M,Ms,Volume")?;
+    for value in average_loop {
+        writeln!(writer, "{:E},{:E},{:E},{:E}", value.b, value.m, value.ms, value.vol)?;
+    }
+    writer.flush()
+}
+
+///
+/// Writes an averaged loop as a JSON object holding the metadata along with the columns `b`,
+/// `m`, `ms` and `volume` as arrays.
+///
+/// # Arguments
+///
+/// * `file_name` - The name of the file to create.
+/// * `average_loop` - The averaged loop.
+/// * `metadata` - The metadata describing the averaged loops.
+///
+/// # Errors
+/// Returns an error if the file cannot be created or written.
+///
+pub fn write_averaged_loop_to_json(
+    file_name: &str,
+    average_loop: &[AveragedMagneticLoopMeasurement],
+    metadata: &LoopMetadata,
+) -> io::Result<()> {
+    let column = |value: fn(&AveragedMagneticLoopMeasurement) -> f64| {
+        average_loop.iter().map(|v| json_number(value(v))).collect::<Vec<String>>().join(", ")
+    };
+    let source_files: Vec<String> = metadata.source_files.iter().map(|f| json_string(f)).collect();
+
+    let mut writer = BufWriter::new(File::create(file_name)?);
+    writeln!(writer, "{{")?;
+    writeln!(writer, "  \"metadata\": {{")?;
+    writeln!(writer, "    \"source_files\": [{}],", source_files.join(", "))?;
+    writeln!(writer, "    \"no_of_hysteresis_loops\": {},", metadata.no_of_hysteresis_loops)?;
+    writeln!(writer, "    \"field_start\": {},", json_number(metadata.field_start))?;
+    writeln!(writer, "    \"field_end\": {},", json_number(metadata.field_end))?;
+    writeln!(writer, "    \"field_step\": {}", json_number(metadata.field_step))?;
+    writeln!(writer, "  }},")?;
+    writeln!(writer, "  \"b\": [{}],", column(|v| v.b))?;
+    writeln!(writer, "  \"m\": [{}],", column(|v| v.m))?;
+    writeln!(writer, "  \"ms\": [{}],", column(|v| v.ms))?;
+    writeln!(writer, "  \"volume\": [{}]", column(|v| v.vol))?;
+    writeln!(writer, "}}")?;
+    writer.flush()
+}
+
+///
+/// Writes a vector averaged loop as comma separated values, one row per field step holding the
+/// field, the mean, standard deviation, standard error, minimum and maximum of each of `Mx`,
+/// `My`, `Mz`, `M_par` and `M_perp` (e.g. `Mx_mean`, `Mx_std`, `Mx_sem`, `Mx_min`, `Mx_max`),
+/// `Ms` and `Volume`; preceded by the metadata as `#` comment lines.
+///
+/// # Arguments
+///
+/// * `file_name` - The name of the file to create.
+/// * `average_loop` - The vector averaged loop.
+/// * `metadata` - The metadata describing the averaged loops.
+///
+/// # Errors
+/// Returns an error if the file cannot be created or written.
+///
+pub fn write_vector_averaged_loop_to_csv(
+    file_name: &str,
+    average_loop: &[VectorAveragedMagneticLoopMeasurement],
+    metadata: &LoopMetadata,
+) -> io::Result<()> {
+    const QUANTITIES: [&str; 5] = ["Mx", "My", "Mz", "M_par", "M_perp"];
+    let mut writer = BufWriter::new(File::create(file_name)?);
+    for line in metadata_comments(metadata) {
+        writeln!(writer, "{}", line)?;
+    }
+
+    let mut header = vec![String::from("B")];
+    for quantity in QUANTITIES {
+        for statistic in ["mean", "std", "sem", "min", "max"] {
+            header.push(format!("{}_{}", quantity, statistic));
+        }
+    }
+    header.push(String::from("Ms"));
+    header.push(String::from("Volume"));
+    writeln!(writer, "{}", header.join(","))?;
+
+    for value in average_loop {
+        let mut row = vec![format!("{:E}", value.b)];
+        for statistics in [&value.mx, &value.my, &value.mz, &value.m_parallel, &value.m_perpendicular] {
+            for x in [statistics.mean, statistics.std_dev, statistics.std_error, statistics.min, statistics.max] {
+                row.push(format!("{:E}", x));
+            }
+        }
+        row.push(format!("{:E}", value.ms));
+        row.push(format!("{:E}", value.vol));
+        writeln!(writer, "{}", row.join(","))?;
+    }
+    writer.flush()
+}
+
+///
+/// The standard hysteresis parameters of a loop.
+///
+/// # Fields
+///
+/// * `ms` - The saturation moment (Am^2), after removing the high-field slope if it was
+///   corrected.
+/// * `mrs` - The saturation remanence (Am^2), the mean size of the moment at zero field over the
+///   branches that pass through zero field, or `None` if no branch does.
+/// * `bc` - The coercivity (T), the mean size of the field at which the (slope corrected) moment
+///   vanishes over the branches on which it changes sign, or `None` if it does not on any branch.
+/// * `squareness` - The ratio Mrs/Ms, or `None` if there is no remanence.
+/// * `high_field_slope` - The slope dM/dB (Am^2/T) of the high-field (para- or diamagnetic)
+///   contribution that was removed, zero if the slope was not corrected.
+/// * `no_of_branches` - The number of branches of the loop.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct HysteresisParameters {
+    pub ms: f64,
+    pub mrs: Option<f64>,
+    pub bc: Option<f64>,
+    pub squareness: Option<f64>,
+    pub high_field_slope: f64,
+    pub no_of_branches: usize,
+}
+
+///
+/// The least squares line `y = slope x + intercept` through a set of points.
+///
+fn linear_fit(xs: &[f64], ys: &[f64]) -> Option<(f64, f64)> {
+    let n = xs.len() as f64;
+    let x_mean = xs.iter().sum::<f64>() / n;
+    let y_mean = ys.iter().sum::<f64>() / n;
+    let sxx: f64 = xs.iter().map(|x| (x - x_mean).powi(2)).sum();
+    let sxy: f64 = xs.iter().zip(ys.iter()).map(|(x, y)| (x - x_mean) * (y - y_mean)).sum();
+    if xs.len() < 2 || sxx <= 0.0 {
+        return None;
+    }
+    let slope = sxy / sxx;
+    Some((slope, y_mean - slope * x_mean))
+}
+
+///
+/// The value of `ys` where `xs` first changes sign along a branch (given as indices), linearly
+/// interpolated.
+///
+fn value_at_crossing(branch: &[usize], xs: &[f64], ys: &[f64]) -> Option<f64> {
+    branch.windows(2).find_map(|pair| {
+        let (x0, x1) = (xs[pair[0]], xs[pair[1]]);
+        if x0 == 0.0 {
+            Some(ys[pair[0]])
+        } else if x0 * x1 <= 0.0 {
+            let t = x0 / (x0 - x1);
+            Some(ys[pair[0]] + t * (ys[pair[1]] - ys[pair[0]]))
+        } else {
+            None
+        }
+    })
+}
+
+///
+/// Computes the hysteresis parameters of a loop, given as the field and the moment along the
+/// field at every step. The loop is split into branches of decreasing or increasing field, and
+/// the remanence and the coercivity are interpolated at the zero crossings on each branch.
+///
+/// With a high-field slope correction, the moment at fields above the given fraction of the
+/// largest field is fitted with a line on either side of zero field; the mean slope of the two
+/// lines is removed from the loop and Ms is half the difference of their intercepts. Otherwise
+/// Ms is half the range of the moment.
+///
+/// # Arguments
+///
+/// * `fields` - The applied field (T) at every step.
+/// * `moments` - The moment along the field (Am^2) at every step.
+/// * `high_field_fraction` - The fraction (e.g. 0.7) of the largest field above which the
+///   high-field slope is fitted, or `None` for no correction.
+///
+/// # Returns
+///
+/// The `HysteresisParameters` of the loop.
+///
+/// # Errors
+/// Returns an error if the loop is empty, or there are too few high-field steps on either side
+/// of zero field to fit the slope.
+///
+pub fn hysteresis_parameters(
+    fields: &[f64],
+    moments: &[f64],
+    high_field_fraction: Option<f64>,
+) -> io::Result<HysteresisParameters> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    if fields.is_empty() || fields.len() != moments.len() {
+        return Err(invalid("No loop data found."));
+    }
+
+    let (ms, high_field_slope) = match high_field_fraction {
+        Some(fraction) => {
+            let b_max = fields.iter().map(|b| b.abs()).fold(0.0, f64::max);
+            let side = |sign: f64| -> (Vec<f64>, Vec<f64>) {
+                fields
+                    .iter()
+                    .zip(moments.iter())
+                    .filter(|(b, _)| sign * **b >= fraction * b_max && **b != 0.0)
+                    .map(|(b, m)| (*b, *m))
+                    .unzip()
+            };
+            let (positive_b, positive_m) = side(1.0);
+            let (negative_b, negative_m) = side(-1.0);
+            let fit = |b: &[f64], m: &[f64]| {
+                linear_fit(b, m).ok_or_else(|| invalid("Too few high-field steps to fit the slope."))
+            };
+            let (positive_slope, positive_intercept) = fit(&positive_b, &positive_m)?;
+            let (negative_slope, negative_intercept) = fit(&negative_b, &negative_m)?;
+            (0.5 * (positive_intercept - negative_intercept), 0.5 * (positive_slope + negative_slope))
+        }
+        None => {
+            let largest = moments.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let smallest = moments.iter().copied().fold(f64::INFINITY, f64::min);
+            (0.5 * (largest - smallest), 0.0)
+        }
+    };
+    let corrected: Vec<f64> = fields.iter().zip(moments.iter()).map(|(b, m)| m - high_field_slope * b).collect();
+
+    let branches = field_branches(fields);
+    let remanences: Vec<f64> = branches
+        .iter()
+        .filter_map(|branch| value_at_crossing(branch, fields, &corrected))
+        .map(f64::abs)
+        .collect();
+    let coercivities: Vec<f64> = branches
+        .iter()
+        .filter_map(|branch| value_at_crossing(branch, &corrected, fields))
+        .map(f64::abs)
+        .collect();
+    let mean = |values: &[f64]| {
+        if values.is_empty() { None } else { Some(values.iter().sum::<f64>() / values.len() as f64) }
+    };
+    let mrs = mean(&remanences);
+
+    Ok(HysteresisParameters {
+        ms,
+        mrs,
+        bc: mean(&coercivities),
+        squareness: mrs.filter(|_| ms > 0.0).map(|mrs| mrs / ms),
+        high_field_slope,
+        no_of_branches: branches.len(),
+    })
+}
+
+///
+/// Computes the hysteresis parameters of a single loop from its moment along the field (see
+/// `hysteresis_parameters`).
+///
+pub fn loop_parameters(
+    hysteresis_loop: &[MagneticLoopMeasurement],
+    high_field_fraction: Option<f64>,
+) -> io::Result<HysteresisParameters> {
+    let fields: Vec<f64> = hysteresis_loop.iter().map(|measurement| measurement.b).collect();
+    let moments: Vec<f64> = hysteresis_loop.iter().map(|measurement| parallel_perpendicular(measurement).0).collect();
+    hysteresis_parameters(&fields, &moments, high_field_fraction)
+}
+
+///
+/// Computes the hysteresis parameters of an averaged loop (see `hysteresis_parameters`).
+///
+pub fn averaged_loop_parameters(
+    average_loop: &[AveragedMagneticLoopMeasurement],
+    high_field_fraction: Option<f64>,
+) -> io::Result<HysteresisParameters> {
+    let fields: Vec<f64> = average_loop.iter().map(|value| value.b).collect();
+    let moments: Vec<f64> = average_loop.iter().map(|value| value.m).collect();
+    hysteresis_parameters(&fields, &moments, high_field_fraction)
+}
+
+///
+/// The direction in which the field is swept along a branch.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchDirection {
+    Descending,
+    Ascending,
+}
+
+///
+/// A branch of a loop, a run of strictly decreasing or increasing field.
+///
+/// # Fields
+///
+/// * `direction` - The direction of the field sweep.
+/// * `fields` - The applied field (T) at every step, in the order of the sweep.
+/// * `moments` - The moment along the field (Am^2) at every step.
+/// * `field_step` - The (signed) field step of the branch, from its first two steps.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoopBranch {
+    pub direction: BranchDirection,
+    pub fields: Vec<f64>,
+    pub moments: Vec<f64>,
+    pub field_step: f64,
+}
+
+impl LoopBranch {
+    ///
+    /// Builds a branch from the given steps of a curve.
+    ///
+    fn from_indices(branch: &[usize], fields: &[f64], moments: &[f64]) -> LoopBranch {
+        let fields: Vec<f64> = branch.iter().map(|index| fields[*index]).collect();
+        let field_step = if fields.len() > 1 { fields[1] - fields[0] } else { 0.0 };
+        LoopBranch {
+            direction: if field_step < 0.0 { BranchDirection::Descending } else { BranchDirection::Ascending },
+            fields,
+            moments: branch.iter().map(|index| moments[*index]).collect(),
+            field_step,
+        }
+    }
+
+    ///
+    /// The smallest and the largest field of the branch.
+    ///
+    pub fn field_range(&self) -> (f64, f64) {
+        let first = self.fields[0];
+        let last = self.fields[self.fields.len() - 1];
+        (first.min(last), first.max(last))
+    }
+
+    ///
+    /// Checks that the field step is constant along the branch.
+    ///
+    pub fn has_constant_step(&self, tolerance: f64) -> bool {
+        self.fields.windows(2).all(|pair| (pair[1] - pair[0] - self.field_step).abs() <= tolerance)
+    }
+
+    ///
+    /// Interpolates the moment of the branch at a field.
+    ///
+    /// # Returns
+    ///
+    /// The moment, or `None` if the field lies outside the branch.
+    ///
+    pub fn interpolate(&self, b: f64, interpolation: LoopInterpolation) -> Option<f64> {
+        let (lo, hi) = self.field_range();
+        if b < lo || b > hi {
+            return None;
+        }
+        // Interpolation works on increasing fields.
+        if self.direction == BranchDirection::Descending {
+            let xs: Vec<f64> = self.fields.iter().rev().copied().collect();
+            let ys: Vec<f64> = self.moments.iter().rev().copied().collect();
+            Some(interpolate_loop_value(&xs, &ys, b, interpolation))
+        } else {
+            Some(interpolate_loop_value(&self.fields, &self.moments, b, interpolation))
+        }
+    }
+}
+
+///
+/// The hysteretic curves of a loop at one field.
+///
+/// # Fields
+///
+/// * `b` - The applied field (T).
+/// * `m_upper` - The moment on the upper (descending) branch (Am^2).
+/// * `m_lower` - The moment on the lower (ascending) branch (Am^2).
+/// * `delta_m` - The difference of the branches, `M_upper - M_lower` (Am^2).
+/// * `m_ih` - The induced hysteretic moment, `(M_upper + M_lower) / 2` (Am^2).
+/// * `m_rh` - The remanent hysteretic moment, `(M_upper - M_lower) / 2` (Am^2).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct HystereticCurveMeasurement {
+    pub b: f64,
+    pub m_upper: f64,
+    pub m_lower: f64,
+    pub delta_m: f64,
+    pub m_ih: f64,
+    pub m_rh: f64,
+}
+
+///
+/// A loop represented by its upper (descending) and lower (ascending) branches.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchedLoop {
+    pub upper: LoopBranch,
+    pub lower: LoopBranch,
+}
+
+impl BranchedLoop {
+    ///
+    /// Splits a curve, given as the field and the moment along the field at every step, at its
+    /// turning points and keeps the first descending branch and the first ascending branch that
+    /// follows it; e.g. an initial (virgin) ascending branch is skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `fields` - The applied field (T) at every step.
+    /// * `moments` - The moment along the field (Am^2) at every step.
+    ///
+    /// # Returns
+    ///
+    /// The `BranchedLoop`.
+    ///
+    /// # Errors
+    /// Returns an error if the curve has no descending branch followed by an ascending one.
+    ///
+    pub fn from_curve(fields: &[f64], moments: &[f64]) -> io::Result<BranchedLoop> {
+        let branches: Vec<LoopBranch> = field_branches(fields)
+            .iter()
+            .filter(|branch| branch.len() > 1)
+            .map(|branch| LoopBranch::from_indices(branch, fields, moments))
+            .collect();
+        let upper = branches.iter().position(|branch| branch.direction == BranchDirection::Descending);
+        let lower = upper.and_then(|upper| {
+            branches[upper..].iter().position(|branch| branch.direction == BranchDirection::Ascending).map(|k| upper + k)
+        });
+        match (upper, lower) {
+            (Some(upper), Some(lower)) => {
+                Ok(BranchedLoop { upper: branches[upper].clone(), lower: branches[lower].clone() })
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The loop has no descending branch followed by an ascending branch.",
+            )),
+        }
+    }
+
+    ///
+    /// Splits a single loop, using its moment along the field (see `from_curve`).
+    ///
+    pub fn from_loop(hysteresis_loop: &[MagneticLoopMeasurement]) -> io::Result<BranchedLoop> {
+        let fields: Vec<f64> = hysteresis_loop.iter().map(|measurement| measurement.b).collect();
+        let moments: Vec<f64> = hysteresis_loop.iter().map(|measurement| parallel_perpendicular(measurement).0).collect();
+        BranchedLoop::from_curve(&fields, &moments)
+    }
+
+    ///
+    /// Splits an averaged loop (see `from_curve`).
+    ///
+    pub fn from_averaged_loop(average_loop: &[AveragedMagneticLoopMeasurement]) -> io::Result<BranchedLoop> {
+        let fields: Vec<f64> = average_loop.iter().map(|value| value.b).collect();
+        let moments: Vec<f64> = average_loop.iter().map(|value| value.m).collect();
+        BranchedLoop::from_curve(&fields, &moments)
+    }
+
+    ///
+    /// Computes the hysteretic curves, the ΔM curve and the induced and remanent hysteretic
+    /// (Mih, Mrh) curves, over the field range shared by both branches. The curves are evaluated
+    /// at the fields of the upper branch, interpolating the lower branch.
+    ///
+    /// # Arguments
+    ///
+    /// * `interpolation` - How the lower branch is interpolated.
+    ///
+    /// # Returns
+    ///
+    /// One `HystereticCurveMeasurement` per field, in order of increasing field.
+    ///
+    pub fn hysteretic_curves(&self, interpolation: LoopInterpolation) -> Vec<HystereticCurveMeasurement> {
+        let mut curves: Vec<HystereticCurveMeasurement> = self
+            .upper
+            .fields
+            .iter()
+            .zip(self.upper.moments.iter())
+            .filter_map(|(b, m_upper)| {
+                self.lower.interpolate(*b, interpolation).map(|m_lower| HystereticCurveMeasurement {
+                    b: *b,
+                    m_upper: *m_upper,
+                    m_lower,
+                    delta_m: m_upper - m_lower,
+                    m_ih: 0.5 * (m_upper + m_lower),
+                    m_rh: 0.5 * (m_upper - m_lower),
+                })
+            })
+            .collect();
+        curves.sort_by(|a, b| a.b.total_cmp(&b.b));
+        curves
+    }
+}
+
+///
+/// A backfield (DC demagnetization) remanence curve: the remanent moment after saturating along
+/// the field direction and then applying (and removing) increasing fields in the opposite
+/// direction.
+///
+/// # Fields
+///
+/// * `source_file` - The file the curve was read from.
+/// * `fields` - The applied backfield (T) at every step, signed along the field direction.
+/// * `remanences` - The remanent moment along the field direction (Am^2) at every step.
+/// * `ms` - The saturation magnetization (A/m).
+/// * `vol` - The volume (m^3).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackfieldCurve {
+    pub source_file: String,
+    pub fields: Vec<f64>,
+    pub remanences: Vec<f64>,
+    pub ms: f64,
+    pub vol: f64,
+}
+
+impl BackfieldCurve {
+    ///
+    /// Builds a backfield curve from measurements in the layout of a loop file, using the
+    /// moment along the field direction as the remanence.
+    ///
+    pub fn from_measurements(source_file: &str, measurements: &[MagneticLoopMeasurement]) -> BackfieldCurve {
+        BackfieldCurve {
+            source_file: source_file.to_string(),
+            fields: measurements.iter().map(|measurement| measurement.b).collect(),
+            remanences: measurements.iter().map(|measurement| parallel_perpendicular(measurement).0).collect(),
+            ms: measurements.first().map_or(0.0, |measurement| measurement.ms),
+            vol: measurements.first().map_or(0.0, |measurement| measurement.vol),
+        }
+    }
+
+    ///
+    /// The coercivity of remanence Bcr (T), the size of the backfield at which the remanence
+    /// first changes sign, linearly interpolated.
+    ///
+    /// # Returns
+    ///
+    /// Bcr, or `None` if the remanence does not change sign.
+    ///
+    pub fn coercivity_of_remanence(&self) -> Option<f64> {
+        let steps: Vec<usize> = (0..self.fields.len()).collect();
+        value_at_crossing(&steps, &self.remanences, &self.fields).map(f64::abs)
+    }
+}
+
+///
+/// Reads a backfield remanence curve. MERRILL writes backfield runs in the layout of its loop
+/// files, so the file is read as a loop file (see `read_loop_file`).
+///
+/// # Arguments
+///
+/// * `file_name` - The backfield file.
+///
+/// # Returns
+///
+/// The `BackfieldCurve`.
+///
+/// # Errors
+/// Returns a `LoopFileError` if the file cannot be read or parsed.
+///
+pub fn read_backfield_file(file_name: &str) -> Result<BackfieldCurve, LoopFileError> {
+    read_loop_file(file_name).map(|measurements| BackfieldCurve::from_measurements(file_name, &measurements))
+}
+
+///
+/// Averages backfield curves measured at the same fields.
+///
+/// # Errors
+/// Returns an error if there are no curves, or the curves are not measured at the same fields.
+///
+pub fn average_backfield_curves(curves: &[BackfieldCurve]) -> io::Result<BackfieldCurve> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let first = curves.first().ok_or_else(|| invalid("No backfield curves found."))?;
+    if curves.iter().any(|curve| {
+        curve.fields.len() != first.fields.len()
+            || curve.fields.iter().zip(first.fields.iter()).any(|(a, b)| (a - b).abs() > 1e-12)
+    }) {
+        return Err(invalid("Backfield curves are not measured at the same fields."));
+    }
+
+    let n = curves.len() as f64;
+    Ok(BackfieldCurve {
+        source_file: curves.iter().map(|curve| curve.source_file.as_str()).collect::<Vec<&str>>().join(";"),
+        fields: first.fields.clone(),
+        remanences: (0..first.fields.len())
+            .map(|step| curves.iter().map(|curve| curve.remanences[step]).sum::<f64>() / n)
+            .collect(),
+        ms: curves.iter().map(|curve| curve.ms).sum::<f64>() / n,
+        vol: curves.iter().map(|curve| curve.vol).sum::<f64>() / n,
     })
+}
 
+impl HysteresisParameters {
+    ///
+    /// The ratio Bcr/Bc of the coercivity of remanence to the coercivity, which with Mrs/Ms
+    /// places a particle on a Day plot.
+    ///
+    /// # Returns
+    ///
+    /// Bcr/Bc, or `None` if the loop has no coercivity.
+    ///
+    pub fn bcr_bc(&self, bcr: f64) -> Option<f64> {
+        self.bc.filter(|bc| *bc > 0.0).map(|bc| bcr / bc)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::env;
+    use std::fs;
+    use std::io;
     use std::path::Path;
-    use crate::hysteresis_loops::{read_loop_file, read_loop_files, MagneticLoopMeasurement};
+    use crate::hysteresis_loops::{
+        average_backfield_curves,
+        averaged_loop_parameters,
+        read_backfield_file,
+        BranchDirection,
+        BranchedLoop,
+        hysteresis_parameters,
+        interpolate_loop_value,
+        loop_parameters,
+        read_averaged_loop_file,
+        read_loop_file,
+        read_loop_file_with_mode,
+        read_loop_files,
+        read_loop_files_with_mode,
+        write_averaged_loop_to_csv,
+        write_averaged_loop_to_json,
+        write_averaged_loop_to_loop_file,
+        LoopFileError,
+        LoopFileFormat,
+        LoopInterpolation,
+        LoopParseMode,
+        LoopWeighting,
+        MagneticLoopMeasurement,
+        direction_coverage,
+        solid_angle_weights,
+        StepStatistics,
+        write_vector_averaged_loop_to_csv,
+        MagneticLoopStack,
+    };
+
+    fn basic_loop_stack() -> MagneticLoopStack {
+        let root_file_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join("basic_loop_file");
+        let file_names: Vec<String> = ["basic1.loop", "basic2.loop"]
+            .iter()
+            .map(|name| root_file_path.join(name).to_str().unwrap().to_string())
+            .collect();
+        read_loop_files(&file_names).unwrap()
+    }
 
     #[test]
     fn test_read_loop_file() {
@@ -700,4 +2634,583 @@ mod tests {
             assert!((expected_data[i].vol - average_loop_stack[i].vol).abs() < 1e-12);
         }
     }
+
+    #[test]
+    fn test_loop_file_format() {
+        assert_eq!(LoopFileFormat::from_file_name("average.csv"), LoopFileFormat::Csv);
+        assert_eq!(LoopFileFormat::from_file_name("average.JSON"), LoopFileFormat::Json);
+        assert_eq!(LoopFileFormat::from_file_name("average.h5"), LoopFileFormat::Hdf5);
+        assert_eq!(LoopFileFormat::from_file_name("average.loop"), LoopFileFormat::Loop);
+        assert_eq!(LoopFileFormat::from_file_name("average"), LoopFileFormat::Loop);
+        assert_eq!(LoopFileFormat::from_name("hdf5"), Some(LoopFileFormat::Hdf5));
+        assert_eq!(LoopFileFormat::from_name("xml"), None);
+    }
+
+    #[test]
+    fn test_write_averaged_loop_to_loop_file() {
+        let loop_stack = basic_loop_stack();
+        let average_loop = loop_stack.average();
+        let file_name = env::temp_dir().join("tec2hdf5_test_average.loop");
+        let file_name = file_name.to_str().unwrap();
+        write_averaged_loop_to_loop_file(file_name, &average_loop, &loop_stack.metadata()).unwrap();
+
+        let contents = fs::read_to_string(file_name).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[0], "# hysteresis loops: 2");
+        assert!(lines[4].starts_with("# source file: ") && lines[4].ends_with("basic1.loop"));
+        assert_eq!(
+            lines[6],
+            "       B (Tesla),              Bx,              By,              Bz,     <Mx> (Am^2),     <My> (Am^2),     <Mz> (Am^2),        Ms (A/m),    Volume (m^3)"
+        );
+        assert!(lines[7].starts_with("  2.00000000E-01,  1.00000000E+00,  0.00000000E+00,  0.00000000E+00,"));
+        assert!(lines[7].ends_with(",  4.76799747E+05,  6.14125004E-22"));
+        assert_eq!(lines.len(), 7 + loop_stack.steps_per_hysteresis_loop);
+
+        // The file reads back as a loop file along x.
+        let read_loop = read_loop_file(file_name).unwrap();
+        assert_eq!(read_loop.len(), average_loop.len());
+        for (a, b) in read_loop.iter().zip(average_loop.iter()) {
+            assert!((a.b - b.b).abs() < 1e-12);
+            assert_eq!((a.bx, a.by, a.bz), (1.0, 0.0, 0.0));
+            assert!((a.mx - b.m).abs() <= 1e-8 * b.m.abs());
+            assert_eq!((a.my, a.mz), (0.0, 0.0));
+            assert!((a.ms - b.ms).abs() <= 1e-8 * b.ms);
+            assert!((a.vol - b.vol).abs() <= 1e-8 * b.vol);
+        }
+    }
+
+    #[test]
+    fn test_write_averaged_loop_to_loop_file_opposing_directions() {
+        // Along the +/- axis directions the moments cancel, but the moment along the field
+        // does not.
+        let loop_stack = direction_stack(&[
+            [1.0, 0.0, 0.0], [-1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0], [0.0, -1.0, 0.0],
+            [0.0, 0.0, 1.0], [0.0, 0.0, -1.0],
+        ]);
+        let average_loop = loop_stack.average();
+        assert!((average_loop[0].m - 1.0).abs() < 1e-12);
+
+        let file_name = env::temp_dir().join("tec2hdf5_test_average_opposing.loop");
+        let file_name = file_name.to_str().unwrap();
+        write_averaged_loop_to_loop_file(file_name, &average_loop, &loop_stack.metadata()).unwrap();
+
+        let read_loop = read_loop_file(file_name).unwrap();
+        assert!((read_loop[0].parallel_moment() - 1.0).abs() < 1e-12);
+        let read_loop = read_averaged_loop_file(file_name).unwrap();
+        assert!((read_loop[0].m - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_write_averaged_loop_to_csv() {
+        let loop_stack = basic_loop_stack();
+        let file_name = env::temp_dir().join("tec2hdf5_test_average.csv");
+        let file_name = file_name.to_str().unwrap();
+        write_averaged_loop_to_csv(file_name, &loop_stack.average(), &loop_stack.metadata()).unwrap();
+
+        let contents = fs::read_to_string(file_name).unwrap();
+        let rows: Vec<&str> = contents.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(rows[0], "B,M,Ms,Volume");
+        assert_eq!(rows.len(), 1 + loop_stack.steps_per_hysteresis_loop);
+        let values: Vec<f64> = rows[1].split(',').map(|value| value.parse().unwrap()).collect();
+        assert_eq!(values[0], 0.2);
+        assert_eq!(values[3], 6.14125004e-22);
+    }
+
+    #[test]
+    fn test_read_averaged_loop_file() {
+        let loop_stack = basic_loop_stack();
+        let average_loop = loop_stack.average();
+        for name in ["tec2hdf5_test_read_average.loop", "tec2hdf5_test_read_average.csv"] {
+            let file_name = env::temp_dir().join(name);
+            let file_name = file_name.to_str().unwrap();
+            if name.ends_with(".csv") {
+                write_averaged_loop_to_csv(file_name, &average_loop, &loop_stack.metadata()).unwrap();
+            } else {
+                write_averaged_loop_to_loop_file(file_name, &average_loop, &loop_stack.metadata()).unwrap();
+            }
+
+            let read_loop = read_averaged_loop_file(file_name).unwrap();
+            assert_eq!(read_loop.len(), average_loop.len());
+            for (a, b) in read_loop.iter().zip(average_loop.iter()) {
+                assert!((a.b - b.b).abs() < 1e-12);
+                assert!((a.m - b.m).abs() <= 1e-8 * b.m.abs());
+                assert!((a.vol - b.vol).abs() <= 1e-8 * b.vol);
+            }
+        }
+
+        // A loop file reads as its moment along the field.
+        let loop_file = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join("basic_loop_file")
+            .join("basic1.loop");
+        let read_loop = read_averaged_loop_file(loop_file.to_str().unwrap()).unwrap();
+        assert_eq!(read_loop.len(), loop_stack.steps_per_hysteresis_loop);
+        assert_eq!(read_loop[0].m, loop_stack.hysteresis_loops[0][0].parallel_moment());
+    }
+
+    #[test]
+    fn test_write_averaged_loop_to_json() {
+        let loop_stack = basic_loop_stack();
+        let file_name = env::temp_dir().join("tec2hdf5_test_average.json");
+        let file_name = file_name.to_str().unwrap();
+        write_averaged_loop_to_json(file_name, &loop_stack.average(), &loop_stack.metadata()).unwrap();
+
+        let contents = fs::read_to_string(file_name).unwrap();
+        assert!(contents.contains("\"no_of_hysteresis_loops\": 2,"));
+        assert!(contents.contains("\"field_start\": 2E-1,"));
+        assert!(contents.contains("basic2.loop\"]"));
+        assert!(contents.contains("\"b\": [2E-1, 1.999E-1, "));
+    }
+
+    #[test]
+    fn test_read_loop_file_reordered_columns() {
+        let loop_file = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join("loop_file_columns")
+            .join("reordered.loop");
+
+        let data = read_loop_file(loop_file.to_str().unwrap()).unwrap();
+
+        assert_eq!(data.len(), 2);
+        assert!((data[0].b - 0.2).abs() < 1e-12);
+        assert!((data[1].b - 0.1999).abs() < 1e-12);
+        assert!((data[0].bz - (-0.387755102)).abs() < 1e-12);
+        assert!((data[0].mx - 2.60122213e-16).abs() < 1e-28);
+        assert!((data[1].my - 2.64428752e-17).abs() < 1e-28);
+        assert!((data[0].ms - 476799.747).abs() < 1e-6);
+        assert!((data[0].vol - 6.14125004e-22).abs() < 1e-34);
+    }
+
+    #[test]
+    fn test_read_loop_file_malformed_row() {
+        let loop_file = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join("loop_file_columns")
+            .join("corrupt.loop");
+        let loop_file = loop_file.to_str().unwrap();
+
+        match read_loop_file(loop_file) {
+            Err(LoopFileError::MalformedRow { line, .. }) => assert_eq!(line, 3),
+            Err(error) => panic!("unexpected error: {}", error),
+            Ok(_) => panic!("expected an error"),
+        }
+
+        let (data, skipped) = read_loop_file_with_mode(loop_file, LoopParseMode::Lenient).unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(skipped.iter().map(|row| row.line).collect::<Vec<usize>>(), vec![3, 5]);
+        assert!(skipped[1].reason.contains("2.6011913#E-16"));
+    }
+
+    #[test]
+    fn test_read_loop_file_missing_column() {
+        let loop_file = env::temp_dir().join("tec2hdf5_test_missing_column.loop");
+        fs::write(&loop_file, "B (Tesla), Bx, By, Bz, <Mx> (Am^2), <My> (Am^2), <Mz> (Am^2), Volume (m^3)\n").unwrap();
+
+        match read_loop_file(loop_file.to_str().unwrap()) {
+            Err(LoopFileError::MissingColumn { column, .. }) => assert_eq!(column, "Ms"),
+            Err(error) => panic!("unexpected error: {}", error),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn test_read_loop_files_no_data_rows() {
+        let loop_file = env::temp_dir().join("tec2hdf5_test_no_data_rows.loop");
+        fs::write(
+            &loop_file,
+            "B (Tesla), Bx, By, Bz, <Mx> (Am^2), <My> (Am^2), <Mz> (Am^2), Ms (A/m), Volume (m^3)\n\
+             0.2, 1.0, 0.0, 0.0, 1.0e-16\n\
+             0.1, 1.0, 0.0, 0.0, x, 0.0, 0.0, 4.8e5, 1.0e-21\n",
+        )
+        .unwrap();
+        let file_names = vec![loop_file.to_str().unwrap().to_string()];
+
+        let (data, skipped) = read_loop_file_with_mode(&file_names[0], LoopParseMode::Lenient).unwrap();
+        assert!(data.is_empty());
+        assert_eq!(skipped.len(), 2);
+
+        let error = read_loop_files_with_mode(&file_names, LoopParseMode::Lenient).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().ends_with("no data rows"));
+    }
+
+    fn synthetic_loop(fields: &[f64]) -> Vec<MagneticLoopMeasurement> {
+        // Along the descending branch mx = 2 B, along the ascending branch mx = -2 B - 0.8, so
+        // that the loop is continuous at its turning point B = -0.2.
+        let mut ascending = false;
+        fields
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                if i > 0 && *b > fields[i - 1] {
+                    ascending = true;
+                }
+                MagneticLoopMeasurement {
+                    b: *b,
+                    bx: 1.0,
+                    by: 0.0,
+                    bz: 0.0,
+                    mx: if ascending { -2.0 * b - 0.8 } else { 2.0 * b },
+                    my: 0.0,
+                    mz: 0.0,
+                    ms: 480e3,
+                    vol: 1e-21,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resampled_loop_stack() {
+        let coarse = synthetic_loop(&[0.2, 0.1, 0.0, -0.1, -0.2, -0.1, 0.0, 0.1, 0.2]);
+        let truncated: Vec<f64> = (0..8)
+            .map(|i| 0.15 - 0.05 * i as f64)
+            .chain((1..=8).map(|i| -0.2 + 0.05 * i as f64))
+            .collect();
+        let fine = synthetic_loop(&truncated);
+
+        for interpolation in [LoopInterpolation::Linear, LoopInterpolation::MonotoneCubic] {
+            let (stack, report) = MagneticLoopStack::resampled(
+                vec![String::from("coarse.loop"), String::from("fine.loop")],
+                vec![coarse.clone(), fine.clone()],
+                interpolation,
+                None,
+            ).unwrap();
+
+            assert_eq!(report.no_of_branches, 2);
+            assert_eq!(report.clipped_loops, 1);
+            assert!((stack.field_step + 0.1).abs() < 1e-12);
+            assert!((stack.field_start - 0.15).abs() < 1e-12);
+            assert!((stack.field_end - 0.2).abs() < 1e-12);
+
+            // Four fields on the descending branch (0.15 down to -0.15), five on the ascending.
+            assert_eq!(stack.steps_per_hysteresis_loop, 9);
+            for hysteresis_loop in &stack.hysteresis_loops {
+                for (i, measurement) in hysteresis_loop.iter().enumerate() {
+                    let b = measurement.b;
+                    let expected = if i >= 4 { -2.0 * b - 0.8 } else { 2.0 * b };
+                    assert!((measurement.mx - expected).abs() < 1e-12);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_resampled_loop_stack_mismatched_branches() {
+        let descending = synthetic_loop(&[0.2, 0.1, 0.0]);
+        let ascending = synthetic_loop(&[0.0, 0.1, 0.2]);
+        let result = MagneticLoopStack::resampled(vec![], vec![descending, ascending], LoopInterpolation::Linear, None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_interpolate_loop_value_monotone() {
+        let xs = [0.0, 1.0, 2.0, 3.0];
+        let ys = [0.0, 0.0, 1.0, 1.0];
+
+        // The monotone cubic stays flat where the data is flat, and within the data between.
+        assert_eq!(interpolate_loop_value(&xs, &ys, 0.5, LoopInterpolation::MonotoneCubic), 0.0);
+        assert_eq!(interpolate_loop_value(&xs, &ys, 2.5, LoopInterpolation::MonotoneCubic), 1.0);
+        let middle = interpolate_loop_value(&xs, &ys, 1.25, LoopInterpolation::MonotoneCubic);
+        assert!(middle > 0.0 && middle < 0.25);
+        assert_eq!(interpolate_loop_value(&xs, &ys, 1.25, LoopInterpolation::Linear), 0.25);
+    }
+
+    #[test]
+    fn test_step_statistics() {
+        let statistics = StepStatistics::from_samples(&[1.0, 2.0, 3.0, 6.0]);
+        assert_eq!(statistics.mean, 3.0);
+        assert!((statistics.std_dev - (14.0_f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert!((statistics.std_error - statistics.std_dev / 2.0).abs() < 1e-12);
+        assert_eq!(statistics.min, 1.0);
+        assert_eq!(statistics.max, 6.0);
+
+        assert_eq!(StepStatistics::from_samples(&[2.0]).std_dev, 0.0);
+    }
+
+    #[test]
+    fn test_vector_average() {
+        // Two loops along +x and +y, the moment has a transverse component in each.
+        let measurement = |bx: f64, by: f64, mx: f64, my: f64| MagneticLoopMeasurement {
+            b: 0.1,
+            bx,
+            by,
+            bz: 0.0,
+            mx,
+            my,
+            mz: 0.0,
+            ms: 480e3,
+            vol: 1e-21,
+        };
+        let stack = MagneticLoopStack {
+            source_files: vec![],
+            hysteresis_loops: vec![vec![measurement(1.0, 0.0, 3.0, 4.0)], vec![measurement(0.0, 2.0, 0.0, 1.0)]],
+            no_of_hysteresis_loops: 2,
+            steps_per_hysteresis_loop: 1,
+            field_start: 0.1,
+            field_end: 0.1,
+            field_step: 0.0,
+        };
+
+        let average = stack.vector_average();
+        assert_eq!(average.len(), 1);
+        assert_eq!(average[0].mx.mean, 1.5);
+        assert_eq!(average[0].my.mean, 2.5);
+        assert_eq!(average[0].m_parallel.min, 1.0);
+        assert_eq!(average[0].m_parallel.max, 3.0);
+        assert_eq!(average[0].m_perpendicular.mean, 2.0);
+        assert!((average[0].m_perpendicular.std_dev - 8.0_f64.sqrt()).abs() < 1e-12);
+
+        let file_name = env::temp_dir().join("tec2hdf5_test_vector_average.csv");
+        let file_name = file_name.to_str().unwrap();
+        write_vector_averaged_loop_to_csv(file_name, &average, &stack.metadata()).unwrap();
+        let contents = fs::read_to_string(file_name).unwrap();
+        let rows: Vec<&str> = contents.lines().filter(|line| !line.starts_with('#')).collect();
+        assert!(rows[0].starts_with("B,Mx_mean,Mx_std,Mx_sem,Mx_min,Mx_max,My_mean,"));
+        assert!(rows[0].ends_with(",M_perp_max,Ms,Volume"));
+        assert_eq!(rows[1].split(',').count(), 28);
+    }
+
+    fn direction_stack(directions: &[[f64; 3]]) -> MagneticLoopStack {
+        // One single step loop per direction, with the moment along the field.
+        let hysteresis_loops: Vec<Vec<MagneticLoopMeasurement>> = directions
+            .iter()
+            .map(|d| vec![MagneticLoopMeasurement {
+                b: 0.1,
+                bx: d[0],
+                by: d[1],
+                bz: d[2],
+                mx: d[0],
+                my: d[1],
+                mz: d[2],
+                ms: 480e3,
+                vol: 1e-21,
+            }])
+            .collect();
+        MagneticLoopStack {
+            source_files: vec![],
+            no_of_hysteresis_loops: hysteresis_loops.len(),
+            hysteresis_loops,
+            steps_per_hysteresis_loop: 1,
+            field_start: 0.1,
+            field_end: 0.1,
+            field_step: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_solid_angle_weights() {
+        // The six +/- axis directions each own a sixth of the sphere.
+        let axes = [
+            [1.0, 0.0, 0.0], [-1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0], [0.0, -1.0, 0.0],
+            [0.0, 0.0, 1.0], [0.0, 0.0, -1.0],
+        ];
+        let weights = solid_angle_weights(&axes);
+        assert!(weights.iter().all(|w| (w - 1.0 / 6.0).abs() < 1e-3));
+
+        let coverage = direction_coverage(&axes);
+        assert!(coverage.anisotropy < 1e-12);
+        assert!((coverage.max_gap - (1.0_f64 / 3.0_f64.sqrt()).acos().to_degrees()).abs() < 1.0);
+
+        // The same axes sampled on a hemisphere cover the sphere in the same way.
+        let hemisphere = [[1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, 1.0]];
+        let weights = solid_angle_weights(&hemisphere);
+        assert!(weights.iter().all(|w| (w - 1.0 / 3.0).abs() < 1e-3));
+        let coverage = direction_coverage(&hemisphere);
+        assert!((coverage.max_gap - (1.0_f64 / 3.0_f64.sqrt()).acos().to_degrees()).abs() < 1.0);
+
+        // Two of three directions close together around +z, the third at -z, which is the +z
+        // axis and so lies between them. All the cell boundaries contain the x axis, so the
+        // cells are lunes: with the others at an angle a = atan(0.1 / 0.995) from the z axis,
+        // the cell of -z is a / pi and the others share the rest.
+        let clustered = [[0.0, 0.1, 0.995], [0.0, -0.1, 0.995], [0.0, 0.0, -1.0]];
+        let lune = (0.1_f64 / 0.995).atan() / std::f64::consts::PI;
+        let weights = solid_angle_weights(&clustered);
+        assert!((weights[2] - lune).abs() < 1e-3);
+        assert!((weights[0] - 0.5 * (1.0 - lune)).abs() < 1e-3);
+        let coverage = direction_coverage(&clustered);
+        assert!(coverage.anisotropy > 0.9);
+        assert!((coverage.weight_ratio - 0.5 * (1.0 - lune) / lune).abs() < 0.5);
+
+        // Repeated axes share their cell.
+        let weights = solid_angle_weights(&[[1.0, 0.0, 0.0], [1.0, 0.0, 0.0]]);
+        assert_eq!(weights, vec![0.5, 0.5]);
+        let weights = solid_angle_weights(&[[1.0, 0.0, 0.0], [-1.0, 0.0, 0.0]]);
+        assert_eq!(weights, vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_weighted_average() {
+        // Three loops near +x and one at +y, solid angle weights balance the cluster.
+        let directions = [[1.0, 0.0, 0.0], [0.995, 0.1, 0.0], [0.995, -0.1, 0.0], [0.0, 1.0, 0.0]];
+        let stack = direction_stack(&directions);
+
+        let uniform = stack.loop_weights(&LoopWeighting::Uniform).unwrap();
+        assert_eq!(uniform, vec![0.25; 4]);
+        let average = stack.weighted_vector_average(&uniform);
+        assert!((average[0].my.mean - 0.25).abs() < 1e-12);
+
+        let weights = stack.loop_weights(&LoopWeighting::SolidAngle).unwrap();
+        assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(weights[3] > weights[0] + weights[1]);
+
+        let user = stack.loop_weights(&LoopWeighting::User(vec![0.0, 0.0, 0.0, 2.0])).unwrap();
+        let average = stack.weighted_vector_average(&user);
+        assert_eq!(average[0].my.mean, 1.0);
+        assert_eq!(average[0].my.std_dev, 0.0);
+        assert_eq!(stack.weighted_average(&user)[0].m, 1.0);
+
+        assert!(stack.loop_weights(&LoopWeighting::User(vec![1.0])).is_err());
+    }
+
+    #[test]
+    fn test_hysteresis_parameters() {
+        // A square loop from +1 to -1 T and back, switching at -/+0.3 T, with Ms = 2 and a
+        // high-field slope of 0.5.
+        let mut fields: Vec<f64> = (0..=20).map(|i| 1.0 - 0.1 * i as f64).collect();
+        fields.extend((1..=20).map(|i| -1.0 + 0.1 * i as f64));
+        let moments: Vec<f64> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                let descending = i <= 20;
+                let switched = if descending { *b < -0.35 } else { *b > 0.35 };
+                let sign = if descending != switched { 1.0 } else { -1.0 };
+                2.0 * sign + 0.5 * b
+            })
+            .collect();
+
+        let parameters = hysteresis_parameters(&fields, &moments, Some(0.7)).unwrap();
+        assert_eq!(parameters.no_of_branches, 2);
+        assert!((parameters.high_field_slope - 0.5).abs() < 1e-12);
+        assert!((parameters.ms - 2.0).abs() < 1e-12);
+        assert!((parameters.mrs.unwrap() - 2.0).abs() < 1e-12);
+        assert!((parameters.squareness.unwrap() - 1.0).abs() < 1e-12);
+        // The corrected moment changes sign half way between -0.3 and -0.4 T (and back).
+        assert!((parameters.bc.unwrap() - 0.35).abs() < 1e-12);
+
+        // Without the correction, Ms includes the high-field contribution.
+        let parameters = hysteresis_parameters(&fields, &moments, None).unwrap();
+        assert!((parameters.ms - 2.5).abs() < 1e-12);
+        assert_eq!(parameters.high_field_slope, 0.0);
+
+        // A branch that never reaches zero field has no remanence or coercivity.
+        let parameters = hysteresis_parameters(&[1.0, 0.5, 0.2], &[2.0, 1.9, 1.8], None).unwrap();
+        assert_eq!(parameters.mrs, None);
+        assert_eq!(parameters.bc, None);
+        assert!(hysteresis_parameters(&[1.0, 0.5, 0.2], &[2.0, 1.9, 1.8], Some(0.7)).is_err());
+    }
+
+    #[test]
+    fn test_loop_parameters() {
+        // The basic loops only cover the start of the descending branch.
+        let loop_stack = basic_loop_stack();
+        let parameters = loop_parameters(&loop_stack.hysteresis_loops[0], None).unwrap();
+        assert!(parameters.ms > 0.0);
+        assert_eq!(parameters.no_of_branches, 1);
+        assert_eq!(parameters.mrs, None);
+
+        let average = averaged_loop_parameters(&loop_stack.average(), None).unwrap();
+        assert_eq!(average.no_of_branches, 1);
+        assert_eq!(average.bc, None);
+    }
+
+    #[test]
+    fn test_read_loop_files_with_branches() {
+        let root_file_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join("loop_branches");
+        let file_names: Vec<String> = ["full1.loop", "full2.loop"]
+            .iter()
+            .map(|name| root_file_path.join(name).to_str().unwrap().to_string())
+            .collect();
+
+        // The field step changes sign at the turning point (which is repeated in full2.loop).
+        let loop_stack = read_loop_files(&file_names[..1].to_vec()).unwrap();
+        assert!((loop_stack.field_step + 0.1).abs() < 1e-12);
+        assert_eq!(loop_stack.steps_per_hysteresis_loop, 9);
+        // The repeated turning point is dropped and reported, so that the loops stack.
+        let (both, skipped) = read_loop_files_with_mode(&file_names, LoopParseMode::Strict).unwrap();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!((skipped[0].file.as_str(), skipped[0].line), (file_names[1].as_str(), 7));
+        assert!(skipped[0].reason.contains("repeated turning field"));
+        assert_eq!(both.steps_per_hysteresis_loop, 9);
+        for (a, b) in both.hysteresis_loops[1].iter().zip(loop_stack.hysteresis_loops[0].iter()) {
+            assert_eq!((a.b, a.mx), (b.b, b.mx));
+        }
+
+        let branched = BranchedLoop::from_loop(&loop_stack.hysteresis_loops[0]).unwrap();
+        assert_eq!(branched.upper.direction, BranchDirection::Descending);
+        assert_eq!(branched.lower.direction, BranchDirection::Ascending);
+        assert_eq!(branched.upper.fields.len(), 5);
+        assert_eq!(branched.lower.fields, vec![-0.2, -0.1, 0.0, 0.1, 0.2]);
+        assert!((branched.lower.field_step - 0.1).abs() < 1e-12);
+        assert!(branched.upper.has_constant_step(1e-12));
+
+        let repeated = read_loop_files(&file_names[1..].to_vec()).unwrap();
+        assert_eq!(BranchedLoop::from_loop(&repeated.hysteresis_loops[0]).unwrap(), branched);
+
+        // A field repeated other than at a turning point is an error naming the file and line.
+        let contents = fs::read_to_string(&file_names[0]).unwrap();
+        let mut lines: Vec<&str> = contents.lines().collect();
+        lines.insert(2, lines[2]);
+        let repeated_file = env::temp_dir().join("tec2hdf5_test_repeated_field.loop");
+        fs::write(&repeated_file, lines.join("\n")).unwrap();
+        let repeated_file = repeated_file.to_str().unwrap().to_string();
+        let error = read_loop_files(&vec![repeated_file.clone()]).unwrap_err();
+        assert_eq!(error.to_string(), format!("{} line 4: the field value 0.1 T is repeated", repeated_file));
+        fs::remove_file(&repeated_file).unwrap();
+    }
+
+    #[test]
+    fn test_hysteretic_curves() {
+        // An initial ascending branch, then the upper and lower branches.
+        let fields = [0.0, 0.1, 0.2, 0.1, 0.0, -0.1, -0.2, -0.1, 0.0, 0.1, 0.2];
+        let moments = [0.0, 0.5, 1.0, 0.8, 0.5, -0.6, -1.0, -0.8, -0.5, 0.6, 1.0];
+        let branched = BranchedLoop::from_curve(&fields, &moments).unwrap();
+        assert_eq!(branched.upper.fields, vec![0.2, 0.1, 0.0, -0.1, -0.2]);
+        assert!((branched.upper.interpolate(0.05, LoopInterpolation::Linear).unwrap() - 0.65).abs() < 1e-12);
+        assert_eq!(branched.upper.interpolate(0.3, LoopInterpolation::Linear), None);
+
+        let curves = branched.hysteretic_curves(LoopInterpolation::Linear);
+        assert_eq!(curves.len(), 5);
+        assert_eq!(curves[0].b, -0.2);
+        assert!((curves[2].delta_m - 1.0).abs() < 1e-12);
+        assert!((curves[2].m_rh - 0.5).abs() < 1e-12);
+        assert!(curves[2].m_ih.abs() < 1e-12);
+        assert!((curves[3].m_ih - 0.7).abs() < 1e-12);
+        assert!((curves[3].delta_m - 0.2).abs() < 1e-12);
+        assert!(curves[4].delta_m.abs() < 1e-12);
+
+        assert!(BranchedLoop::from_curve(&[0.2, 0.1, 0.0], &[1.0, 0.8, 0.5]).is_err());
+    }
+
+    #[test]
+    fn test_read_backfield_file() {
+        let backfield_file = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join("backfield")
+            .join("backfield1.loop");
+        let curve = read_backfield_file(backfield_file.to_str().unwrap()).unwrap();
+        assert_eq!(curve.fields.len(), 5);
+        assert_eq!(curve.remanences[0], 4.8e-17);
+        assert_eq!(curve.ms, 4.8e5);
+
+        // The remanence changes sign a third of the way from -0.1 to -0.15 T.
+        let bcr = curve.coercivity_of_remanence().unwrap();
+        assert!((bcr - (0.1 + 0.05 / 3.0)).abs() < 1e-12);
+
+        let average = average_backfield_curves(&[curve.clone(), curve.clone()]).unwrap();
+        assert_eq!(average.remanences, curve.remanences);
+        let mut shifted = curve.clone();
+        shifted.fields[1] = -0.06;
+        assert!(average_backfield_curves(&[curve, shifted]).is_err());
+
+        let fields = [0.2, 0.0, -0.2, 0.0, 0.2];
+        let parameters = hysteresis_parameters(&fields, &[1.0, 0.5, -1.0, -0.5, 1.0], None).unwrap();
+        let bc = parameters.bc.unwrap();
+        assert!((parameters.bcr_bc(bcr).unwrap() - bcr / bc).abs() < 1e-12);
+    }
 }
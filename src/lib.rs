@@ -3,4 +3,17 @@ pub mod mesh;
 pub mod linalg;
 pub mod geometry;
 pub mod hysteresis_loops;
-mod hdf5_io;
\ No newline at end of file
+pub mod spatial_index;
+pub mod regular_grid;
+pub mod ovf_io;
+pub mod vtk_io;
+pub mod gmsh_io;
+pub mod patran_io;
+pub mod abaqus_io;
+pub mod surface_io;
+pub mod domain_state;
+pub mod vortex_core;
+pub mod field_transfer;
+pub mod grain_ensemble;
+pub mod forc;
+pub mod hdf5_io;
\ No newline at end of file
@@ -407,13 +407,15 @@ impl Inverse for [[f64; 4]; 4] {
 /// Given four vectors <v0[0], v0[1], v0[2]>, <v1[0], v1[1], v1[2]>, <v2[0], v2[1], v2[2]> and
 /// <v3[0], v3[1], v3[2]>, compute the edge-matrix:
 ///
+/// ```text
 ///     +-                                               -+
 ///     | v1[0] - v0[0]    v2[0] - v0[0]    v3[0] - v0[0] |
 ///     | v1[1] - v0[1]    v2[1] - v0[1]    v3[1] - v0[1] |
 ///     | v1[2] - v0[2]    v2[2] - v0[2]    v3[2] - v0[2] |
 ///     +-                                               -+
+/// ```
 ///
-fn edge_matrix(v0: [f64; 3], v1: [f64; 3], v2: [f64; 3], v3: [f64; 3]) -> [[f64; 3]; 3] {
+pub fn edge_matrix(v0: [f64; 3], v1: [f64; 3], v2: [f64; 3], v3: [f64; 3]) -> [[f64; 3]; 3] {
     [
         [v1[0] - v0[0], v2[0] - v0[0], v3[0] - v0[0]],
         [v1[1] - v0[1], v2[1] - v0[1], v3[1] - v0[1]],
@@ -421,6 +423,131 @@ fn edge_matrix(v0: [f64; 3], v1: [f64; 3], v2: [f64; 3], v3: [f64; 3]) -> [[f64;
     ]
 }
 
+///
+/// The Euclidean length of a vector.
+///
+pub(crate) fn norm(v: [f64; 3]) -> f64 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+///
+/// The dot product of two vectors.
+///
+pub(crate) fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+///
+/// The difference `a - b` of two vectors.
+///
+pub(crate) fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+///
+/// Computes the eigenvalues and eigenvectors of a symmetric 3x3 matrix using the cyclic Jacobi
+/// method.
+///
+/// Only the upper triangle of the matrix is assumed to be meaningful, it is mirrored before the
+/// iteration starts.
+///
+/// # Returns
+///
+/// The eigenvalues in ascending order, and the matching unit eigenvectors (the i-th row is the
+/// eigenvector of the i-th eigenvalue).
+///
+pub fn symmetric_eigen(m: [[f64; 3]; 3]) -> ([f64; 3], [[f64; 3]; 3]) {
+    const MAX_SWEEPS: usize = 50;
+    const PAIRS: [(usize, usize); 3] = [(0, 1), (0, 2), (1, 2)];
+
+    let mut a = m;
+    for (p, q) in PAIRS {
+        a[q][p] = a[p][q];
+    }
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+    let scale = a.iter().flatten().fold(0.0_f64, |acc, value| acc.max(value.abs()));
+    for _ in 0..MAX_SWEEPS {
+        let off_diagonal = PAIRS.iter().map(|(p, q)| a[*p][*q] * a[*p][*q]).sum::<f64>();
+        if off_diagonal <= (f64::EPSILON * scale).powi(2) {
+            break;
+        }
+        for (p, q) in PAIRS {
+            if a[p][q] == 0.0 {
+                continue;
+            }
+            // The rotation that zeroes a[p][q].
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+            for row in a.iter_mut() {
+                let (akp, akq) = (row[p], row[q]);
+                row[p] = c * akp - s * akq;
+                row[q] = s * akp + c * akq;
+            }
+            let (row_p, row_q) = (a[p], a[q]);
+            a[p] = [0, 1, 2].map(|k| c * row_p[k] - s * row_q[k]);
+            a[q] = [0, 1, 2].map(|k| s * row_p[k] + c * row_q[k]);
+            for row in v.iter_mut() {
+                let (vkp, vkq) = (row[p], row[q]);
+                row[p] = c * vkp - s * vkq;
+                row[q] = s * vkp + c * vkq;
+            }
+        }
+    }
+
+    // The eigenvectors are the columns of v.
+    let mut order = [0usize, 1, 2];
+    order.sort_by(|i, j| a[*i][*i].total_cmp(&a[*j][*j]));
+    let values = order.map(|i| a[i][i]);
+    let vectors = order.map(|i| [v[0][i], v[1][i], v[2][i]]);
+    (values, vectors)
+}
+
+///
+/// Solves the linear system `a x = b` by Gaussian elimination with partial pivoting.
+///
+/// # Returns
+///
+/// The solution `x`, or `None` if the matrix is (numerically) singular.
+///
+pub fn solve_linear_system<const N: usize>(a: [[f64; N]; N], b: [f64; N]) -> Option<[f64; N]> {
+    let mut a = a;
+    let mut b = b;
+    let scale = a.iter().flatten().fold(0.0_f64, |acc, value| acc.max(value.abs()));
+    if scale == 0.0 {
+        return None;
+    }
+
+    for column in 0..N {
+        // Swap the row with the largest pivot into place.
+        let pivot = (column..N).max_by(|i, j| a[*i][column].abs().total_cmp(&a[*j][column].abs()))?;
+        if a[pivot][column].abs() <= 1e-12 * scale {
+            return None;
+        }
+        a.swap(column, pivot);
+        b.swap(column, pivot);
+
+        let pivot_row = a[column];
+        for row in column + 1..N {
+            let factor = a[row][column] / pivot_row[column];
+            for (value, pivot_value) in a[row].iter_mut().zip(pivot_row.iter()).skip(column) {
+                *value -= factor * pivot_value;
+            }
+            b[row] -= factor * b[column];
+        }
+    }
+
+    // Back substitution.
+    let mut x = [0.0; N];
+    for row in (0..N).rev() {
+        let sum: f64 = (row + 1..N).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
 //----------------------------------------------------------------------------------------------//
 //- Tests                                                                                      -//
 //----------------------------------------------------------------------------------------------//
@@ -760,4 +887,57 @@ mod tests {
             }
         }
     }
+
+    //..........................................................................................//
+    //. symmetric_eigen()                                                                      .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_symmetric_eigen() {
+        let m = [
+            [4.0, 1.0, -2.0],
+            [1.0, 2.0, 0.5],
+            [-2.0, 0.5, 3.0],
+        ];
+        let (values, vectors) = symmetric_eigen(m);
+
+        assert!(values[0] <= values[1] && values[1] <= values[2]);
+        assert!((values.iter().sum::<f64>() - 9.0).abs() < 1e-12);
+        assert!((values[0] * values[1] * values[2] - m.determinant()).abs() < 1e-12);
+
+        for (value, vector) in values.iter().zip(vectors.iter()) {
+            let length = vector.iter().map(|x| x * x).sum::<f64>().sqrt();
+            assert!((length - 1.0).abs() < 1e-12);
+            for i in 0..3 {
+                let mv = (0..3).map(|j| m[i][j] * vector[j]).sum::<f64>();
+                assert!((mv - value * vector[i]).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_symmetric_eigen_diagonal() {
+        let (values, vectors) = symmetric_eigen([[3.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 2.0]]);
+        assert_eq!(values, [1.0, 2.0, 3.0]);
+        assert_eq!(vectors, [[0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]]);
+    }
+
+    //..........................................................................................//
+    //. solve_linear_system()                                                                  .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_solve_linear_system() {
+        let a = [
+            [0.0, 2.0, 1.0],
+            [1.0, -1.0, 3.0],
+            [4.0, 1.0, -2.0],
+        ];
+        let expected = [1.0, -2.0, 3.0];
+        let b = [0, 1, 2].map(|i| (0..3).map(|j| a[i][j] * expected[j]).sum::<f64>());
+        let x = solve_linear_system(a, b).unwrap();
+        assert!((0..3).all(|i| (x[i] - expected[i]).abs() < 1e-12));
+
+        assert_eq!(solve_linear_system([[1.0, 2.0], [2.0, 4.0]], [1.0, 2.0]), None);
+    }
 }
@@ -1,7 +1,17 @@
+use std::collections::HashMap;
+
 use crate::geometry::{
     tet_volume,
-    tet_lin_vec_integral
+    tet_lin_vec_integral,
+    tet_barycentric,
+    tet_lin_vec_curl,
+    tet_lin_vec_divergence,
+    triangle_area,
+    triangle_area_vector
 };
+use crate::linalg::{dot, norm, symmetric_eigen, sub};
+use crate::spatial_index::SpatialIndex;
+use crate::regular_grid::{GridField, RegularGrid};
 
 /// Represents a vector field associated with a mesh, containing a label and a collection of 3D
 /// vectors.
@@ -19,6 +29,536 @@ pub struct Field {
     pub vectors: Vec<[f64; 3]>,
 }
 
+///
+/// A single sample of a field taken at an arbitrary point.
+///
+/// # Fields
+///
+/// * `point` - The point at which the field was sampled.
+/// * `value` - The linearly interpolated field vector, or `None` if the point lies outside the
+///   mesh.
+///
+#[derive(Debug, Clone)]
+pub struct FieldSample {
+    pub point: [f64; 3],
+    pub value: Option<[f64; 3]>,
+}
+
+///
+/// Statistics of the magnitudes `|m|` of the vectors of a field.
+///
+/// # Fields
+///
+/// * `min` - The smallest magnitude.
+/// * `max` - The largest magnitude.
+/// * `mean` - The mean magnitude over all nodes.
+/// * `off_count` - The number of nodes whose magnitude differs from one by more than the
+///   tolerance.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormStatistics {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub off_count: usize,
+}
+
+impl Field {
+    ///
+    /// Computes statistics of the vector magnitudes of the field, MERRILL fields should have
+    /// `|m| = 1` at every node.
+    ///
+    /// # Arguments
+    ///
+    /// * `tolerance` - The largest allowed deviation of `|m|` from one.
+    ///
+    /// # Returns
+    ///
+    /// The `NormStatistics` of the field, all zero if the field has no vectors.
+    ///
+    pub fn norm_statistics(&self, tolerance: f64) -> NormStatistics {
+        if self.vectors.is_empty() {
+            return NormStatistics { min: 0.0, max: 0.0, mean: 0.0, off_count: 0 };
+        }
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        let mut sum = 0.0;
+        let mut off_count = 0;
+        for m in &self.vectors {
+            let length = norm(*m);
+            min = min.min(length);
+            max = max.max(length);
+            sum += length;
+            if (length - 1.0).abs() > tolerance {
+                off_count += 1;
+            }
+        }
+        NormStatistics { min, max, mean: sum / self.vectors.len() as f64, off_count }
+    }
+
+    ///
+    /// Rescales every vector of the field to unit length; zero vectors have no direction and
+    /// are left unchanged.
+    ///
+    /// # Returns
+    ///
+    /// The number of zero vectors that could not be normalized.
+    ///
+    pub fn normalize(&mut self) -> usize {
+        let mut zero_count = 0;
+        for m in self.vectors.iter_mut() {
+            let length = norm(*m);
+            if length > 0.0 {
+                *m = m.map(|component| component / length);
+            } else {
+                zero_count += 1;
+            }
+        }
+        zero_count
+    }
+
+    ///
+    /// Checks that two fields have a vector at each of the same nodes.
+    ///
+    fn assert_same_length(&self, other: &Field) {
+        assert_eq!(
+            self.vectors.len(),
+            other.vectors.len(),
+            "Fields {} and {} have different numbers of vectors",
+            self.label, other.label
+        );
+    }
+
+    ///
+    /// Computes the difference `self - other` of two fields on the same mesh.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The field to subtract, with the same number of vectors as this field.
+    ///
+    /// # Returns
+    ///
+    /// The difference field, labelled `"<self> - <other>"`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fields have different numbers of vectors.
+    ///
+    pub fn difference(&self, other: &Field) -> Field {
+        self.assert_same_length(other);
+        Field {
+            label: format!("{} - {}", self.label.trim_matches('"'), other.label.trim_matches('"')),
+            vectors: self
+                .vectors
+                .iter()
+                .zip(other.vectors.iter())
+                .map(|(a, b)| sub(*a, *b))
+                .collect(),
+        }
+    }
+
+    ///
+    /// Computes the dot product of two fields at each node.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The other field, with the same number of vectors as this field.
+    ///
+    /// # Returns
+    ///
+    /// One dot product per node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fields have different numbers of vectors.
+    ///
+    pub fn dot(&self, other: &Field) -> Vec<f64> {
+        self.assert_same_length(other);
+        self.vectors
+            .iter()
+            .zip(other.vectors.iter())
+            .map(|(a, b)| dot(*a, *b))
+            .collect()
+    }
+
+    ///
+    /// Computes the angle between two fields at each node.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The other field, with the same number of vectors as this field.
+    ///
+    /// # Returns
+    ///
+    /// One angle, in degrees between 0 and 180, per node; nodes where either vector is zero
+    /// have no direction and an angle of zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fields have different numbers of vectors.
+    ///
+    pub fn angles(&self, other: &Field) -> Vec<f64> {
+        self.assert_same_length(other);
+        self.vectors
+            .iter()
+            .zip(other.vectors.iter())
+            .map(|(a, b)| {
+                let lengths = norm(*a) * norm(*b);
+                if lengths > 0.0 {
+                    let cosine = dot(*a, *b) / lengths;
+                    cosine.clamp(-1.0, 1.0).acos().to_degrees()
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    ///
+    /// Computes the largest angle between two fields over all nodes.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The other field, with the same number of vectors as this field.
+    ///
+    /// # Returns
+    ///
+    /// The maximum angular deviation in degrees (see `angles`).
+    ///
+    pub fn max_angular_deviation(&self, other: &Field) -> f64 {
+        self.angles(other).into_iter().fold(0.0, f64::max)
+    }
+
+    ///
+    /// Computes the volume weighted root mean square difference of two fields,
+    /// `sqrt(int |a - b|^2 dV / V)`, integrating the linear interpolation of the difference
+    /// exactly over each element.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh on which both fields are defined.
+    /// * `other` - The other field.
+    ///
+    /// # Returns
+    ///
+    /// The RMS difference, or zero for a mesh without volume.
+    ///
+    pub fn rms_difference(&self, mesh: &Mesh, other: &Field) -> f64 {
+        let difference = self.difference(other);
+        let mut integral = 0.0;
+        let mut volume = 0.0;
+        for element in &mesh.elements {
+            let [v0, v1, v2, v3] = element.map(|vertex| mesh.vertices[vertex]);
+            let d = element.map(|vertex| difference.vectors[vertex]);
+            let element_volume = tet_volume(v0, v1, v2, v3).abs();
+
+            // For a linear function f over a tetrahedron, int f^2 dV = V / 20 (sum f_a^2 + (sum f_a)^2).
+            for i in 0..3 {
+                let squares: f64 = d.iter().map(|vector| vector[i] * vector[i]).sum();
+                let sum: f64 = d.iter().map(|vector| vector[i]).sum();
+                integral += element_volume / 20.0 * (squares + sum * sum);
+            }
+            volume += element_volume;
+        }
+        if volume > 0.0 { (integral / volume).sqrt() } else { 0.0 }
+    }
+
+    ///
+    /// Samples the field at an arbitrary point by linear interpolation over the element that
+    /// contains the point.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh on which the field is defined.
+    /// * `point` - The point at which to sample the field.
+    ///
+    /// # Returns
+    ///
+    /// The interpolated field vector, or `None` if the point lies outside the mesh.
+    ///
+    pub fn sample(&self, mesh: &Mesh, point: [f64; 3]) -> Option<[f64; 3]> {
+        let (element_index, l) = mesh.locate(point)?;
+        let element = &mesh.elements[element_index];
+        let mut value = [0.0; 3];
+        for (vertex_index, weight) in element.iter().zip(l.iter()) {
+            let vector = &self.vectors[*vertex_index];
+            for i in 0..3 {
+                value[i] += weight * vector[i];
+            }
+        }
+        Some(value)
+    }
+
+    ///
+    /// Samples the field at `n` equally spaced points on the line segment from `start` to `end`
+    /// (both inclusive).
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh on which the field is defined.
+    /// * `start` - The first point of the line.
+    /// * `end` - The last point of the line.
+    /// * `n` - The number of sample points.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `n` samples ordered from `start` to `end`.
+    ///
+    pub fn sample_line(
+        &self,
+        mesh: &Mesh,
+        start: [f64; 3],
+        end: [f64; 3],
+        n: usize,
+    ) -> Vec<FieldSample> {
+        let origin = start;
+        let direction = [end[0] - start[0], end[1] - start[1], end[2] - start[2]];
+        self.sample_grid(mesh, origin, [direction, [0.0; 3], [0.0; 3]], [n, 1, 1])
+    }
+
+    ///
+    /// Samples the field on a regular grid of points in a plane, the points are
+    /// `origin + s u + t v` for `nu` equally spaced values of `s` and `nv` equally spaced values
+    /// of `t` in `[0, 1]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh on which the field is defined.
+    /// * `origin` - A corner of the plane grid.
+    /// * `u` - The first edge of the plane grid.
+    /// * `v` - The second edge of the plane grid.
+    /// * `nu` - The number of points along `u`.
+    /// * `nv` - The number of points along `v`.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `nu * nv` samples with the `u` direction varying fastest.
+    ///
+    pub fn sample_plane(
+        &self,
+        mesh: &Mesh,
+        origin: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        nu: usize,
+        nv: usize,
+    ) -> Vec<FieldSample> {
+        self.sample_grid(mesh, origin, [u, v, [0.0; 3]], [nu, nv, 1])
+    }
+
+    ///
+    /// Samples the field on a regular 3D lattice spanning the axis aligned box from `min` to
+    /// `max` (both inclusive).
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh on which the field is defined.
+    /// * `min` - The lower corner of the box.
+    /// * `max` - The upper corner of the box.
+    /// * `counts` - The number of lattice points along x, y and z.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `counts[0] * counts[1] * counts[2]` samples with x varying fastest, then y,
+    /// then z.
+    ///
+    pub fn sample_lattice(
+        &self,
+        mesh: &Mesh,
+        min: [f64; 3],
+        max: [f64; 3],
+        counts: [usize; 3],
+    ) -> Vec<FieldSample> {
+        let axes = [
+            [max[0] - min[0], 0.0, 0.0],
+            [0.0, max[1] - min[1], 0.0],
+            [0.0, 0.0, max[2] - min[2]],
+        ];
+        self.sample_grid(mesh, min, axes, counts)
+    }
+
+    ///
+    /// Resamples the field onto the cell centres of a regular grid by linear interpolation.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh on which the field is defined.
+    /// * `grid` - The grid onto which the field is resampled.
+    ///
+    /// # Returns
+    ///
+    /// A `GridField` holding one vector per grid cell; cells whose centres lie outside the mesh
+    /// are masked out and hold zero vectors.
+    ///
+    pub fn resample(&self, mesh: &Mesh, grid: &RegularGrid) -> GridField {
+        let mut vectors = vec![[0.0; 3]; grid.no_of_cells()];
+        let mut mask = vec![false; grid.no_of_cells()];
+        for k in 0..grid.dims[2] {
+            for j in 0..grid.dims[1] {
+                for i in 0..grid.dims[0] {
+                    let index = grid.cell_index(i, j, k);
+                    if let Some(value) = self.sample(mesh, grid.cell_centre(i, j, k)) {
+                        vectors[index] = value;
+                        mask[index] = true;
+                    }
+                }
+            }
+        }
+        GridField {
+            label: self.label.clone(),
+            grid: grid.clone(),
+            vectors,
+            mask,
+        }
+    }
+
+    ///
+    /// Samples the field at the points `origin + sum_k t_k axes[k]`, where each `t_k` takes
+    /// `counts[k]` equally spaced values in `[0, 1]` (or just `0` if `counts[k]` is one).
+    ///
+    fn sample_grid(
+        &self,
+        mesh: &Mesh,
+        origin: [f64; 3],
+        axes: [[f64; 3]; 3],
+        counts: [usize; 3],
+    ) -> Vec<FieldSample> {
+        let fraction = |index: usize, count: usize| -> f64 {
+            if count > 1 {
+                index as f64 / (count - 1) as f64
+            } else {
+                0.0
+            }
+        };
+
+        let mut samples = Vec::with_capacity(counts[0] * counts[1] * counts[2]);
+        for k in 0..counts[2] {
+            for j in 0..counts[1] {
+                for i in 0..counts[0] {
+                    let t = [
+                        fraction(i, counts[0]),
+                        fraction(j, counts[1]),
+                        fraction(k, counts[2]),
+                    ];
+                    let mut point = origin;
+                    for (axis, t_axis) in axes.iter().zip(t.iter()) {
+                        for c in 0..3 {
+                            point[c] += t_axis * axis[c];
+                        }
+                    }
+                    samples.push(FieldSample {
+                        point,
+                        value: self.sample(mesh, point),
+                    });
+                }
+            }
+        }
+        samples
+    }
+}
+
+///
+/// A triangular face on the boundary of a mesh.
+///
+/// # Fields
+///
+/// * `vertices` - The vertex indices of the face, ordered so that the right hand rule normal
+///   points out of the mesh.
+/// * `element` - The index of the (only) element that the face belongs to.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundaryFace {
+    pub vertices: [usize; 3],
+    pub element: usize,
+}
+
+///
+/// A triangular face shared by two elements belonging to different subdomains.
+///
+/// # Fields
+///
+/// * `vertices` - The vertex indices of the face, ordered so that the right hand rule normal
+///   points out of `elements[0]` and into `elements[1]`.
+/// * `elements` - The indices of the two elements sharing the face.
+/// * `subdomains` - The submesh indices of the two elements, `subdomains[0] < subdomains[1]`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceFace {
+    pub vertices: [usize; 3],
+    pub elements: [usize; 2],
+    pub subdomains: [usize; 2],
+}
+
+///
+/// The magnetic charges of a (magnetization) field on a mesh.
+///
+/// The surface charge density is `sigma = M . n` on the boundary and the volume charge density
+/// is `rho = -div M`, using the linear interpolation of `M` over each element. By the divergence
+/// theorem the total charge `surface + volume` vanishes, so its size relative to the absolute
+/// charges is a check on the quality of the mesh and the field.
+///
+/// # Fields
+///
+/// * `surface` - The net surface charge, the integral of `sigma` over the boundary.
+/// * `volume` - The net volume charge, the integral of `rho` over the mesh.
+/// * `surface_abs` - The sum of the magnitudes of the surface charges on each boundary face.
+/// * `volume_abs` - The sum of the magnitudes of the volume charges in each element.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct MagneticCharges {
+    pub surface: f64,
+    pub volume: f64,
+    pub surface_abs: f64,
+    pub volume_abs: f64,
+}
+
+impl MagneticCharges {
+    ///
+    /// The total (surface plus volume) charge, this should be close to zero.
+    ///
+    pub fn total(&self) -> f64 {
+        self.surface + self.volume
+    }
+
+    ///
+    /// A measure of how charged a state is: the absolute surface and volume charges divided by
+    /// the surface area, which is dimensionless for a unit field. A uniform (single domain)
+    /// state has large surface charges, a flower state somewhat less, and a vortex state, which
+    /// avoids surface charges, very little.
+    ///
+    /// # Arguments
+    ///
+    /// * `surface_area` - The area of the mesh's boundary surface.
+    ///
+    /// # Returns
+    ///
+    /// The normalised charge, or `None` if the surface area is not positive.
+    ///
+    pub fn normalised(&self, surface_area: f64) -> Option<f64> {
+        if surface_area <= 0.0 {
+            return None;
+        }
+        Some((self.surface_abs + self.volume_abs) / surface_area)
+    }
+}
+
+///
+/// The principal axes of a mesh, treated as a solid of uniform density.
+///
+/// # Fields
+///
+/// * `centroid` - The centroid of the mesh.
+/// * `moments` - The principal moments of inertia (per unit density), in ascending order.
+/// * `axes` - The unit principal axes, `axes[i]` belongs to `moments[i]`; the first is the axis
+///   about which the mesh is easiest to rotate, i.e. the long axis of an elongated particle.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrincipalAxes {
+    pub centroid: [f64; 3],
+    pub moments: [f64; 3],
+    pub axes: [[f64; 3]; 3],
+}
+
 ///
 /// Represents a 3D mesh model, including its geometry, connectivity, and associated data fields.
 ///
@@ -34,6 +574,11 @@ pub struct Field {
 ///              such as vector fields or other data.
 /// * `volume` - An optional precomputed volume of the mesh (if available). If `None`,
 ///              the volume can be computed using the appropriate method.
+/// * `spatial_index` - An optional precomputed spatial index used to accelerate point location.
+///   If `None`, point location falls back to checking every element.
+/// * `surface_area` - An optional precomputed area of the mesh's boundary surface.
+/// * `charges` - Optional precomputed magnetic charges, one entry per field.
+/// * `helicities` - Optional precomputed helicities (integrals of `m . curl m`), one per field.
 ///
 /// The `Mesh` struct provides methods for creating new meshes, as well as computing derived
 /// quantities such as volume.
@@ -46,6 +591,10 @@ pub struct Mesh {
     pub fields: Vec<Field>,
     pub volume: Option<f64>,
     pub net_moments: Option<Vec<[f64; 3]>>,
+    pub spatial_index: Option<SpatialIndex>,
+    pub surface_area: Option<f64>,
+    pub charges: Option<Vec<MagneticCharges>>,
+    pub helicities: Option<Vec<f64>>,
 }
 
 impl Mesh {
@@ -84,6 +633,10 @@ impl Mesh {
             fields,
             volume: None,
             net_moments: None,
+            spatial_index: None,
+            surface_area: None,
+            charges: None,
+            helicities: None,
         }
     }
 
@@ -154,4 +707,810 @@ impl Mesh {
         }
         self.net_moments = Some(net_moments);
     }
+
+    ///
+    /// The four faces of an element, each ordered so that the right hand rule normal points out
+    /// of the element (whatever the orientation of the element itself).
+    ///
+    fn element_faces(&self, element_index: usize) -> [[usize; 3]; 4] {
+        let element = &self.elements[element_index];
+        let mut faces = [[0usize; 3]; 4];
+        for (opposite, face) in faces.iter_mut().enumerate() {
+            let mut vertices = [0usize; 3];
+            let mut index = 0;
+            for (local, vertex) in element.iter().enumerate() {
+                if local != opposite {
+                    vertices[index] = *vertex;
+                    index += 1;
+                }
+            }
+            let p = self.vertices[vertices[0]];
+            let n = triangle_area_vector(p, self.vertices[vertices[1]], self.vertices[vertices[2]]);
+            let d = self.vertices[element[opposite]];
+            let inward = n[0] * (d[0] - p[0]) + n[1] * (d[1] - p[1]) + n[2] * (d[2] - p[2]) > 0.0;
+            if inward {
+                vertices.swap(1, 2);
+            }
+            *face = vertices;
+        }
+        faces
+    }
+
+    ///
+    /// Maps each face of the mesh (keyed by its sorted vertex indices) to the elements that
+    /// share it.
+    ///
+    fn face_elements(&self) -> HashMap<[usize; 3], Vec<usize>> {
+        let mut face_elements: HashMap<[usize; 3], Vec<usize>> = HashMap::new();
+        for element_index in 0..self.elements.len() {
+            for face in self.element_faces(element_index) {
+                let mut key = face;
+                key.sort_unstable();
+                face_elements.entry(key).or_default().push(element_index);
+            }
+        }
+        face_elements
+    }
+
+    ///
+    /// Finds the faces on the boundary of the mesh, i.e. those faces that belong to exactly one
+    /// element.
+    ///
+    /// # Returns
+    ///
+    /// The boundary faces, oriented so that their (right hand rule) normals point out of the
+    /// mesh. Faces are listed in element order.
+    ///
+    pub fn boundary_faces(&self) -> Vec<BoundaryFace> {
+        let face_elements = self.face_elements();
+        let mut boundary_faces = vec![];
+        for element_index in 0..self.elements.len() {
+            for face in self.element_faces(element_index) {
+                let mut key = face;
+                key.sort_unstable();
+                if face_elements[&key].len() == 1 {
+                    boundary_faces.push(BoundaryFace { vertices: face, element: element_index });
+                }
+            }
+        }
+        boundary_faces
+    }
+
+    ///
+    /// Finds the faces shared by elements of different subdomains (see `submesh_indices`).
+    ///
+    /// # Returns
+    ///
+    /// The interface faces, each oriented so that its normal points out of the element with the
+    /// lower submesh index. The faces between a particular pair of subdomains can be picked out
+    /// using the `subdomains` field. Faces are listed in element order. A mesh without a submesh
+    /// index for each element has no interface faces.
+    ///
+    pub fn interface_faces(&self) -> Vec<InterfaceFace> {
+        if self.submesh_indices.len() != self.elements.len() {
+            return vec![];
+        }
+        let face_elements = self.face_elements();
+        let mut interface_faces = vec![];
+        for element_index in 0..self.elements.len() {
+            let subdomain = self.submesh_indices[element_index];
+            for face in self.element_faces(element_index) {
+                let mut key = face;
+                key.sort_unstable();
+                let elements = &face_elements[&key];
+                if elements.len() != 2 {
+                    continue;
+                }
+                let neighbour = if elements[0] == element_index { elements[1] } else { elements[0] };
+                let neighbour_subdomain = self.submesh_indices[neighbour];
+                if subdomain < neighbour_subdomain {
+                    interface_faces.push(InterfaceFace {
+                        vertices: face,
+                        elements: [element_index, neighbour],
+                        subdomains: [subdomain, neighbour_subdomain],
+                    });
+                }
+            }
+        }
+        interface_faces
+    }
+
+    ///
+    /// Computes the area of the mesh's boundary surface, the result is cached in the
+    /// `surface_area` field.
+    ///
+    pub fn compute_surface_area(&mut self) {
+        let surface_area = self
+            .boundary_faces()
+            .iter()
+            .map(|face| {
+                triangle_area(
+                    self.vertices[face.vertices[0]],
+                    self.vertices[face.vertices[1]],
+                    self.vertices[face.vertices[2]],
+                )
+            })
+            .sum();
+        self.surface_area = Some(surface_area);
+    }
+
+    ///
+    /// Computes the magnetic surface and volume charges of each field (see `MagneticCharges`),
+    /// the result is cached in the `charges` field.
+    ///
+    /// The surface charge on each boundary face is `(M_avg . n) A`, where `M_avg` is the mean of
+    /// the field at the face's vertices (this is exact for a linear field), and the volume charge
+    /// of each element is `-div M |V|`. Degenerate elements do not contribute.
+    ///
+    pub fn compute_charges(&mut self) {
+        let boundary_faces = self.boundary_faces();
+        let mut charges = Vec::with_capacity(self.fields.len());
+        for field in &self.fields {
+            let mut field_charges = MagneticCharges {
+                surface: 0.0,
+                volume: 0.0,
+                surface_abs: 0.0,
+                volume_abs: 0.0,
+            };
+
+            for face in &boundary_faces {
+                let [v0, v1, v2] = face.vertices.map(|vertex| self.vertices[vertex]);
+                let area_vector = triangle_area_vector(v0, v1, v2);
+                let mut charge = 0.0;
+                for vertex in face.vertices {
+                    let m = field.vectors[vertex];
+                    let m_dot_n = dot(m, area_vector);
+                    charge += m_dot_n / 3.0;
+                }
+                field_charges.surface += charge;
+                field_charges.surface_abs += charge.abs();
+            }
+
+            for element in &self.elements {
+                let [v0, v1, v2, v3] = element.map(|vertex| self.vertices[vertex]);
+                let [f0, f1, f2, f3] = element.map(|vertex| field.vectors[vertex]);
+                if let Some(divergence) = tet_lin_vec_divergence(v0, v1, v2, v3, f0, f1, f2, f3) {
+                    let charge = -divergence * tet_volume(v0, v1, v2, v3).abs();
+                    field_charges.volume += charge;
+                    field_charges.volume_abs += charge.abs();
+                }
+            }
+
+            charges.push(field_charges);
+        }
+        self.charges = Some(charges);
+    }
+
+    ///
+    /// Checks whether two meshes are the same, i.e. have the same connectivity and (within a
+    /// tolerance) the same vertices, so that their fields can be compared node by node.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The other mesh.
+    /// * `tolerance` - The largest allowed difference between vertex coordinates.
+    ///
+    pub fn same_geometry(&self, other: &Mesh, tolerance: f64) -> bool {
+        self.vertices.len() == other.vertices.len()
+            && self.elements == other.elements
+            && self
+                .vertices
+                .iter()
+                .zip(other.vertices.iter())
+                .all(|(a, b)| (0..3).all(|i| (a[i] - b[i]).abs() <= tolerance))
+    }
+
+    ///
+    /// Computes the helicity density `m . (curl m)` of a field in each element, using the
+    /// element's mean magnetization and the (constant) curl of the linearly interpolated field.
+    /// Degenerate elements have zero helicity density.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - The field, with one vector per vertex of the mesh.
+    ///
+    /// # Returns
+    ///
+    /// One helicity density per element.
+    ///
+    pub fn helicity_densities(&self, field: &Field) -> Vec<f64> {
+        self.elements
+            .iter()
+            .map(|element| {
+                let [v0, v1, v2, v3] = element.map(|vertex| self.vertices[vertex]);
+                let [f0, f1, f2, f3] = element.map(|vertex| field.vectors[vertex]);
+                match tet_lin_vec_curl(v0, v1, v2, v3, f0, f1, f2, f3) {
+                    Some(curl) => (0..3).map(|i| 0.25 * (f0[i] + f1[i] + f2[i] + f3[i]) * curl[i]).sum(),
+                    None => 0.0,
+                }
+            })
+            .collect()
+    }
+
+    ///
+    /// Computes the helicity, the volume integral of `m . (curl m)`, of each field; the result
+    /// is cached in the `helicities` field.
+    ///
+    pub fn compute_helicities(&mut self) {
+        let volumes: Vec<f64> = self
+            .elements
+            .iter()
+            .map(|element| {
+                let [v0, v1, v2, v3] = element.map(|vertex| self.vertices[vertex]);
+                tet_volume(v0, v1, v2, v3).abs()
+            })
+            .collect();
+        let helicities = self
+            .fields
+            .iter()
+            .map(|field| {
+                self.helicity_densities(field)
+                    .iter()
+                    .zip(volumes.iter())
+                    .map(|(density, volume)| density * volume)
+                    .sum()
+            })
+            .collect();
+        self.helicities = Some(helicities);
+    }
+
+    ///
+    /// Computes the principal axes of the mesh from its inertia tensor, treating the mesh as a
+    /// solid of uniform (unit) density.
+    ///
+    /// # Returns
+    ///
+    /// The `PrincipalAxes` of the mesh.
+    ///
+    pub fn principal_axes(&self) -> PrincipalAxes {
+        // Volume, first and second moments; for a tetrahedron with vertex sum X,
+        // int x_i x_j dV = V / 20 (sum_a x_ai x_aj + X_i X_j).
+        let mut volume = 0.0;
+        let mut first = [0.0; 3];
+        let mut second = [[0.0; 3]; 3];
+        for element in &self.elements {
+            let vertices = element.map(|vertex| self.vertices[vertex]);
+            let v = tet_volume(vertices[0], vertices[1], vertices[2], vertices[3]).abs();
+            let mut sum = [0.0; 3];
+            for vertex in &vertices {
+                for i in 0..3 {
+                    sum[i] += vertex[i];
+                }
+            }
+            volume += v;
+            for i in 0..3 {
+                first[i] += 0.25 * v * sum[i];
+                for j in 0..3 {
+                    let products = vertices.iter().map(|x| x[i] * x[j]).sum::<f64>();
+                    second[i][j] += v / 20.0 * (products + sum[i] * sum[j]);
+                }
+            }
+        }
+
+        let centroid = if volume > 0.0 { first.map(|f| f / volume) } else { [0.0; 3] };
+
+        // The inertia tensor about the centroid, I = tr(C) 1 - C for central second moments C.
+        let mut central = [[0.0; 3]; 3];
+        for i in 0..3 {
+            for j in 0..3 {
+                central[i][j] = second[i][j] - volume * centroid[i] * centroid[j];
+            }
+        }
+        let trace = central[0][0] + central[1][1] + central[2][2];
+        let mut inertia = [[0.0; 3]; 3];
+        for i in 0..3 {
+            for j in 0..3 {
+                inertia[i][j] = if i == j { trace } else { 0.0 } - central[i][j];
+            }
+        }
+
+        let (moments, axes) = symmetric_eigen(inertia);
+        PrincipalAxes { centroid, moments, axes }
+    }
+
+    ///
+    /// Builds a uniform grid spatial index over the mesh's elements, the index is cached in the
+    /// `spatial_index` field and used by `locate` to accelerate point location.
+    ///
+    pub fn compute_spatial_index(&mut self) {
+        self.spatial_index = Some(SpatialIndex::new(&self.vertices, &self.elements));
+    }
+
+    ///
+    /// Locates the element that contains a point.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - The point to locate.
+    ///
+    /// # Returns
+    ///
+    /// The index of the containing element along with the barycentric coordinates of the point
+    /// with respect to that element, or `None` if the point lies outside the mesh. Points on a
+    /// face shared by several elements are reported in the first element found.
+    ///
+    /// If `compute_spatial_index` has been called only nearby elements are checked, otherwise
+    /// every element of the mesh is checked.
+    ///
+    pub fn locate(&self, point: [f64; 3]) -> Option<(usize, [f64; 4])> {
+        match &self.spatial_index {
+            Some(index) => index
+                .candidates(point)
+                .iter()
+                .find_map(|element_index| self.element_barycentric(*element_index, point)),
+            None => (0..self.elements.len())
+                .find_map(|element_index| self.element_barycentric(element_index, point)),
+        }
+    }
+
+    ///
+    /// Computes the barycentric coordinates of a point with respect to an element, if the point
+    /// lies within that element (up to a small tolerance).
+    ///
+    fn element_barycentric(&self, element_index: usize, point: [f64; 3]) -> Option<(usize, [f64; 4])> {
+        const TOLERANCE: f64 = 1e-10;
+        let element = &self.elements[element_index];
+        let l = tet_barycentric(
+            self.vertices[element[0]],
+            self.vertices[element[1]],
+            self.vertices[element[2]],
+            self.vertices[element[3]],
+            point,
+        )?;
+        if l.iter().all(|li| *li >= -TOLERANCE) {
+            Some((element_index, l))
+        } else {
+            None
+        }
+    }
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::tecplot_parser::create_mesh_from_tecplot;
+
+    ///
+    /// The tecplot test mesh, a cube with sides of 0.02 centred on the origin.
+    ///
+    pub(crate) fn cube_mesh() -> Mesh {
+        let tecplot_file = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join("histo_two_zones")
+            .join("example.tec");
+        create_mesh_from_tecplot(tecplot_file.to_str().unwrap())
+    }
+
+    ///
+    /// A unit field on the vertices of a mesh, the normalised values of `m`.
+    ///
+    pub(crate) fn unit_field<F: Fn([f64; 3]) -> [f64; 3]>(mesh: &Mesh, m: F) -> Field {
+        let vectors = mesh
+            .vertices
+            .iter()
+            .map(|v| {
+                let m = m(*v);
+                let length = norm(m);
+                [m[0] / length, m[1] / length, m[2] / length]
+            })
+            .collect();
+        Field { label: String::from("m"), vectors }
+    }
+
+    fn two_tet_mesh() -> Mesh {
+        // Two tetrahedra sharing the face (1, 2, 3), with a field that is linear in space.
+        let vertices = vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [1.0, 1.0, 1.0],
+        ];
+        let vectors = vertices
+            .iter()
+            .map(|v| [v[0], 2.0 * v[1], v[0] + v[2]])
+            .collect();
+        Mesh::new(
+            String::from("two tets"),
+            vertices,
+            vec![[0, 1, 2, 3], [1, 2, 3, 4]],
+            vec![1, 2],
+            vec![Field { label: String::from("linear"), vectors }],
+        )
+    }
+
+    //..........................................................................................//
+    //. locate()                                                                               .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_locate() {
+        let mut mesh = two_tet_mesh();
+
+        for with_index in [false, true] {
+            if with_index {
+                mesh.compute_spatial_index();
+            }
+
+            let (element_index, l) = mesh.locate([0.1, 0.2, 0.3]).unwrap();
+            assert_eq!(element_index, 0);
+            assert!((l[1] - 0.1).abs() < 1e-12);
+            assert!((l[2] - 0.2).abs() < 1e-12);
+            assert!((l[3] - 0.3).abs() < 1e-12);
+
+            let (element_index, _) = mesh.locate([0.6, 0.6, 0.6]).unwrap();
+            assert_eq!(element_index, 1);
+
+            assert!(mesh.locate([1.0, 1.0, 0.0]).is_none());
+            assert!(mesh.locate([-0.1, 0.2, 0.3]).is_none());
+        }
+    }
+
+    #[test]
+    fn test_locate_tecplot_mesh() {
+        // Every element centroid of a real mesh should be located in its own element.
+        let mut mesh = cube_mesh();
+        mesh.compute_spatial_index();
+
+        for (element_index, element) in mesh.elements.iter().enumerate() {
+            let mut centroid = [0.0; 3];
+            for vertex_index in element {
+                for (c, x) in centroid.iter_mut().zip(mesh.vertices[*vertex_index].iter()) {
+                    *c += 0.25 * x;
+                }
+            }
+            let (found, l) = mesh.locate(centroid).unwrap();
+            assert_eq!(found, element_index);
+            for li in l {
+                assert!((li - 0.25).abs() < 1e-9);
+            }
+        }
+    }
+
+    //..........................................................................................//
+    //. Field::resample()                                                                      .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_field_resample() {
+        let mut mesh = two_tet_mesh();
+        mesh.compute_spatial_index();
+        let field = &mesh.fields[0];
+
+        let grid = RegularGrid::new([0.0; 3], [1.0; 3], [2, 2, 2]);
+        let grid_field = field.resample(&mesh, &grid);
+
+        assert_eq!(grid_field.vectors.len(), 8);
+
+        // Cell (0, 0, 0) is centred at (0.25, 0.25, 0.25), inside the first tetrahedron.
+        let index = grid.cell_index(0, 0, 0);
+        assert!(grid_field.mask[index]);
+        assert!((grid_field.vectors[index][1] - 0.5).abs() < 1e-12);
+
+        // Cell (1, 1, 0) is centred at (0.75, 0.75, 0.25), outside the mesh.
+        let index = grid.cell_index(1, 1, 0);
+        assert!(!grid_field.mask[index]);
+        assert_eq!(grid_field.vectors[index], [0.0; 3]);
+    }
+
+    //..........................................................................................//
+    //. Field::sample()                                                                        .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_field_sample() {
+        let mut mesh = two_tet_mesh();
+        mesh.compute_spatial_index();
+        let field = &mesh.fields[0];
+
+        // The field is linear, so interpolation is exact.
+        for point in [[0.1, 0.2, 0.3], [0.6, 0.6, 0.6], [0.9, 0.8, 0.7]] {
+            let value = field.sample(&mesh, point).unwrap();
+            assert!((value[0] - point[0]).abs() < 1e-12);
+            assert!((value[1] - 2.0 * point[1]).abs() < 1e-12);
+            assert!((value[2] - (point[0] + point[2])).abs() < 1e-12);
+        }
+
+        assert!(field.sample(&mesh, [2.0, 0.0, 0.0]).is_none());
+    }
+
+    #[test]
+    fn test_field_sample_line_plane_lattice() {
+        let mut mesh = two_tet_mesh();
+        mesh.compute_spatial_index();
+        let field = &mesh.fields[0];
+
+        let line = field.sample_line(&mesh, [0.0, 0.0, 0.0], [1.0, 1.0, 1.0], 5);
+        assert_eq!(line.len(), 5);
+        assert!((line[2].point[0] - 0.5).abs() < 1e-12);
+        assert!(line.iter().all(|sample| sample.value.is_some()));
+
+        let plane = field.sample_plane(
+            &mesh, [0.0, 0.0, 0.1], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], 3, 3
+        );
+        assert_eq!(plane.len(), 9);
+        assert!(plane[0].value.is_some());
+        assert!(plane[8].value.is_none());
+        assert!((plane[1].point[0] - 0.5).abs() < 1e-12);
+        assert!((plane[3].point[1] - 0.5).abs() < 1e-12);
+
+        let lattice = field.sample_lattice(&mesh, [0.0; 3], [1.0; 3], [2, 3, 4]);
+        assert_eq!(lattice.len(), 24);
+        assert!((lattice[1].point[0] - 1.0).abs() < 1e-12);
+        assert!((lattice[23].point[2] - 1.0).abs() < 1e-12);
+    }
+
+    //..........................................................................................//
+    //. Field::norm_statistics(), Field::normalize()                                           .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_field_norm_statistics() {
+        let mut field = Field {
+            label: String::from("m"),
+            vectors: vec![[1.0, 0.0, 0.0], [0.0, 0.6, 0.8], [0.0, 0.0, 2.0], [0.0, 0.0, 0.0]],
+        };
+
+        let statistics = field.norm_statistics(1e-6);
+        assert_eq!(statistics.min, 0.0);
+        assert_eq!(statistics.max, 2.0);
+        assert!((statistics.mean - 1.0).abs() < 1e-12);
+        assert_eq!(statistics.off_count, 2);
+
+        assert_eq!(field.normalize(), 1);
+        assert_eq!(field.vectors[2], [0.0, 0.0, 1.0]);
+        assert_eq!(field.vectors[3], [0.0, 0.0, 0.0]);
+
+        let statistics = field.norm_statistics(1e-6);
+        assert_eq!(statistics.max, 1.0);
+        assert_eq!(statistics.off_count, 1);
+    }
+
+    //..........................................................................................//
+    //. Field::difference(), Field::dot(), Field::angles(), Field::rms_difference()            .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_field_comparison() {
+        let mesh = two_tet_mesh();
+        let a = Field {
+            label: String::from("\"a\""),
+            vectors: vec![[1.0, 0.0, 0.0]; 5],
+        };
+        let b = Field {
+            label: String::from("\"b\""),
+            vectors: vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0]],
+        };
+
+        let difference = a.difference(&b);
+        assert_eq!(difference.label, "a - b");
+        assert_eq!(difference.vectors[1], [1.0, -1.0, 0.0]);
+        assert_eq!(a.dot(&b), vec![1.0, 0.0, -1.0, 0.0, 1.0]);
+
+        let angles = a.angles(&b);
+        assert!((angles[1] - 90.0).abs() < 1e-12);
+        assert!((angles[2] - 180.0).abs() < 1e-12);
+        assert_eq!(angles[3], 0.0);
+        assert!((a.max_angular_deviation(&b) - 180.0).abs() < 1e-12);
+
+        // Identical fields do not differ, a constant difference d has an RMS difference of |d|.
+        assert_eq!(a.rms_difference(&mesh, &a), 0.0);
+        let c = Field {
+            label: String::from("c"),
+            vectors: vec![[1.0, 3.0, 4.0]; 5],
+        };
+        assert!((a.rms_difference(&mesh, &c) - 5.0).abs() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_field_comparison_length_mismatch() {
+        let a = Field { label: String::from("a"), vectors: vec![[1.0, 0.0, 0.0]; 5] };
+        let b = Field { label: String::from("b"), vectors: vec![[1.0, 0.0, 0.0]; 4] };
+        a.angles(&b);
+    }
+
+    #[test]
+    fn test_same_geometry() {
+        let mesh = two_tet_mesh();
+        let mut other = two_tet_mesh();
+        assert!(mesh.same_geometry(&other, 0.0));
+
+        other.vertices[4][0] += 1e-9;
+        assert!(!mesh.same_geometry(&other, 0.0));
+        assert!(mesh.same_geometry(&other, 1e-6));
+
+        other.elements[1] = [1, 2, 4, 3];
+        assert!(!mesh.same_geometry(&other, 1e-6));
+    }
+
+    //..........................................................................................//
+    //. boundary_faces(), interface_faces()                                                    .//
+    //..........................................................................................//
+
+    fn face_normal(mesh: &Mesh, face: &[usize; 3]) -> [f64; 3] {
+        triangle_area_vector(mesh.vertices[face[0]], mesh.vertices[face[1]], mesh.vertices[face[2]])
+    }
+
+    fn element_centroid(mesh: &Mesh, element_index: usize) -> [f64; 3] {
+        let mut centroid = [0.0; 3];
+        for vertex in mesh.elements[element_index] {
+            for (c, x) in centroid.iter_mut().zip(mesh.vertices[vertex].iter()) {
+                *c += 0.25 * x;
+            }
+        }
+        centroid
+    }
+
+    #[test]
+    fn test_boundary_faces() {
+        let mesh = two_tet_mesh();
+        let faces = mesh.boundary_faces();
+
+        // Eight faces, less the two copies of the shared face (1, 2, 3).
+        assert_eq!(faces.len(), 6);
+        for face in &faces {
+            let mut key = face.vertices;
+            key.sort_unstable();
+            assert_ne!(key, [1, 2, 3]);
+
+            // The normal points away from the centroid of the owning element.
+            let n = face_normal(&mesh, &face.vertices);
+            let centroid = element_centroid(&mesh, face.element);
+            let p = mesh.vertices[face.vertices[0]];
+            let outward = (0..3).map(|i| n[i] * (p[i] - centroid[i])).sum::<f64>();
+            assert!(outward > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_boundary_faces_closed_surface() {
+        // The boundary of the tecplot mesh is closed, so its area vectors sum to zero.
+        let mesh = cube_mesh();
+
+        let mut total = [0.0; 3];
+        for face in mesh.boundary_faces() {
+            let n = face_normal(&mesh, &face.vertices);
+            for i in 0..3 {
+                total[i] += n[i];
+            }
+        }
+        assert!(total.iter().all(|t| t.abs() < 1e-15));
+
+        // The mesh is a cube with sides of 0.02.
+        let mut mesh = mesh;
+        mesh.compute_surface_area();
+        assert!((mesh.surface_area.unwrap() - 6.0 * 0.02 * 0.02).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_interface_faces() {
+        let mesh = two_tet_mesh();
+        let faces = mesh.interface_faces();
+
+        assert_eq!(faces.len(), 1);
+        assert_eq!(faces[0].elements, [0, 1]);
+        assert_eq!(faces[0].subdomains, [1, 2]);
+
+        // The shared face (1, 2, 3) points from the first element towards (1, 1, 1).
+        let n = face_normal(&mesh, &faces[0].vertices);
+        assert!(n.iter().all(|component| *component > 0.0));
+
+        // Without distinct subdomains there is no interface.
+        let mut single = two_tet_mesh();
+        single.submesh_indices = vec![1, 1];
+        assert!(single.interface_faces().is_empty());
+
+        // Nor without a submesh index for each element.
+        single.submesh_indices = vec![1];
+        assert!(single.interface_faces().is_empty());
+    }
+
+    #[test]
+    fn test_compute_surface_area() {
+        let mut mesh = two_tet_mesh();
+        mesh.compute_surface_area();
+
+        // Three right triangles at the origin plus three faces of the second tetrahedron, of
+        // which (1, 2, 4), (1, 3, 4) and (2, 3, 4) all have area sqrt(3) / 2.
+        let expected = 1.5 + 3.0 * 3.0_f64.sqrt() / 2.0;
+        assert!((mesh.surface_area.unwrap() - expected).abs() < 1e-12);
+    }
+
+    //..........................................................................................//
+    //. compute_charges()                                                                      .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_compute_charges() {
+        let mut mesh = two_tet_mesh();
+        mesh.compute_charges();
+        let charges = &mesh.charges.as_ref().unwrap()[0];
+
+        // The linear field [x, 2y, x + z] has divergence 4 and the mesh has volume 1/6 + 1/3, so
+        // the volume charge is -2 and the surface charge balances it.
+        assert!((charges.volume + 2.0).abs() < 1e-12);
+        assert!((charges.surface - 2.0).abs() < 1e-12);
+        assert!(charges.total().abs() < 1e-12);
+        assert!((charges.volume_abs - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_compute_charges_uniform() {
+        // A uniform field along z has no volume charge, and surface charges +A and -A on the
+        // top and bottom of the cube.
+        let mut mesh = cube_mesh();
+        mesh.fields = vec![Field {
+            label: String::from("uniform"),
+            vectors: vec![[0.0, 0.0, 1.0]; mesh.vertices.len()],
+        }];
+        mesh.compute_surface_area();
+        mesh.compute_charges();
+        let charges = &mesh.charges.as_ref().unwrap()[0];
+
+        assert!(charges.volume_abs < 1e-15);
+        assert!(charges.total().abs() < 1e-15);
+        assert!((charges.surface_abs - 2.0 * 0.02 * 0.02).abs() < 1e-15);
+        assert!((charges.normalised(mesh.surface_area.unwrap()).unwrap() - 1.0 / 3.0).abs() < 1e-12);
+        assert_eq!(charges.normalised(0.0), None);
+    }
+
+    //..........................................................................................//
+    //. helicity_densities(), compute_helicities()                                             .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_compute_helicities() {
+        let mut mesh = two_tet_mesh();
+        // m = (-y, x, 1) has curl (0, 0, 2), so m . curl m = 2 everywhere.
+        mesh.fields = vec![Field {
+            label: String::from("twist"),
+            vectors: mesh.vertices.iter().map(|v| [-v[1], v[0], 1.0]).collect(),
+        }];
+
+        let densities = mesh.helicity_densities(&mesh.fields[0]);
+        assert!(densities.iter().all(|density| (density - 2.0).abs() < 1e-12));
+
+        mesh.compute_helicities();
+        assert!((mesh.helicities.unwrap()[0] - 2.0 * 0.5).abs() < 1e-12);
+    }
+
+    //..........................................................................................//
+    //. principal_axes()                                                                       .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_principal_axes() {
+        // A single tetrahedron stretched along y, its long axis is (close to) y.
+        let mesh = Mesh::new(
+            String::from("needle"),
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 1.0]],
+            vec![[0, 1, 2, 3]],
+            vec![1],
+            vec![],
+        );
+        let principal = mesh.principal_axes();
+
+        assert!((principal.centroid[1] - 2.5).abs() < 1e-12);
+        assert!(principal.moments[0] <= principal.moments[1]);
+        assert!(principal.moments[1] <= principal.moments[2]);
+        assert!(principal.axes[0][1].abs() > 0.99);
+    }
+
+    #[test]
+    fn test_principal_axes_cube() {
+        let mesh = cube_mesh();
+        let principal = mesh.principal_axes();
+
+        // For a cube of side a, all principal moments are a^5 / 6.
+        let expected = 0.02_f64.powi(5) / 6.0;
+        assert!(principal.centroid.iter().all(|c| c.abs() < 1e-15));
+        assert!(principal.moments.iter().all(|moment| (moment - expected).abs() < 1e-9 * expected));
+    }
 }
@@ -0,0 +1,498 @@
+//
+// file: ovf_io.rs
+// author: L. Nagy
+//
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+use crate::regular_grid::{GridField, RegularGrid};
+
+///
+/// The data encodings supported for OVF output.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OvfFormat {
+    /// Whitespace separated text values.
+    Text,
+
+    /// 8-byte IEEE floating point values, little-endian for OVF 2.0 and big-endian for OVF 1.0.
+    Binary8,
+}
+
+impl OvfFormat {
+    ///
+    /// The name of the format as it appears in the `# Begin: Data ...` line of an OVF file.
+    ///
+    fn data_name(&self) -> &'static str {
+        match self {
+            OvfFormat::Text => "Text",
+            OvfFormat::Binary8 => "Binary 8",
+        }
+    }
+}
+
+///
+/// The data encodings supported for OVF input, those of `OvfFormat` along with 4-byte binary.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OvfDataFormat {
+    /// Whitespace separated text values.
+    Text,
+
+    /// 4-byte IEEE floating point values.
+    Binary4,
+
+    /// 8-byte IEEE floating point values.
+    Binary8,
+}
+
+///
+/// The check value that precedes 4-byte binary data in OVF files.
+///
+const OVF_BINARY4_CHECK_VALUE: f32 = 1234567.0;
+
+///
+/// The check value that precedes 8-byte binary data in OVF files.
+///
+const OVF2_BINARY8_CHECK_VALUE: f64 = 123456789012345.0;
+
+///
+/// Writes a `GridField` to an OOMMF OVF 2.0 file with a single segment.
+///
+/// The grid coordinates are written in the mesh unit given by `mesh_unit` (usually "m"); masked
+/// cells, i.e. those outside the material, are written as zero vectors which OOMMF and mumax
+/// interpret as empty space.
+///
+/// # Parameters
+/// - `grid_field`: The field to write.
+/// - `filename`: The name of the OVF file to create.
+/// - `mesh_unit`: The unit of the grid coordinates.
+/// - `format`: The encoding used for the data block.
+///
+/// # Returns
+/// - `io::Result<()>`: Returns `Ok(())` on success or an error if the file could not be written.
+///
+pub fn write_grid_field_to_ovf(
+    grid_field: &GridField,
+    filename: &str,
+    mesh_unit: &str,
+    format: OvfFormat,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(filename)?);
+
+    let grid = &grid_field.grid;
+    let max = grid.max();
+    let base = grid.cell_centre(0, 0, 0);
+    let title = grid_field.label.trim_matches('"');
+    let title = if title.is_empty() { "m" } else { title };
+
+    writeln!(writer, "# OOMMF OVF 2.0")?;
+    writeln!(writer, "# Segment count: 1")?;
+    writeln!(writer, "# Begin: Segment")?;
+    writeln!(writer, "# Begin: Header")?;
+    writeln!(writer, "# Title: {}", title)?;
+    writeln!(writer, "# meshtype: rectangular")?;
+    writeln!(writer, "# meshunit: {}", mesh_unit)?;
+    writeln!(writer, "# xmin: {:E}", grid.min[0])?;
+    writeln!(writer, "# ymin: {:E}", grid.min[1])?;
+    writeln!(writer, "# zmin: {:E}", grid.min[2])?;
+    writeln!(writer, "# xmax: {:E}", max[0])?;
+    writeln!(writer, "# ymax: {:E}", max[1])?;
+    writeln!(writer, "# zmax: {:E}", max[2])?;
+    writeln!(writer, "# valuedim: 3")?;
+    writeln!(writer, "# valuelabels: m_x m_y m_z")?;
+    writeln!(writer, "# valueunits: 1 1 1")?;
+    writeln!(writer, "# xbase: {:E}", base[0])?;
+    writeln!(writer, "# ybase: {:E}", base[1])?;
+    writeln!(writer, "# zbase: {:E}", base[2])?;
+    writeln!(writer, "# xnodes: {}", grid.dims[0])?;
+    writeln!(writer, "# ynodes: {}", grid.dims[1])?;
+    writeln!(writer, "# znodes: {}", grid.dims[2])?;
+    writeln!(writer, "# xstepsize: {:E}", grid.cell_size[0])?;
+    writeln!(writer, "# ystepsize: {:E}", grid.cell_size[1])?;
+    writeln!(writer, "# zstepsize: {:E}", grid.cell_size[2])?;
+    writeln!(writer, "# End: Header")?;
+    writeln!(writer, "# Begin: Data {}", format.data_name())?;
+
+    match format {
+        OvfFormat::Text => {
+            for vector in &grid_field.vectors {
+                writeln!(writer, "{:E} {:E} {:E}", vector[0], vector[1], vector[2])?;
+            }
+        }
+        OvfFormat::Binary8 => {
+            writer.write_all(&OVF2_BINARY8_CHECK_VALUE.to_le_bytes())?;
+            for vector in &grid_field.vectors {
+                for component in vector {
+                    writer.write_all(&component.to_le_bytes())?;
+                }
+            }
+            writeln!(writer)?;
+        }
+    }
+
+    writeln!(writer, "# End: Data {}", format.data_name())?;
+    writeln!(writer, "# End: Segment")?;
+
+    writer.flush()
+}
+
+///
+/// Reads the first segment of an OOMMF OVF 1.0 or 2.0 file holding a vector field on a
+/// rectangular mesh, as written by OOMMF and mumax.
+///
+/// Text, 4-byte and 8-byte binary data are supported. OVF 1.0 binary data is big-endian and
+/// OVF 2.0 binary data is little-endian; the check value is used to confirm the byte order. For
+/// OVF 1.0 files the `valuemultiplier` is applied to the data.
+///
+/// # Parameters
+/// - `filename`: The name of the OVF file to read.
+///
+/// # Returns
+/// - `io::Result<GridField>`: The field on its grid, cells holding zero vectors (empty space) are
+///   masked out.
+///
+/// # Errors
+/// Returns an `InvalidData` error if the file is not an OVF file, uses an irregular mesh, does
+/// not hold a three component vector field, or its data block is malformed or truncated.
+///
+pub fn read_grid_field_from_ovf(filename: &str) -> io::Result<GridField> {
+    let bytes = fs::read(filename)?;
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let mut position = 0;
+    let mut version: Option<u32> = None;
+    let mut title = String::new();
+    let mut mesh_type = String::new();
+    let mut value_dim: usize = 3;
+    let mut value_multiplier: f64 = 1.0;
+    let mut min = [0.0; 3];
+    let mut step = [0.0; 3];
+    let mut nodes = [0usize; 3];
+    let mut data_format: Option<OvfDataFormat> = None;
+
+    // Parse the header, line by line, up to the start of the first data block.
+    while position < bytes.len() {
+        let end = bytes[position..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(bytes.len(), |offset| position + offset);
+        let line = String::from_utf8_lossy(&bytes[position..end]).trim().to_string();
+        position = (end + 1).min(bytes.len());
+
+        if line.is_empty() {
+            continue;
+        }
+        if !line.starts_with('#') {
+            return Err(invalid(format!("Unexpected line in OVF header: '{}'", line)));
+        }
+        let line = line.trim_start_matches('#').trim();
+        let lower = line.to_lowercase();
+
+        if version.is_none() {
+            if lower.starts_with("oommf ovf 2.0") {
+                version = Some(2);
+            } else if lower.starts_with("oommf: rectangular mesh v1.0")
+                || lower.starts_with("oommf ovf 1.0")
+            {
+                version = Some(1);
+            } else if lower.starts_with("oommf") {
+                return Err(invalid(format!("Unsupported OVF version: '{}'", line)));
+            } else {
+                return Err(invalid(String::from("Not an OVF file (missing 'OOMMF' header).")));
+            }
+            continue;
+        }
+
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim().to_lowercase(), value.trim().to_string()),
+            None => continue,
+        };
+
+        let parse_f64 = |value: &str| -> io::Result<f64> {
+            value.parse::<f64>().map_err(|_| invalid(format!("Invalid value for '{}': '{}'", key, value)))
+        };
+        let parse_usize = |value: &str| -> io::Result<usize> {
+            value.parse::<usize>().map_err(|_| invalid(format!("Invalid value for '{}': '{}'", key, value)))
+        };
+
+        match key.as_str() {
+            "title" => title = value,
+            "meshtype" => mesh_type = value.to_lowercase(),
+            "valuedim" => value_dim = parse_usize(&value)?,
+            "valuemultiplier" => value_multiplier = parse_f64(&value)?,
+            "xmin" => min[0] = parse_f64(&value)?,
+            "ymin" => min[1] = parse_f64(&value)?,
+            "zmin" => min[2] = parse_f64(&value)?,
+            "xstepsize" => step[0] = parse_f64(&value)?,
+            "ystepsize" => step[1] = parse_f64(&value)?,
+            "zstepsize" => step[2] = parse_f64(&value)?,
+            "xnodes" => nodes[0] = parse_usize(&value)?,
+            "ynodes" => nodes[1] = parse_usize(&value)?,
+            "znodes" => nodes[2] = parse_usize(&value)?,
+            "begin" => {
+                let block = value.to_lowercase();
+                if let Some(kind) = block.strip_prefix("data") {
+                    data_format = match kind.trim() {
+                        "text" => Some(OvfDataFormat::Text),
+                        "binary 4" => Some(OvfDataFormat::Binary4),
+                        "binary 8" => Some(OvfDataFormat::Binary8),
+                        other => return Err(invalid(format!("Unsupported OVF data format: '{}'", other))),
+                    };
+                    break;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let version = version.ok_or_else(|| invalid(String::from("Empty OVF file.")))?;
+    let data_format = data_format.ok_or_else(|| invalid(String::from("No data block found in OVF file.")))?;
+
+    if mesh_type != "rectangular" {
+        return Err(invalid(format!("Unsupported OVF mesh type: '{}'", mesh_type)));
+    }
+    if value_dim != 3 {
+        return Err(invalid(format!("Expected a vector field (valuedim 3), found valuedim {}", value_dim)));
+    }
+    if nodes.contains(&0) {
+        return Err(invalid(String::from("OVF header is missing the number of nodes.")));
+    }
+
+    let count = 3 * nodes[0] * nodes[1] * nodes[2];
+    let data = &bytes[position..];
+    let values: Vec<f64> = match data_format {
+        OvfDataFormat::Text => {
+            let values = String::from_utf8_lossy(data)
+                .lines()
+                .take_while(|line| !line.trim_start().starts_with('#'))
+                .flat_map(|line| line.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+                .take(count)
+                .map(|token| token.parse::<f64>().map_err(|_| invalid(format!("Invalid OVF data value '{}'", token))))
+                .collect::<io::Result<Vec<f64>>>()?;
+            values
+        }
+        OvfDataFormat::Binary4 => {
+            if data.len() < 4 * (count + 1) {
+                return Err(invalid(String::from("OVF binary data block is truncated.")));
+            }
+            let check: [u8; 4] = data[0..4].try_into().unwrap();
+            let big_endian = if f32::from_le_bytes(check) == OVF_BINARY4_CHECK_VALUE {
+                false
+            } else if f32::from_be_bytes(check) == OVF_BINARY4_CHECK_VALUE {
+                true
+            } else {
+                return Err(invalid(String::from("OVF binary check value mismatch.")));
+            };
+            data[4..4 * (count + 1)]
+                .chunks_exact(4)
+                .map(|chunk| {
+                    let chunk: [u8; 4] = chunk.try_into().unwrap();
+                    if big_endian { f32::from_be_bytes(chunk) as f64 } else { f32::from_le_bytes(chunk) as f64 }
+                })
+                .collect()
+        }
+        OvfDataFormat::Binary8 => {
+            if data.len() < 8 * (count + 1) {
+                return Err(invalid(String::from("OVF binary data block is truncated.")));
+            }
+            let check: [u8; 8] = data[0..8].try_into().unwrap();
+            let big_endian = if f64::from_le_bytes(check) == OVF2_BINARY8_CHECK_VALUE {
+                false
+            } else if f64::from_be_bytes(check) == OVF2_BINARY8_CHECK_VALUE {
+                true
+            } else {
+                return Err(invalid(String::from("OVF binary check value mismatch.")));
+            };
+            data[8..8 * (count + 1)]
+                .chunks_exact(8)
+                .map(|chunk| {
+                    let chunk: [u8; 8] = chunk.try_into().unwrap();
+                    if big_endian { f64::from_be_bytes(chunk) } else { f64::from_le_bytes(chunk) }
+                })
+                .collect()
+        }
+    };
+
+    if values.len() != count {
+        return Err(invalid(format!(
+            "OVF data block holds {} values, expected {}", values.len(), count)));
+    }
+
+    // The value multiplier only exists in OVF 1.0 files.
+    let multiplier = if version == 1 { value_multiplier } else { 1.0 };
+
+    let vectors: Vec<[f64; 3]> = values
+        .chunks_exact(3)
+        .map(|v| [multiplier * v[0], multiplier * v[1], multiplier * v[2]])
+        .collect();
+    let mask: Vec<bool> = vectors
+        .iter()
+        .map(|v| v[0] != 0.0 || v[1] != 0.0 || v[2] != 0.0)
+        .collect();
+
+    Ok(GridField {
+        label: title,
+        grid: RegularGrid {
+            min,
+            cell_size: step,
+            dims: nodes,
+        },
+        vectors,
+        mask,
+    })
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::*;
+    use crate::regular_grid::RegularGrid;
+
+    fn example_grid_field() -> GridField {
+        let grid = RegularGrid::new([0.0; 3], [2e-9, 1e-9, 1e-9], [2, 1, 1]);
+        GridField {
+            label: String::from("\"400.0000 mT\""),
+            grid,
+            vectors: vec![[1.0, 0.0, 0.0], [0.0, 0.0, 0.0]],
+            mask: vec![true, false],
+        }
+    }
+
+    //..........................................................................................//
+    //. write_grid_field_to_ovf()                                                              .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_write_grid_field_to_ovf_text() {
+        let file_name = env::temp_dir().join("tec2hdf5_test_write_text.ovf");
+        let file_name = file_name.to_str().unwrap();
+
+        write_grid_field_to_ovf(&example_grid_field(), file_name, "m", OvfFormat::Text).unwrap();
+
+        let contents = fs::read_to_string(file_name).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[0], "# OOMMF OVF 2.0");
+        assert!(lines.contains(&"# Title: 400.0000 mT"));
+        assert!(lines.contains(&"# xnodes: 2"));
+        assert!(lines.contains(&"# xbase: 5E-10"));
+        assert!(lines.contains(&"# Begin: Data Text"));
+        assert!(lines.contains(&"1E0 0E0 0E0"));
+        assert_eq!(lines[lines.len() - 1], "# End: Segment");
+    }
+
+    //..........................................................................................//
+    //. read_grid_field_from_ovf()                                                             .//
+    //..........................................................................................//
+
+    fn assert_same_grid_field(expected: &GridField, result: &GridField) {
+        assert_eq!(expected.grid.dims, result.grid.dims);
+        for i in 0..3 {
+            assert!((expected.grid.min[i] - result.grid.min[i]).abs() < 1e-20);
+            assert!((expected.grid.cell_size[i] - result.grid.cell_size[i]).abs() < 1e-20);
+        }
+        assert_eq!(expected.mask, result.mask);
+        assert_eq!(expected.vectors, result.vectors);
+    }
+
+    #[test]
+    fn test_read_grid_field_from_ovf_round_trip() {
+        let expected = example_grid_field();
+        for format in [OvfFormat::Text, OvfFormat::Binary8] {
+            let file_name = env::temp_dir().join(format!("tec2hdf5_test_round_trip_{:?}.ovf", format));
+            let file_name = file_name.to_str().unwrap();
+
+            write_grid_field_to_ovf(&expected, file_name, "m", format).unwrap();
+            let result = read_grid_field_from_ovf(file_name).unwrap();
+
+            assert_eq!(result.label, "400.0000 mT");
+            assert_same_grid_field(&expected, &result);
+        }
+    }
+
+    #[test]
+    fn test_read_grid_field_from_ovf1_binary4() {
+        // OVF 1.0 binary data is big-endian and scaled by the value multiplier.
+        let mut bytes: Vec<u8> = Vec::new();
+        let header = "# OOMMF: rectangular mesh v1.0\n\
+                      # Segment count: 1\n\
+                      # Begin: Segment\n\
+                      # Begin: Header\n\
+                      # Title: m\n\
+                      # meshtype: rectangular\n\
+                      # meshunit: m\n\
+                      # xbase: 5e-10\n\
+                      # ybase: 5e-10\n\
+                      # zbase: 5e-10\n\
+                      # xstepsize: 1e-9\n\
+                      # ystepsize: 1e-9\n\
+                      # zstepsize: 1e-9\n\
+                      # xnodes: 2\n\
+                      # ynodes: 1\n\
+                      # znodes: 1\n\
+                      # xmin: 0\n\
+                      # ymin: 0\n\
+                      # zmin: 0\n\
+                      # xmax: 2e-9\n\
+                      # ymax: 1e-9\n\
+                      # zmax: 1e-9\n\
+                      # valueunit: A/m\n\
+                      # valuemultiplier: 2\n\
+                      # End: Header\n\
+                      # Begin: Data Binary 4\n";
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(&OVF_BINARY4_CHECK_VALUE.to_be_bytes());
+        for value in [0.5_f32, 0.0, 0.0, 0.0, 0.0, 0.0] {
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        bytes.extend_from_slice(b"\n# End: Data Binary 4\n# End: Segment\n");
+
+        let file_name = env::temp_dir().join("tec2hdf5_test_read_ovf1_binary4.ovf");
+        let file_name = file_name.to_str().unwrap();
+        fs::write(file_name, bytes).unwrap();
+
+        let result = read_grid_field_from_ovf(file_name).unwrap();
+        assert_same_grid_field(&example_grid_field(), &result);
+    }
+
+    #[test]
+    fn test_read_grid_field_from_ovf_irregular() {
+        let file_name = env::temp_dir().join("tec2hdf5_test_read_ovf_irregular.ovf");
+        let file_name = file_name.to_str().unwrap();
+        fs::write(
+            file_name,
+            "# OOMMF OVF 2.0\n# meshtype: irregular\n# Begin: Data Text\n0 0 0 1 0 0\n",
+        ).unwrap();
+
+        assert!(read_grid_field_from_ovf(file_name).is_err());
+    }
+
+    #[test]
+    fn test_write_grid_field_to_ovf_binary8() {
+        let file_name = env::temp_dir().join("tec2hdf5_test_write_binary8.ovf");
+        let file_name = file_name.to_str().unwrap();
+
+        write_grid_field_to_ovf(&example_grid_field(), file_name, "m", OvfFormat::Binary8)
+            .unwrap();
+
+        let bytes = fs::read(file_name).unwrap();
+        let marker = b"# Begin: Data Binary 8\n";
+        let start = bytes
+            .windows(marker.len())
+            .position(|window| window == marker)
+            .unwrap() + marker.len();
+
+        let check = f64::from_le_bytes(bytes[start..start + 8].try_into().unwrap());
+        assert_eq!(check, OVF2_BINARY8_CHECK_VALUE);
+
+        let first = f64::from_le_bytes(bytes[start + 8..start + 16].try_into().unwrap());
+        assert_eq!(first, 1.0);
+    }
+}
@@ -0,0 +1,403 @@
+//
+// file: patran_io.rs
+// author: L. Nagy
+//
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::mesh::Mesh;
+
+/// Packet type of the title card.
+const PACKET_TITLE: i64 = 25;
+
+/// Packet type of the summary card.
+const PACKET_SUMMARY: i64 = 26;
+
+/// Packet type of a node.
+const PACKET_NODE: i64 = 1;
+
+/// Packet type of an element.
+const PACKET_ELEMENT: i64 = 2;
+
+/// Packet type of an element property (maps a property id to a material id).
+const PACKET_PROPERTY: i64 = 4;
+
+/// Packet type marking the end of the file.
+const PACKET_END: i64 = 99;
+
+/// PATRAN shape code of a tetrahedron.
+const PATRAN_TETRAHEDRON: i64 = 5;
+
+fn invalid(line_number: usize, message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("PATRAN file line {}: {}", line_number, message))
+}
+
+///
+/// Splits a card into fixed width fields, the first field may have a different width (e.g. the
+/// I2 packet type of a header card). Trailing empty fields are dropped.
+///
+fn fixed_width_fields(line: &str, first_width: usize, width: usize) -> Vec<&str> {
+    let mut fields = vec![];
+    let mut start = 0;
+    let mut field_width = first_width;
+    while start < line.len() {
+        let end = (start + field_width).min(line.len());
+        match line.get(start..end) {
+            Some(field) => fields.push(field.trim()),
+            None => return vec![],
+        }
+        start = end;
+        field_width = width;
+    }
+    while fields.last().is_some_and(|field| field.is_empty()) {
+        fields.pop();
+    }
+    fields
+}
+
+///
+/// Parses the first `count` values of a card. PATRAN cards are fixed width (so that, e.g.,
+/// negative E16.9 values may touch each other), but files written by hand are often only
+/// whitespace separated, so the latter is tried if the fixed width fields cannot be parsed.
+///
+fn parse_card<T: FromStr>(
+    line: &str,
+    line_number: usize,
+    first_width: usize,
+    width: usize,
+    count: usize,
+) -> io::Result<Vec<T>> {
+    let parse = |fields: Vec<&str>| -> Option<Vec<T>> {
+        if fields.len() < count {
+            return None;
+        }
+        fields.iter().take(count).map(|field| field.parse::<T>().ok()).collect()
+    };
+    parse(fixed_width_fields(line, first_width, width))
+        .or_else(|| parse(line.split_whitespace().collect()))
+        .ok_or_else(|| invalid(line_number, format!("expected {} values, found '{}'", count, line.trim_end())))
+}
+
+///
+/// Formats a value in the Fortran E16.9 style used by PATRAN, e.g. ` 1.234567890E-01`.
+///
+fn fortran_e16_9(value: f64) -> String {
+    let formatted = format!("{:.9E}", value);
+    let (mantissa, exponent) = formatted.split_once('E').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    format!("{:>16}", format!("{}E{}{:02}", mantissa, if exponent < 0 { '-' } else { '+' }, exponent.abs()))
+}
+
+///
+/// Formats a PATRAN header card (I2, 8I8).
+///
+fn header_card(packet_type: i64, id: usize, iv: usize, kc: usize, n: [usize; 5]) -> String {
+    format!(
+        "{:2}{:8}{:8}{:8}{:8}{:8}{:8}{:8}{:8}",
+        packet_type, id, iv, kc, n[0], n[1], n[2], n[3], n[4]
+    )
+}
+
+///
+/// Reads a tetrahedral mesh from a PATRAN neutral file (as used for MERRILL geometry input).
+///
+/// Nodes (packet 1) and tetrahedral elements (packet 2, shape code 5) are read, all other
+/// element shapes are ignored. The property id (PID) of each element is mapped to a material id
+/// through the element property packets (packet 4), the material id becomes the element's
+/// submesh index; elements whose property has no packet 4 use the property id itself. Node ids
+/// need not be contiguous, they are renumbered in the order in which the nodes appear.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the PATRAN neutral file.
+///
+/// # Returns
+///
+/// A `Mesh` without any fields, labelled with the title card (or the file stem if the title is
+/// empty).
+///
+/// # Errors
+/// Returns an `InvalidData` error (with the offending line number) if a card is malformed or
+/// missing, an element references an unknown node, or the file contains no tetrahedra.
+///
+pub fn read_mesh_from_patran(filename: &str) -> io::Result<Mesh> {
+    let contents = fs::read_to_string(filename)?;
+    let lines: Vec<&str> = contents.lines().collect();
+
+    let mut title = String::new();
+    let mut vertices: Vec<[f64; 3]> = vec![];
+    let mut node_indices: HashMap<i64, usize> = HashMap::new();
+    let mut tetrahedra: Vec<([i64; 4], i64, usize)> = vec![];
+    let mut materials: HashMap<i64, i64> = HashMap::new();
+
+    let mut index = 0;
+    while index < lines.len() {
+        let line_number = index + 1;
+        if lines[index].trim().is_empty() {
+            index += 1;
+            continue;
+        }
+
+        let header: Vec<i64> = parse_card(lines[index], line_number, 2, 8, 4)?;
+        let (packet_type, id, iv, kc) = (header[0], header[1], header[2], header[3].max(0) as usize);
+        if packet_type == PACKET_END {
+            break;
+        }
+
+        let cards_start = index + 1;
+        let cards_end = cards_start + kc;
+        if cards_end > lines.len() {
+            return Err(invalid(line_number, format!("packet {} is truncated", packet_type)));
+        }
+        let cards = &lines[cards_start..cards_end];
+
+        match packet_type {
+            PACKET_TITLE => {
+                if let Some(card) = cards.first() {
+                    title = card.trim().to_string();
+                }
+            }
+            PACKET_NODE => {
+                if cards.is_empty() {
+                    return Err(invalid(line_number, String::from("node packet without coordinates")));
+                }
+                let position: Vec<f64> = parse_card(cards[0], cards_start + 1, 16, 16, 3)?;
+                node_indices.insert(id, vertices.len());
+                vertices.push([position[0], position[1], position[2]]);
+            }
+            PACKET_ELEMENT if iv == PATRAN_TETRAHEDRON => {
+                if cards.len() < 2 {
+                    return Err(invalid(line_number, String::from("element packet without node list")));
+                }
+                // NODES, CONFIG, PID, ...
+                let properties: Vec<i64> = parse_card(cards[0], cards_start + 1, 8, 8, 3)?;
+                let nodes: Vec<i64> = parse_card(cards[1], cards_start + 2, 8, 8, 4)?;
+                tetrahedra.push(([nodes[0], nodes[1], nodes[2], nodes[3]], properties[2], cards_start + 2));
+            }
+            PACKET_PROPERTY => {
+                materials.insert(id, iv);
+            }
+            _ => (),
+        }
+
+        index = cards_end;
+    }
+
+    if tetrahedra.is_empty() {
+        return Err(invalid(lines.len(), String::from("no tetrahedral elements found")));
+    }
+
+    let mut elements = Vec::with_capacity(tetrahedra.len());
+    let mut submesh_indices = Vec::with_capacity(tetrahedra.len());
+    for (nodes, property, line_number) in tetrahedra {
+        let mut element = [0usize; 4];
+        for (vertex, node) in element.iter_mut().zip(nodes.iter()) {
+            *vertex = *node_indices
+                .get(node)
+                .ok_or_else(|| invalid(line_number, format!("element references unknown node {}", node)))?;
+        }
+        elements.push(element);
+        submesh_indices.push(materials.get(&property).copied().unwrap_or(property).max(0) as usize);
+    }
+
+    let label = if title.is_empty() {
+        Path::new(filename)
+            .file_stem()
+            .map_or(String::from("mesh"), |stem| stem.to_string_lossy().to_string())
+    } else {
+        title
+    };
+
+    Ok(Mesh::new(label, vertices, elements, submesh_indices, vec![]))
+}
+
+///
+/// Writes the geometry of a mesh to a PATRAN neutral file that MERRILL can read.
+///
+/// Nodes and elements are numbered from one, and each element's submesh index is written as its
+/// property id (PID). Fields are not written.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh to write.
+/// * `filename` - The name of the PATRAN neutral file to create.
+///
+/// # Errors
+/// Returns an `InvalidInput` error if the mesh does not have one submesh index per element, or
+/// an error if the file cannot be created or written.
+///
+pub fn write_mesh_to_patran(mesh: &Mesh, filename: &str) -> io::Result<()> {
+    if mesh.submesh_indices.len() != mesh.elements.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "The mesh has {} elements but {} submesh indices.",
+                mesh.elements.len(),
+                mesh.submesh_indices.len()
+            ),
+        ));
+    }
+
+    let mut writer = BufWriter::new(File::create(filename)?);
+
+    writeln!(writer, "{}", header_card(PACKET_TITLE, 0, 0, 1, [0; 5]))?;
+    writeln!(writer, "{}", mesh.label.trim_matches('"'))?;
+    writeln!(
+        writer,
+        "{}",
+        header_card(PACKET_SUMMARY, 0, 0, 1, [mesh.vertices.len(), mesh.elements.len(), 0, 0, 0])
+    )?;
+    // DATE (A12), TIME (A8), VERSION (A12).
+    writeln!(writer, "{:12}{:8}{:>12}", "", "", "3.0")?;
+
+    for (index, vertex) in mesh.vertices.iter().enumerate() {
+        writeln!(writer, "{}", header_card(PACKET_NODE, index + 1, 0, 2, [0; 5]))?;
+        writeln!(
+            writer,
+            "{}{}{}",
+            fortran_e16_9(vertex[0]),
+            fortran_e16_9(vertex[1]),
+            fortran_e16_9(vertex[2])
+        )?;
+        writeln!(writer, "1G       6       0       0  000000")?;
+    }
+
+    for (index, (element, submesh_index)) in mesh.elements.iter().zip(mesh.submesh_indices.iter()).enumerate() {
+        writeln!(writer, "{}", header_card(PACKET_ELEMENT, index + 1, PATRAN_TETRAHEDRON as usize, 2, [0; 5]))?;
+        writeln!(
+            writer,
+            "{:8}{:8}{:8}{:8}{}{}{}",
+            4,
+            0,
+            submesh_index,
+            0,
+            fortran_e16_9(0.0),
+            fortran_e16_9(0.0),
+            fortran_e16_9(0.0)
+        )?;
+        writeln!(
+            writer,
+            "{:8}{:8}{:8}{:8}",
+            element[0] + 1,
+            element[1] + 1,
+            element[2] + 1,
+            element[3] + 1
+        )?;
+    }
+
+    writeln!(writer, "{}", header_card(PACKET_END, 0, 0, 1, [0; 5]))?;
+    writer.flush()
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_fortran_e16_9() {
+        assert_eq!(fortran_e16_9(0.0), " 0.000000000E+00");
+        assert_eq!(fortran_e16_9(-0.1234567891), "-1.234567891E-01");
+        assert_eq!(fortran_e16_9(2.5e12), " 2.500000000E+12");
+    }
+
+    //..........................................................................................//
+    //. read_mesh_from_patran()                                                                .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_read_mesh_from_patran() {
+        let file_name = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join("patran_two_tets")
+            .join("two_tets.pat");
+        let mesh = read_mesh_from_patran(file_name.to_str().unwrap()).unwrap();
+
+        assert_eq!(mesh.label, "two tets");
+        assert_eq!(mesh.vertices.len(), 5);
+        // Touching E16.9 fields are split correctly.
+        assert_eq!(mesh.vertices[4], [1.0, -1.0, 1.0]);
+
+        // The bar element is skipped, and the node ids (10, 20, ...) are renumbered.
+        assert_eq!(mesh.elements, vec![[0, 1, 2, 3], [1, 2, 3, 4]]);
+
+        // Property 3 refers to material 7, property 4 has no property packet.
+        assert_eq!(mesh.submesh_indices, vec![7, 4]);
+    }
+
+    #[test]
+    fn test_read_mesh_from_patran_unknown_node() {
+        let file_name = env::temp_dir().join("tec2hdf5_test_patran_unknown_node.pat");
+        let contents = [
+            header_card(PACKET_NODE, 1, 0, 2, [0; 5]),
+            String::from(" 0.000000000E+00 0.000000000E+00 0.000000000E+00"),
+            String::from("1G       6       0       0  000000"),
+            header_card(PACKET_ELEMENT, 1, 5, 2, [0; 5]),
+            String::from("       4       0       1       0"),
+            String::from("       1       2       3       4"),
+            header_card(PACKET_END, 0, 0, 1, [0; 5]),
+        ]
+        .join("\n");
+        fs::write(&file_name, contents).unwrap();
+
+        match read_mesh_from_patran(file_name.to_str().unwrap()) {
+            Err(error) => {
+                assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+                assert!(error.to_string().contains("line 6"));
+            }
+            Ok(_) => panic!("an element with unknown nodes should be rejected"),
+        }
+    }
+
+    //..........................................................................................//
+    //. write_mesh_to_patran()                                                                 .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_write_mesh_to_patran_round_trip() {
+        let mesh = Mesh::new(
+            String::from("\"round trip\""),
+            vec![[0.0, 0.0, 0.0], [1.5, 0.0, 0.0], [0.0, -2.25, 0.0], [0.0, 0.0, 1e-3], [1.0, 1.0, 1.0]],
+            vec![[0, 1, 2, 3], [1, 2, 3, 4]],
+            vec![1, 2],
+            vec![],
+        );
+
+        let file_name = env::temp_dir().join("tec2hdf5_test_patran_round_trip.pat");
+        write_mesh_to_patran(&mesh, file_name.to_str().unwrap()).unwrap();
+
+        let contents = fs::read_to_string(&file_name).unwrap();
+        assert!(contents.starts_with("25       0       0       1       0       0       0       0       0\nround trip\n"));
+        assert!(contents.contains("\n 0.000000000E+00-2.250000000E+00 0.000000000E+00\n"));
+        assert!(contents.ends_with("99       0       0       1       0       0       0       0       0\n"));
+
+        let result = read_mesh_from_patran(file_name.to_str().unwrap()).unwrap();
+        assert_eq!(result.label, "round trip");
+        assert_eq!(result.vertices, mesh.vertices);
+        assert_eq!(result.elements, mesh.elements);
+        assert_eq!(result.submesh_indices, mesh.submesh_indices);
+    }
+
+    #[test]
+    fn test_write_mesh_to_patran_missing_submesh_indices() {
+        let mesh = Mesh::new(
+            String::from("missing indices"),
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            vec![[0, 1, 2, 3]],
+            vec![],
+            vec![],
+        );
+
+        let file_name = env::temp_dir().join("tec2hdf5_test_patran_missing_indices.pat");
+        let error = write_mesh_to_patran(&mesh, file_name.to_str().unwrap()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,352 @@
+//
+// file: regular_grid.rs
+// author: L. Nagy
+//
+
+use crate::linalg::{dot, sub};
+use crate::mesh::{Field, Mesh};
+
+///
+/// A regular (finite difference style) grid of rectangular cells covering an axis aligned box.
+///
+/// # Fields
+///
+/// * `min` - The lower corner of the grid.
+/// * `cell_size` - The size of a cell along x, y and z.
+/// * `dims` - The number of cells along x, y and z.
+///
+#[derive(Debug, Clone)]
+pub struct RegularGrid {
+    pub min: [f64; 3],
+    pub cell_size: [f64; 3],
+    pub dims: [usize; 3],
+}
+
+impl RegularGrid {
+    ///
+    /// Creates a grid with the given number of cells spanning the box from `min` to `max`.
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - The lower corner of the box.
+    /// * `max` - The upper corner of the box.
+    /// * `dims` - The number of cells along x, y and z, each must be at least one.
+    ///
+    /// # Returns
+    ///
+    /// A new `RegularGrid` instance.
+    ///
+    pub fn new(min: [f64; 3], max: [f64; 3], dims: [usize; 3]) -> RegularGrid {
+        let mut cell_size = [0.0; 3];
+        for i in 0..3 {
+            cell_size[i] = (max[i] - min[i]) / dims[i].max(1) as f64;
+        }
+        RegularGrid {
+            min,
+            cell_size,
+            dims: [dims[0].max(1), dims[1].max(1), dims[2].max(1)],
+        }
+    }
+
+    ///
+    /// Creates a grid of (approximately) cubic cells of the given size covering the box from
+    /// `min` to `max`, the box is enlarged symmetrically so that it is an integer number of cells
+    /// wide along each axis.
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - The lower corner of the box.
+    /// * `max` - The upper corner of the box.
+    /// * `cell_size` - The requested cell edge length.
+    ///
+    /// # Returns
+    ///
+    /// A new `RegularGrid` instance.
+    ///
+    pub fn with_cell_size(min: [f64; 3], max: [f64; 3], cell_size: f64) -> RegularGrid {
+        let mut grid_min = [0.0; 3];
+        let mut dims = [1usize; 3];
+        for i in 0..3 {
+            let extent = max[i] - min[i];
+            dims[i] = ((extent / cell_size).ceil() as usize).max(1);
+            let padding = 0.5 * (dims[i] as f64 * cell_size - extent);
+            grid_min[i] = min[i] - padding;
+        }
+        RegularGrid {
+            min: grid_min,
+            cell_size: [cell_size; 3],
+            dims,
+        }
+    }
+
+    ///
+    /// The upper corner of the grid.
+    ///
+    pub fn max(&self) -> [f64; 3] {
+        [0, 1, 2].map(|i| self.min[i] + self.dims[i] as f64 * self.cell_size[i])
+    }
+
+    ///
+    /// The total number of cells in the grid.
+    ///
+    pub fn no_of_cells(&self) -> usize {
+        self.dims[0] * self.dims[1] * self.dims[2]
+    }
+
+    ///
+    /// The linear index of the cell `(i, j, k)`, with x varying fastest, then y, then z (this is
+    /// the OVF ordering).
+    ///
+    pub fn cell_index(&self, i: usize, j: usize, k: usize) -> usize {
+        i + self.dims[0] * (j + self.dims[1] * k)
+    }
+
+    ///
+    /// The centre of the cell `(i, j, k)`.
+    ///
+    pub fn cell_centre(&self, i: usize, j: usize, k: usize) -> [f64; 3] {
+        let ijk = [i, j, k];
+        let mut centre = [0.0; 3];
+        for c in 0..3 {
+            centre[c] = self.min[c] + (ijk[c] as f64 + 0.5) * self.cell_size[c];
+        }
+        centre
+    }
+}
+
+///
+/// A vector field stored at the cell centres of a regular grid.
+///
+/// # Fields
+///
+/// * `label` - A string identifier for the field.
+/// * `grid` - The grid on which the field is defined.
+/// * `vectors` - One vector per cell, ordered as `RegularGrid::cell_index`.
+/// * `mask` - One flag per cell, `true` if the cell centre lies inside the material (masked
+///   cells hold zero vectors).
+///
+#[derive(Debug, Clone)]
+pub struct GridField {
+    pub label: String,
+    pub grid: RegularGrid,
+    pub vectors: Vec<[f64; 3]>,
+    pub mask: Vec<bool>,
+}
+
+impl GridField {
+    ///
+    /// Interpolates the field at an arbitrary point from the surrounding cell centres using
+    /// trilinear interpolation.
+    ///
+    /// Only cells inside the material (see `mask`) contribute; the weights of the remaining cells
+    /// are renormalised so that empty cells next to the material surface do not shrink the
+    /// interpolated vectors. Points outside the grid are clamped onto the outermost cell centres.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - The point at which to interpolate the field.
+    ///
+    /// # Returns
+    ///
+    /// The interpolated vector, or `None` if none of the surrounding cells are in the material.
+    ///
+    pub fn interpolate(&self, point: [f64; 3]) -> Option<[f64; 3]> {
+        let grid = &self.grid;
+
+        // Lower cell index and fractional offset along each axis (relative to cell centres).
+        let mut lower = [0usize; 3];
+        let mut t = [0.0; 3];
+        for i in 0..3 {
+            let u = (point[i] - grid.min[i]) / grid.cell_size[i] - 0.5;
+            let u = if u.is_finite() { u.clamp(0.0, (grid.dims[i] - 1) as f64) } else { 0.0 };
+            lower[i] = (u.floor() as usize).min(grid.dims[i].saturating_sub(2));
+            t[i] = u - lower[i] as f64;
+        }
+
+        let mut value = [0.0; 3];
+        let mut total_weight = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let mut ijk = [0usize; 3];
+            let mut weight = 1.0;
+            for i in 0..3 {
+                ijk[i] = (lower[i] + offset[i]).min(grid.dims[i] - 1);
+                weight *= if offset[i] == 1 { t[i] } else { 1.0 - t[i] };
+            }
+            let index = grid.cell_index(ijk[0], ijk[1], ijk[2]);
+            if weight > 0.0 && self.mask[index] {
+                for (v, component) in value.iter_mut().zip(self.vectors[index].iter()) {
+                    *v += weight * component;
+                }
+                total_weight += weight;
+            }
+        }
+
+        if total_weight > 0.0 {
+            Some([value[0] / total_weight, value[1] / total_weight, value[2] / total_weight])
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// Interpolates the field onto the vertices of a tetrahedral mesh, producing a new `Field`.
+    ///
+    /// Vertices whose surrounding cells all lie outside the material take the value of the
+    /// nearest cell that is inside the material.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh` - The mesh onto which the field is interpolated.
+    /// * `scale` - The factor converting mesh coordinates to grid coordinates (e.g. `1e-6` for a
+    ///   MERRILL mesh in microns and an OVF grid in metres).
+    ///
+    /// # Returns
+    ///
+    /// A `Field` with one vector per mesh vertex, labelled with the grid field's label.
+    ///
+    pub fn to_field(&self, mesh: &Mesh, scale: f64) -> Field {
+        let vectors = mesh
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let point = [scale * vertex[0], scale * vertex[1], scale * vertex[2]];
+                self.interpolate(point)
+                    .unwrap_or_else(|| self.nearest_material_value(point))
+            })
+            .collect();
+        Field {
+            label: self.label.clone(),
+            vectors,
+        }
+    }
+
+    ///
+    /// The value of the material cell whose centre is closest to a point (or a zero vector if
+    /// there is no material at all).
+    ///
+    fn nearest_material_value(&self, point: [f64; 3]) -> [f64; 3] {
+        let grid = &self.grid;
+        let mut best: Option<(f64, [f64; 3])> = None;
+        for k in 0..grid.dims[2] {
+            for j in 0..grid.dims[1] {
+                for i in 0..grid.dims[0] {
+                    let index = grid.cell_index(i, j, k);
+                    if !self.mask[index] {
+                        continue;
+                    }
+                    let centre = grid.cell_centre(i, j, k);
+                    let offset = sub(centre, point);
+                    let distance = dot(offset, offset);
+                    if best.is_none_or(|(d, _)| distance < d) {
+                        best = Some((distance, self.vectors[index]));
+                    }
+                }
+            }
+        }
+        best.map_or([0.0; 3], |(_, value)| value)
+    }
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regular_grid_with_cell_size() {
+        let grid = RegularGrid::with_cell_size([0.0, 0.0, 0.0], [1.0, 0.5, 0.25], 0.3);
+
+        assert_eq!(grid.dims, [4, 2, 1]);
+        assert_eq!(grid.no_of_cells(), 8);
+
+        // The box is padded symmetrically.
+        assert!((grid.min[0] - (-0.1)).abs() < 1e-12);
+        assert!((grid.max()[0] - 1.1).abs() < 1e-12);
+        assert!((grid.min[2] - (-0.025)).abs() < 1e-12);
+
+        let centre = grid.cell_centre(1, 1, 0);
+        assert!((centre[0] - 0.35).abs() < 1e-12);
+        assert!((centre[1] - 0.4).abs() < 1e-12);
+        assert_eq!(grid.cell_index(1, 1, 0), 5);
+    }
+
+    //..........................................................................................//
+    //. GridField::interpolate()                                                               .//
+    //..........................................................................................//
+
+    fn linear_grid_field() -> GridField {
+        // A field that is linear in space, so trilinear interpolation reproduces it exactly.
+        let grid = RegularGrid::new([0.0; 3], [4.0, 4.0, 4.0], [4, 4, 4]);
+        let mut vectors = vec![[0.0; 3]; grid.no_of_cells()];
+        for k in 0..4 {
+            for j in 0..4 {
+                for i in 0..4 {
+                    let c = grid.cell_centre(i, j, k);
+                    vectors[grid.cell_index(i, j, k)] = [c[0], 2.0 * c[1], c[0] - c[2]];
+                }
+            }
+        }
+        GridField {
+            label: String::from("linear"),
+            mask: vec![true; grid.no_of_cells()],
+            grid,
+            vectors,
+        }
+    }
+
+    #[test]
+    fn test_grid_field_interpolate() {
+        let grid_field = linear_grid_field();
+
+        let value = grid_field.interpolate([1.2, 2.3, 3.1]).unwrap();
+        assert!((value[0] - 1.2).abs() < 1e-12);
+        assert!((value[1] - 4.6).abs() < 1e-12);
+        assert!((value[2] - (1.2 - 3.1)).abs() < 1e-12);
+
+        // Points beyond the outermost cell centres are clamped.
+        let value = grid_field.interpolate([0.0, 0.1, 0.2]).unwrap();
+        assert!((value[0] - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_grid_field_interpolate_masked() {
+        let mut grid_field = linear_grid_field();
+        let grid = grid_field.grid.clone();
+
+        // Remove the material from all cells with i > 1, they must not contribute.
+        for k in 0..4 {
+            for j in 0..4 {
+                for i in 2..4 {
+                    let index = grid.cell_index(i, j, k);
+                    grid_field.mask[index] = false;
+                    grid_field.vectors[index] = [0.0; 3];
+                }
+            }
+        }
+
+        let value = grid_field.interpolate([1.8, 2.0, 2.0]).unwrap();
+        assert!((value[0] - 1.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_grid_field_to_field() {
+        let grid_field = linear_grid_field();
+        let mesh = Mesh::new(
+            String::from("tet"),
+            vec![[1.0, 1.0, 1.0], [3.0, 1.0, 1.0], [1.0, 3.0, 1.0], [1.0, 1.0, 3.0]],
+            vec![[0, 1, 2, 3]],
+            vec![1],
+            vec![],
+        );
+
+        // Mesh coordinates are doubled to obtain grid coordinates.
+        let field = grid_field.to_field(&mesh, 2.0);
+        assert_eq!(field.vectors.len(), 4);
+        assert!((field.vectors[0][0] - 2.0).abs() < 1e-12);
+        assert!((field.vectors[1][0] - 3.5).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,184 @@
+//
+// file: spatial_index.rs
+// author: L. Nagy
+//
+
+///
+/// A uniform grid over the bounding box of a tetrahedral mesh, each grid cell holds the indices of
+/// the elements whose bounding boxes overlap that cell. It is used to reduce point location
+/// queries to a handful of candidate elements.
+///
+/// # Fields
+///
+/// * `min` - The lower corner of the grid's bounding box.
+/// * `max` - The upper corner of the grid's bounding box.
+/// * `dims` - The number of grid cells along x, y and z.
+/// * `cell_size` - The size of a grid cell along x, y and z.
+/// * `cells` - The element indices held by each grid cell, with x varying fastest.
+///
+#[derive(Debug, Clone)]
+pub struct SpatialIndex {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+    pub dims: [usize; 3],
+    pub cell_size: [f64; 3],
+    pub cells: Vec<Vec<usize>>,
+}
+
+impl SpatialIndex {
+    ///
+    /// Builds a uniform grid over the given tetrahedra, the grid resolution is chosen so that
+    /// there are roughly as many grid cells as there are elements.
+    ///
+    /// # Arguments
+    ///
+    /// * `vertices` - The mesh vertices.
+    /// * `elements` - The mesh elements, each referencing four vertices.
+    ///
+    /// # Returns
+    ///
+    /// A new `SpatialIndex` instance.
+    ///
+    pub fn new(vertices: &[[f64; 3]], elements: &[[usize; 4]]) -> SpatialIndex {
+        let mut min = [f64::INFINITY; 3];
+        let mut max = [f64::NEG_INFINITY; 3];
+        for vertex in vertices {
+            for i in 0..3 {
+                min[i] = min[i].min(vertex[i]);
+                max[i] = max[i].max(vertex[i]);
+            }
+        }
+        if vertices.is_empty() {
+            min = [0.0; 3];
+            max = [0.0; 3];
+        }
+
+        // Pad the box slightly so that points on the boundary fall inside the grid.
+        let extent = [max[0] - min[0], max[1] - min[1], max[2] - min[2]];
+        let largest = extent[0].max(extent[1]).max(extent[2]).max(f64::MIN_POSITIVE);
+        for i in 0..3 {
+            min[i] -= 1e-9 * largest;
+            max[i] += 1e-9 * largest;
+        }
+        let extent = [max[0] - min[0], max[1] - min[1], max[2] - min[2]];
+
+        // Choose the cell size so that the grid has about one cell per element.
+        let volume = extent
+            .iter()
+            .map(|e| e.max(1e-6 * largest))
+            .product::<f64>();
+        let target_cells = elements.len().max(1) as f64;
+        let h = (volume / target_cells).cbrt();
+
+        let mut dims = [1usize; 3];
+        let mut cell_size = [0.0; 3];
+        for i in 0..3 {
+            dims[i] = ((extent[i] / h).ceil() as usize).clamp(1, 256);
+            cell_size[i] = extent[i] / dims[i] as f64;
+        }
+
+        let mut index = SpatialIndex {
+            min,
+            max,
+            dims,
+            cell_size,
+            cells: vec![Vec::new(); dims[0] * dims[1] * dims[2]],
+        };
+
+        for (element_index, element) in elements.iter().enumerate() {
+            let mut lo = [f64::INFINITY; 3];
+            let mut hi = [f64::NEG_INFINITY; 3];
+            for vertex_index in element {
+                let vertex = &vertices[*vertex_index];
+                for i in 0..3 {
+                    lo[i] = lo[i].min(vertex[i]);
+                    hi[i] = hi[i].max(vertex[i]);
+                }
+            }
+            let lo = index.cell_coordinates(lo);
+            let hi = index.cell_coordinates(hi);
+            for k in lo[2]..=hi[2] {
+                for j in lo[1]..=hi[1] {
+                    for i in lo[0]..=hi[0] {
+                        let cell = index.cell_index([i, j, k]);
+                        index.cells[cell].push(element_index);
+                    }
+                }
+            }
+        }
+
+        index
+    }
+
+    ///
+    /// Retrieves the elements that may contain the given point.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - The query point.
+    ///
+    /// # Returns
+    ///
+    /// The indices of the candidate elements, this is empty if the point lies outside the grid.
+    ///
+    pub fn candidates(&self, point: [f64; 3]) -> &[usize] {
+        let outside = (0..3).any(|i| point[i] < self.min[i] || point[i] > self.max[i]);
+        if outside {
+            return &[];
+        }
+        let cell = self.cell_index(self.cell_coordinates(point));
+        &self.cells[cell]
+    }
+
+    ///
+    /// The (clamped) integer coordinates of the grid cell containing a point.
+    ///
+    fn cell_coordinates(&self, point: [f64; 3]) -> [usize; 3] {
+        let mut ijk = [0usize; 3];
+        for i in 0..3 {
+            let t = ((point[i] - self.min[i]) / self.cell_size[i]).floor();
+            ijk[i] = if t.is_finite() && t > 0.0 {
+                (t as usize).min(self.dims[i] - 1)
+            } else {
+                0
+            };
+        }
+        ijk
+    }
+
+    ///
+    /// The linear index of a grid cell, with x varying fastest.
+    ///
+    fn cell_index(&self, ijk: [usize; 3]) -> usize {
+        ijk[0] + self.dims[0] * (ijk[1] + self.dims[1] * ijk[2])
+    }
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spatial_index_candidates() {
+        // Two tetrahedra sharing the face (1, 2, 3).
+        let vertices = vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [1.0, 1.0, 1.0],
+        ];
+        let elements = vec![[0, 1, 2, 3], [1, 2, 3, 4]];
+
+        let index = SpatialIndex::new(&vertices, &elements);
+
+        assert!(index.candidates([0.1, 0.1, 0.1]).contains(&0));
+        assert!(index.candidates([0.9, 0.9, 0.9]).contains(&1));
+        assert!(index.candidates([2.0, 0.0, 0.0]).is_empty());
+        assert!(index.candidates([-0.5, 0.5, 0.5]).is_empty());
+    }
+}
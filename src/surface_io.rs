@@ -0,0 +1,300 @@
+//
+// file: surface_io.rs
+// author: L. Nagy
+//
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use crate::geometry::triangle_normal;
+use crate::linalg::norm;
+use crate::mesh::{Field, Mesh};
+
+///
+/// The boundary surface of a mesh as a stand alone triangulation.
+///
+/// # Fields
+///
+/// * `vertices` - The mesh vertex index of each surface vertex.
+/// * `triangles` - The outward oriented triangles, indexing into `vertices`.
+///
+struct Surface {
+    vertices: Vec<usize>,
+    triangles: Vec<[usize; 3]>,
+}
+
+impl Surface {
+    fn from_mesh(mesh: &Mesh) -> Surface {
+        let mut surface_index = vec![usize::MAX; mesh.vertices.len()];
+        let mut vertices = vec![];
+        let mut triangles = vec![];
+        for face in mesh.boundary_faces() {
+            let mut triangle = [0usize; 3];
+            for (corner, vertex) in triangle.iter_mut().zip(face.vertices.iter()) {
+                if surface_index[*vertex] == usize::MAX {
+                    surface_index[*vertex] = vertices.len();
+                    vertices.push(*vertex);
+                }
+                *corner = surface_index[*vertex];
+            }
+            triangles.push(triangle);
+        }
+        Surface { vertices, triangles }
+    }
+}
+
+///
+/// Maps the direction of a vector onto an RGB colour, each component is `(m_i / |m| + 1) / 2`
+/// so that e.g. +x is (1, 0.5, 0.5) and -x is (0, 0.5, 0.5).
+///
+/// # Arguments
+///
+/// * `m` - The vector to colour.
+///
+/// # Returns
+///
+/// The red, green and blue components in the range [0, 1], or mid grey for a zero vector.
+///
+pub fn direction_colour(m: [f64; 3]) -> [f64; 3] {
+    let length = norm(m);
+    if length > 0.0 {
+        [0, 1, 2].map(|i| (0.5 * (m[i] / length + 1.0)).clamp(0.0, 1.0))
+    } else {
+        [0.5; 3]
+    }
+}
+
+///
+/// Writes the boundary surface of a mesh to an ASCII STL file.
+///
+/// STL holds geometry only, so no field data is written.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh whose boundary is written.
+/// * `filename` - The name of the STL file to create.
+///
+/// # Errors
+/// Returns an error if the file cannot be created or written.
+///
+pub fn write_surface_to_stl(mesh: &Mesh, filename: &str) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(filename)?);
+    let name = mesh.label.trim_matches('"').replace(' ', "_");
+
+    writeln!(writer, "solid {}", name)?;
+    for face in mesh.boundary_faces() {
+        let [v0, v1, v2] = face.vertices.map(|vertex| mesh.vertices[vertex]);
+        let n = triangle_normal(v0, v1, v2);
+        writeln!(writer, "  facet normal {:E} {:E} {:E}", n[0], n[1], n[2])?;
+        writeln!(writer, "    outer loop")?;
+        for v in [v0, v1, v2] {
+            writeln!(writer, "      vertex {:E} {:E} {:E}", v[0], v[1], v[2])?;
+        }
+        writeln!(writer, "    endloop")?;
+        writeln!(writer, "  endfacet")?;
+    }
+    writeln!(writer, "endsolid {}", name)?;
+
+    writer.flush()
+}
+
+///
+/// Writes the boundary surface of a mesh to a Wavefront OBJ file.
+///
+/// If a field is given its direction is written as a per-vertex colour (see
+/// `direction_colour`) using the widely supported `v x y z r g b` extension.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh whose boundary is written.
+/// * `field` - An optional field (e.g. the magnetization) used to colour the vertices.
+/// * `filename` - The name of the OBJ file to create.
+///
+/// # Errors
+/// Returns an error if the file cannot be created or written.
+///
+pub fn write_surface_to_obj(mesh: &Mesh, field: Option<&Field>, filename: &str) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(filename)?);
+    let surface = Surface::from_mesh(mesh);
+
+    writeln!(writer, "# {}", mesh.label.trim_matches('"'))?;
+    if let Some(field) = field {
+        writeln!(writer, "# vertex colours: {}", field.label.trim_matches('"'))?;
+    }
+    for vertex in &surface.vertices {
+        let v = mesh.vertices[*vertex];
+        match field {
+            Some(field) => {
+                let c = direction_colour(field.vectors[*vertex]);
+                writeln!(writer, "v {:E} {:E} {:E} {:.6} {:.6} {:.6}", v[0], v[1], v[2], c[0], c[1], c[2])?;
+            }
+            None => writeln!(writer, "v {:E} {:E} {:E}", v[0], v[1], v[2])?,
+        }
+    }
+    // OBJ indices start from one.
+    for triangle in &surface.triangles {
+        writeln!(writer, "f {} {} {}", triangle[0] + 1, triangle[1] + 1, triangle[2] + 1)?;
+    }
+
+    writer.flush()
+}
+
+///
+/// Writes the boundary surface of a mesh to an ASCII PLY file.
+///
+/// If a field is given its vectors are written as the vertex properties `mx`, `my` and `mz`,
+/// along with a per-vertex colour (`red`, `green`, `blue`) derived from their direction (see
+/// `direction_colour`).
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh whose boundary is written.
+/// * `field` - An optional field (e.g. the magnetization) written as vertex attributes.
+/// * `filename` - The name of the PLY file to create.
+///
+/// # Errors
+/// Returns an error if the file cannot be created or written.
+///
+pub fn write_surface_to_ply(mesh: &Mesh, field: Option<&Field>, filename: &str) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(filename)?);
+    let surface = Surface::from_mesh(mesh);
+
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format ascii 1.0")?;
+    writeln!(writer, "comment {}", mesh.label.trim_matches('"'))?;
+    if let Some(field) = field {
+        writeln!(writer, "comment field {}", field.label.trim_matches('"'))?;
+    }
+    writeln!(writer, "element vertex {}", surface.vertices.len())?;
+    for name in ["x", "y", "z"] {
+        writeln!(writer, "property double {}", name)?;
+    }
+    if field.is_some() {
+        for name in ["mx", "my", "mz"] {
+            writeln!(writer, "property double {}", name)?;
+        }
+        for name in ["red", "green", "blue"] {
+            writeln!(writer, "property uchar {}", name)?;
+        }
+    }
+    writeln!(writer, "element face {}", surface.triangles.len())?;
+    writeln!(writer, "property list uchar int vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    for vertex in &surface.vertices {
+        let v = mesh.vertices[*vertex];
+        write!(writer, "{:E} {:E} {:E}", v[0], v[1], v[2])?;
+        if let Some(field) = field {
+            let m = field.vectors[*vertex];
+            let c = direction_colour(m).map(|c| (255.0 * c).round() as u8);
+            write!(writer, " {:E} {:E} {:E} {} {} {}", m[0], m[1], m[2], c[0], c[1], c[2])?;
+        }
+        writeln!(writer)?;
+    }
+    for triangle in &surface.triangles {
+        writeln!(writer, "3 {} {} {}", triangle[0], triangle[1], triangle[2])?;
+    }
+
+    writer.flush()
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    fn single_tet_mesh() -> Mesh {
+        Mesh::new(
+            String::from("\"single tet\""),
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            vec![[0, 1, 2, 3]],
+            vec![1],
+            vec![Field {
+                label: String::from("\"m\""),
+                vectors: vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0], [0.0, 0.0, 0.0]],
+            }],
+        )
+    }
+
+    #[test]
+    fn test_direction_colour() {
+        assert_eq!(direction_colour([2.0, 0.0, 0.0]), [1.0, 0.5, 0.5]);
+        assert_eq!(direction_colour([0.0, 0.0, -3.0]), [0.5, 0.5, 0.0]);
+        assert_eq!(direction_colour([0.0; 3]), [0.5; 3]);
+    }
+
+    //..........................................................................................//
+    //. write_surface_to_stl()                                                                 .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_write_surface_to_stl() {
+        let mesh = single_tet_mesh();
+        let file_name = env::temp_dir().join("tec2hdf5_test_surface.stl");
+        write_surface_to_stl(&mesh, file_name.to_str().unwrap()).unwrap();
+
+        let contents = fs::read_to_string(&file_name).unwrap();
+        assert!(contents.starts_with("solid single_tet\n"));
+        assert!(contents.trim_end().ends_with("endsolid single_tet"));
+        assert_eq!(contents.matches("facet normal").count(), 4);
+        // The face opposite the origin points away from it.
+        let normal = contents
+            .lines()
+            .find(|line| line.trim_start().starts_with("facet normal"))
+            .unwrap();
+        let n: Vec<f64> = normal.split_whitespace().skip(2).map(|x| x.parse().unwrap()).collect();
+        assert!(n.iter().all(|component| (component - 1.0 / 3.0_f64.sqrt()).abs() < 1e-12));
+    }
+
+    //..........................................................................................//
+    //. write_surface_to_obj()                                                                 .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_write_surface_to_obj() {
+        let mesh = single_tet_mesh();
+        let file_name = env::temp_dir().join("tec2hdf5_test_surface.obj");
+        write_surface_to_obj(&mesh, Some(&mesh.fields[0]), file_name.to_str().unwrap()).unwrap();
+
+        let contents = fs::read_to_string(&file_name).unwrap();
+        let vertices: Vec<&str> = contents.lines().filter(|line| line.starts_with("v ")).collect();
+        let faces: Vec<&str> = contents.lines().filter(|line| line.starts_with("f ")).collect();
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(faces.len(), 4);
+        // The first boundary face is (1, 2, 3), so mesh vertex 1 (where m is +y) is written first.
+        assert_eq!(vertices[0], "v 1E0 0E0 0E0 0.500000 1.000000 0.500000");
+    }
+
+    //..........................................................................................//
+    //. write_surface_to_ply()                                                                 .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_write_surface_to_ply() {
+        let mesh = single_tet_mesh();
+        let file_name = env::temp_dir().join("tec2hdf5_test_surface.ply");
+        write_surface_to_ply(&mesh, Some(&mesh.fields[0]), file_name.to_str().unwrap()).unwrap();
+
+        let contents = fs::read_to_string(&file_name).unwrap();
+        let (header, body) = contents.split_once("end_header\n").unwrap();
+        assert!(header.contains("element vertex 4\n"));
+        assert!(header.contains("property double mx\n"));
+        assert!(header.contains("property uchar red\n"));
+        assert!(header.contains("element face 4\n"));
+
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "1E0 0E0 0E0 0E0 1E0 0E0 128 255 128");
+        assert!(lines[4..].iter().all(|line| line.starts_with("3 ")));
+
+        // Without a field only the geometry is written.
+        write_surface_to_ply(&mesh, None, file_name.to_str().unwrap()).unwrap();
+        let contents = fs::read_to_string(&file_name).unwrap();
+        assert!(!contents.contains("property double mx"));
+    }
+}
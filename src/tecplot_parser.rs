@@ -189,6 +189,10 @@ pub fn create_mesh_from_tecplot(file: &str) -> Mesh {
         fields: fields,
         volume: None,
         net_moments: None,
+        spatial_index: None,
+        surface_area: None,
+        charges: None,
+        helicities: None,
     }
 }
 
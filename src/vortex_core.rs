@@ -0,0 +1,165 @@
+//
+// file: vortex_core.rs
+// author: L. Nagy
+//
+
+use crate::domain_state::domain_state_metrics;
+use crate::geometry::tet_volume;
+use crate::linalg::{dot, norm, sub};
+use crate::mesh::{Field, Mesh};
+
+///
+/// A vortex core, traced as a polyline through the elements in which the magnetization is
+/// (almost) parallel to the core axis.
+///
+/// # Fields
+///
+/// * `points` - The points of the core, ordered along `axis`.
+/// * `axis` - The unit vector along the core axis (the mean curl of the magnetization).
+/// * `position` - The mean position of the core points.
+/// * `length` - The length of the core polyline.
+/// * `direction` - The unit vector from the first to the last point of the core, or `axis` if
+///   the core consists of a single point.
+/// * `tilt` - The angles, in degrees between 0 and 90, between `direction` and each of the
+///   particle's principal axes (in the order of `Mesh::principal_axes`).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct VortexCore {
+    pub points: Vec<[f64; 3]>,
+    pub axis: [f64; 3],
+    pub position: [f64; 3],
+    pub length: f64,
+    pub direction: [f64; 3],
+    pub tilt: [f64; 3],
+}
+
+///
+/// Extracts the vortex core of a field.
+///
+/// The core axis is the direction of the mean curl (see `domain_state_metrics`). The particle is
+/// cut into slabs perpendicular to the axis, roughly one element thick, and in each slab the
+/// element centroid with the smallest in-plane magnetization `|m_perp| / |m|` (using the element's
+/// mean magnetization) is taken as a point on the core; slabs where this exceeds `max_in_plane`
+/// are not part of the core.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh on which the field is defined.
+/// * `field` - The (magnetization) field.
+/// * `max_in_plane` - The largest in-plane magnetization, `|m_perp| / |m|`, of a core element.
+///
+/// # Returns
+///
+/// The `VortexCore`, or `None` if no element qualifies.
+///
+pub fn extract_vortex_core(mesh: &Mesh, field: &Field, max_in_plane: f64) -> Option<VortexCore> {
+    if mesh.elements.is_empty() {
+        return None;
+    }
+    let axis = domain_state_metrics(mesh, field).axis;
+
+    // The centroid, mean magnetization and volume of each element.
+    let mut centroids = Vec::with_capacity(mesh.elements.len());
+    let mut in_plane = Vec::with_capacity(mesh.elements.len());
+    let mut volume = 0.0;
+    for element in &mesh.elements {
+        let [v0, v1, v2, v3] = element.map(|vertex| mesh.vertices[vertex]);
+        let [f0, f1, f2, f3] = element.map(|vertex| field.vectors[vertex]);
+        volume += tet_volume(v0, v1, v2, v3).abs();
+        centroids.push([0, 1, 2].map(|i| 0.25 * (v0[i] + v1[i] + v2[i] + v3[i])));
+
+        let m = [0, 1, 2].map(|i| 0.25 * (f0[i] + f1[i] + f2[i] + f3[i]));
+        let m_norm = norm(m);
+        let parallel = dot(m, axis);
+        let perpendicular = [0, 1, 2].map(|i| m[i] - parallel * axis[i]);
+        in_plane.push(if m_norm > 0.0 { norm(perpendicular) / m_norm } else { f64::INFINITY });
+    }
+
+    // Slabs along the axis, about as thick as a (regular) element.
+    let heights: Vec<f64> = centroids.iter().map(|c| dot(*c, axis)).collect();
+    let lowest = heights.iter().copied().fold(f64::INFINITY, f64::min);
+    let highest = heights.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let thickness = (volume / mesh.elements.len() as f64).cbrt();
+    let n_slabs = if thickness > 0.0 { ((highest - lowest) / thickness).ceil().max(1.0) as usize } else { 1 };
+
+    let mut best: Vec<Option<usize>> = vec![None; n_slabs];
+    for (element, height) in heights.iter().enumerate() {
+        if in_plane[element] > max_in_plane {
+            continue;
+        }
+        let slab = (((height - lowest) / thickness) as usize).min(n_slabs - 1);
+        if best[slab].is_none_or(|current| in_plane[element] < in_plane[current]) {
+            best[slab] = Some(element);
+        }
+    }
+
+    let points: Vec<[f64; 3]> = best.iter().flatten().map(|element| centroids[*element]).collect();
+    if points.is_empty() {
+        return None;
+    }
+
+    let n_points = points.len() as f64;
+    let position = [0, 1, 2].map(|i| points.iter().map(|p| p[i]).sum::<f64>() / n_points);
+    let length = points
+        .windows(2)
+        .map(|pair| norm(sub(pair[1], pair[0])))
+        .sum();
+
+    let first = points[0];
+    let last = points[points.len() - 1];
+    let span = sub(last, first);
+    let direction = if norm(span) > 0.0 { span.map(|s| s / norm(span)) } else { axis };
+
+    let principal = mesh.principal_axes();
+    let tilt = principal.axes.map(|principal_axis| dot(direction, principal_axis).abs().min(1.0).acos().to_degrees());
+
+    Some(VortexCore { points, axis, position, length, direction, tilt })
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mesh::tests::{cube_mesh, unit_field};
+
+    fn elongated_mesh_with<F: Fn([f64; 3]) -> [f64; 3]>(m: F) -> (Mesh, Field) {
+        // Stretch the test cube along z so that z is its long principal axis.
+        let mut mesh = cube_mesh();
+        for vertex in mesh.vertices.iter_mut() {
+            vertex[2] *= 3.0;
+        }
+        let field = unit_field(&mesh, m);
+        (mesh, field)
+    }
+
+    //..........................................................................................//
+    //. extract_vortex_core()                                                                  .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_extract_vortex_core() {
+        let (mesh, field) = elongated_mesh_with(|v| [-v[1], v[0], 0.004]);
+        let core = extract_vortex_core(&mesh, &field, 0.5).unwrap();
+
+        assert!(core.axis[2] > 0.999);
+        assert!(core.points.len() > 2);
+        assert!((core.position[0].powi(2) + core.position[1].powi(2)).sqrt() < 0.005);
+        assert!(core.points.windows(2).all(|pair| pair[0][2] < pair[1][2]));
+        assert!(core.length > 0.03);
+        // The core runs along the long axis of the particle.
+        assert!(core.tilt[0] < 20.0);
+        assert!(core.tilt[1] > 70.0 && core.tilt[2] > 70.0);
+    }
+
+    #[test]
+    fn test_extract_vortex_core_two_domains() {
+        // The curl of a domain wall lies in the wall, perpendicular to the magnetization, so
+        // there is no core.
+        let (mesh, field) = elongated_mesh_with(|v| [0.0, 0.0, if v[0] > 0.0 { 1.0 } else { -1.0 }]);
+        assert_eq!(extract_vortex_core(&mesh, &field, 0.5), None);
+    }
+}
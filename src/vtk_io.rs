@@ -0,0 +1,522 @@
+//
+// file: vtk_io.rs
+// author: L. Nagy
+//
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::mesh::{Field, Mesh};
+
+///
+/// The VTK cell type identifier for a linear tetrahedron.
+///
+const VTK_TETRA: u8 = 10;
+
+///
+/// The data encodings supported for VTK XML output.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VtuFormat {
+    /// Whitespace separated text values.
+    Ascii,
+
+    /// Inline base64 encoded little-endian binary values.
+    Binary,
+
+    /// Inline base64 encoded, zlib compressed, little-endian binary values.
+    CompressedBinary,
+}
+
+///
+/// A data array that is to be written to a VTK XML file, holding the raw values in one of the
+/// types used by this module.
+///
+enum DataArrayValues<'a> {
+    Float64(Vec<f64>),
+    Int64(Vec<i64>),
+    UInt8(&'a [u8]),
+}
+
+impl DataArrayValues<'_> {
+    fn type_name(&self) -> &'static str {
+        match self {
+            DataArrayValues::Float64(_) => "Float64",
+            DataArrayValues::Int64(_) => "Int64",
+            DataArrayValues::UInt8(_) => "UInt8",
+        }
+    }
+
+    fn to_ascii(&self) -> String {
+        match self {
+            DataArrayValues::Float64(values) => join_values(values),
+            DataArrayValues::Int64(values) => join_values(values),
+            DataArrayValues::UInt8(values) => join_values(values),
+        }
+    }
+
+    fn to_le_bytes(&self) -> Vec<u8> {
+        match self {
+            DataArrayValues::Float64(values) => values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            DataArrayValues::Int64(values) => values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            DataArrayValues::UInt8(values) => values.to_vec(),
+        }
+    }
+}
+
+fn join_values<T: ToString>(values: &[T]) -> String {
+    values.iter().map(|v| v.to_string()).collect::<Vec<String>>().join(" ")
+}
+
+///
+/// Writes a single `<DataArray>` element, encoding its values in the requested format.
+///
+fn write_data_array<W: Write>(
+    writer: &mut W,
+    name: Option<&str>,
+    components: usize,
+    values: &DataArrayValues,
+    format: VtuFormat,
+) -> io::Result<()> {
+    let name_attribute = name.map_or(String::new(), |name| format!(" Name=\"{}\"", name));
+    let encoding = match format {
+        VtuFormat::Ascii => "ascii",
+        VtuFormat::Binary | VtuFormat::CompressedBinary => "binary",
+    };
+    writeln!(
+        writer,
+        "        <DataArray type=\"{}\"{} NumberOfComponents=\"{}\" format=\"{}\">",
+        values.type_name(), name_attribute, components, encoding
+    )?;
+
+    match format {
+        VtuFormat::Ascii => {
+            writeln!(writer, "          {}", values.to_ascii())?;
+        }
+        VtuFormat::Binary => {
+            // A single UInt64 header holding the number of bytes, encoded together with the data.
+            let raw = values.to_le_bytes();
+            let mut block = (raw.len() as u64).to_le_bytes().to_vec();
+            block.extend_from_slice(&raw);
+            writeln!(writer, "          {}", BASE64.encode(block))?;
+        }
+        VtuFormat::CompressedBinary => {
+            // The header [no. of blocks, block size, last block size, compressed sizes...] is
+            // encoded separately from the compressed data; we always use a single block.
+            let raw = values.to_le_bytes();
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&raw)?;
+            let compressed = encoder.finish()?;
+            let header: Vec<u8> = [1, raw.len() as u64, raw.len() as u64, compressed.len() as u64]
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect();
+            writeln!(writer, "          {}{}", BASE64.encode(header), BASE64.encode(compressed))?;
+        }
+    }
+
+    writeln!(writer, "        </DataArray>")
+}
+
+///
+/// The name under which a field is stored in a VTK file, the field label without the enclosing
+/// quotes used by tecplot (or `field<n>` if the label is empty), escaped for use in an XML
+/// attribute.
+///
+fn vtk_field_name(field: &Field, index: usize) -> String {
+    let name = field.label.trim_matches('"').trim();
+    if name.is_empty() {
+        format!("field{}", index + 1)
+    } else {
+        name.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+}
+
+///
+/// Writes the mesh geometry along with the given fields to a VTK XML unstructured grid file.
+///
+fn write_vtu(mesh: &Mesh, fields: &[(usize, &Field)], filename: &str, format: VtuFormat) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(filename)?);
+
+    let compressor = if format == VtuFormat::CompressedBinary {
+        " compressor=\"vtkZLibDataCompressor\""
+    } else {
+        ""
+    };
+
+    writeln!(writer, "<?xml version=\"1.0\"?>")?;
+    writeln!(
+        writer,
+        "<VTKFile type=\"UnstructuredGrid\" version=\"1.0\" byte_order=\"LittleEndian\" header_type=\"UInt64\"{}>",
+        compressor
+    )?;
+    writeln!(writer, "  <UnstructuredGrid>")?;
+    writeln!(
+        writer,
+        "    <Piece NumberOfPoints=\"{}\" NumberOfCells=\"{}\">",
+        mesh.vertices.len(), mesh.elements.len()
+    )?;
+
+    // Point data, one vector array per field.
+    let names: Vec<String> = fields.iter().map(|(index, field)| vtk_field_name(field, *index)).collect();
+    match names.first() {
+        Some(name) => writeln!(writer, "      <PointData Vectors=\"{}\">", name)?,
+        None => writeln!(writer, "      <PointData>")?,
+    }
+    for ((_, field), name) in fields.iter().zip(names.iter()) {
+        let values = DataArrayValues::Float64(field.vectors.iter().flatten().copied().collect());
+        write_data_array(&mut writer, Some(name), 3, &values, format)?;
+    }
+    writeln!(writer, "      </PointData>")?;
+
+    // Cell data, the subdomain (submesh) index of each element.
+    writeln!(writer, "      <CellData Scalars=\"subdomain\">")?;
+    let subdomains = DataArrayValues::Int64(mesh.submesh_indices.iter().map(|i| *i as i64).collect());
+    write_data_array(&mut writer, Some("subdomain"), 1, &subdomains, format)?;
+    writeln!(writer, "      </CellData>")?;
+
+    writeln!(writer, "      <Points>")?;
+    let points = DataArrayValues::Float64(mesh.vertices.iter().flatten().copied().collect());
+    write_data_array(&mut writer, None, 3, &points, format)?;
+    writeln!(writer, "      </Points>")?;
+
+    writeln!(writer, "      <Cells>")?;
+    let connectivity = DataArrayValues::Int64(mesh.elements.iter().flatten().map(|i| *i as i64).collect());
+    write_data_array(&mut writer, Some("connectivity"), 1, &connectivity, format)?;
+    let offsets = DataArrayValues::Int64((1..=mesh.elements.len()).map(|i| 4 * i as i64).collect());
+    write_data_array(&mut writer, Some("offsets"), 1, &offsets, format)?;
+    let cell_types = vec![VTK_TETRA; mesh.elements.len()];
+    write_data_array(&mut writer, Some("types"), 1, &DataArrayValues::UInt8(&cell_types), format)?;
+    writeln!(writer, "      </Cells>")?;
+
+    writeln!(writer, "    </Piece>")?;
+    writeln!(writer, "  </UnstructuredGrid>")?;
+    writeln!(writer, "</VTKFile>")?;
+
+    writer.flush()
+}
+
+///
+/// Writes a `Mesh` object, with all of its fields, to a VTK XML unstructured grid (`.vtu`) file.
+///
+/// The tetrahedra are written as VTK tetra cells, the submesh indices as the integer cell data
+/// array `subdomain` and each field as a three component point data array named after the
+/// field's label.
+///
+/// # Parameters
+/// - `mesh`: A reference to the `Mesh` object to be written to the file.
+/// - `filename`: The name of the `.vtu` file to create.
+/// - `format`: The encoding used for the data arrays.
+///
+/// # Returns
+/// - `io::Result<()>`: Returns `Ok(())` on success or an error if the file could not be written.
+///
+pub fn write_mesh_to_vtu(mesh: &Mesh, filename: &str, format: VtuFormat) -> io::Result<()> {
+    let fields: Vec<(usize, &Field)> = mesh.fields.iter().enumerate().collect();
+    write_vtu(mesh, &fields, filename, format)
+}
+
+///
+/// Writes a `Mesh` object as a ParaView collection, one `.vtu` file per field named
+/// `<basename>_<nnnn>.vtu` along with a `<basename>.pvd` file that lists them as timesteps.
+///
+/// The time value of each timestep is the first number found in the field's label (the zone
+/// title, e.g. `"400.0000 mT"`); if a label holds no number the field's (one based) index is used.
+///
+/// # Parameters
+/// - `mesh`: A reference to the `Mesh` object to be written.
+/// - `basename`: The base name of the output files.
+/// - `format`: The encoding used for the data arrays.
+///
+/// # Returns
+/// - `io::Result<()>`: Returns `Ok(())` on success or an error if a file could not be written.
+///
+pub fn write_mesh_to_pvd(mesh: &Mesh, basename: &str, format: VtuFormat) -> io::Result<()> {
+    let mut datasets: Vec<(f64, String)> = Vec::new();
+    for (index, field) in mesh.fields.iter().enumerate() {
+        let vtu_filename = format!("{}_{:04}.vtu", basename, index + 1);
+        write_vtu(mesh, &[(index, field)], &vtu_filename, format)?;
+
+        // The collection refers to the .vtu files relative to the .pvd file.
+        let relative = Path::new(&vtu_filename)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&vtu_filename)
+            .to_string();
+        let time = zone_time_value(&field.label).unwrap_or((index + 1) as f64);
+        datasets.push((time, relative));
+    }
+
+    let mut writer = BufWriter::new(File::create(format!("{}.pvd", basename))?);
+    writeln!(writer, "<?xml version=\"1.0\"?>")?;
+    writeln!(writer, "<VTKFile type=\"Collection\" version=\"1.0\" byte_order=\"LittleEndian\">")?;
+    writeln!(writer, "  <Collection>")?;
+    for (time, file) in &datasets {
+        writeln!(
+            writer,
+            "    <DataSet timestep=\"{}\" group=\"\" part=\"0\" file=\"{}\"/>",
+            time, file
+        )?;
+    }
+    writeln!(writer, "  </Collection>")?;
+    writeln!(writer, "</VTKFile>")?;
+
+    writer.flush()
+}
+
+///
+/// Writes a polyline (e.g. a vortex core) to a VTK XML polydata (`.vtp`) file as a single line
+/// cell through the points in order.
+///
+/// # Parameters
+/// - `points`: The points of the polyline.
+/// - `filename`: The name of the `.vtp` file to create.
+/// - `format`: The encoding used for the data arrays.
+///
+/// # Returns
+/// - `io::Result<()>`: Returns `Ok(())` on success or an error if the file could not be written.
+///
+pub fn write_polyline_to_vtp(points: &[[f64; 3]], filename: &str, format: VtuFormat) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(filename)?);
+
+    let compressor = if format == VtuFormat::CompressedBinary {
+        " compressor=\"vtkZLibDataCompressor\""
+    } else {
+        ""
+    };
+    let lines = usize::from(!points.is_empty());
+
+    writeln!(writer, "<?xml version=\"1.0\"?>")?;
+    writeln!(
+        writer,
+        "<VTKFile type=\"PolyData\" version=\"1.0\" byte_order=\"LittleEndian\" header_type=\"UInt64\"{}>",
+        compressor
+    )?;
+    writeln!(writer, "  <PolyData>")?;
+    writeln!(
+        writer,
+        "    <Piece NumberOfPoints=\"{}\" NumberOfVerts=\"0\" NumberOfLines=\"{}\" NumberOfStrips=\"0\" NumberOfPolys=\"0\">",
+        points.len(), lines
+    )?;
+
+    writeln!(writer, "      <Points>")?;
+    let coordinates = DataArrayValues::Float64(points.iter().flatten().copied().collect());
+    write_data_array(&mut writer, None, 3, &coordinates, format)?;
+    writeln!(writer, "      </Points>")?;
+
+    writeln!(writer, "      <Lines>")?;
+    let connectivity = DataArrayValues::Int64((0..points.len()).map(|i| i as i64).collect());
+    write_data_array(&mut writer, Some("connectivity"), 1, &connectivity, format)?;
+    let offsets = DataArrayValues::Int64(if lines > 0 { vec![points.len() as i64] } else { vec![] });
+    write_data_array(&mut writer, Some("offsets"), 1, &offsets, format)?;
+    writeln!(writer, "      </Lines>")?;
+
+    writeln!(writer, "    </Piece>")?;
+    writeln!(writer, "  </PolyData>")?;
+    writeln!(writer, "</VTKFile>")?;
+
+    writer.flush()
+}
+
+///
+/// Extracts the first number from a zone title, for example `"400.0000 mT"` gives `400.0`.
+///
+/// # Parameters
+/// - `title`: The zone title.
+///
+/// # Returns
+/// - `Option<f64>`: The first number in the title, or `None` if there is no number.
+///
+pub fn zone_time_value(title: &str) -> Option<f64> {
+    let chars: Vec<char> = title.chars().collect();
+    let mut start = 0;
+    while start < chars.len() {
+        let c = chars[start];
+        let starts_number = c.is_ascii_digit()
+            || ((c == '-' || c == '+' || c == '.')
+                && chars.get(start + 1).is_some_and(|n| n.is_ascii_digit() || *n == '.'));
+        if starts_number {
+            // Take the longest prefix from here that parses as a number.
+            for end in (start + 1..=chars.len()).rev() {
+                let candidate: String = chars[start..end].iter().collect();
+                if let Ok(value) = candidate.parse::<f64>() {
+                    return Some(value);
+                }
+            }
+        }
+        start += 1;
+    }
+    None
+}
+
+//----------------------------------------------------------------------------------------------//
+//- Tests                                                                                      -//
+//----------------------------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::Read;
+
+    use flate2::read::ZlibDecoder;
+
+    use super::*;
+
+    fn example_mesh() -> Mesh {
+        Mesh::new(
+            String::from("My mesh"),
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            vec![[0, 1, 2, 3]],
+            vec![2],
+            vec![
+                Field {
+                    label: String::from("\"400.0000 mT\""),
+                    vectors: vec![[1.0, 0.0, 0.0]; 4],
+                },
+                Field {
+                    label: String::from("\"-20.5 mT\""),
+                    vectors: vec![[0.0, 0.5, 0.0]; 4],
+                },
+            ],
+        )
+    }
+
+    /// Extracts the text content of the data array with the given name.
+    fn data_array_content<'a>(contents: &'a str, name: &str) -> &'a str {
+        let tag = format!("Name=\"{}\"", name);
+        let start = contents.find(&tag).unwrap();
+        let start = start + contents[start..].find('>').unwrap() + 1;
+        let end = start + contents[start..].find("</DataArray>").unwrap();
+        contents[start..end].trim()
+    }
+
+    //..........................................................................................//
+    //. write_mesh_to_vtu()                                                                    .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_write_mesh_to_vtu_ascii() {
+        let file_name = env::temp_dir().join("tec2hdf5_test_write_ascii.vtu");
+        let file_name = file_name.to_str().unwrap();
+
+        write_mesh_to_vtu(&example_mesh(), file_name, VtuFormat::Ascii).unwrap();
+
+        let contents = fs::read_to_string(file_name).unwrap();
+        assert!(contents.contains("<Piece NumberOfPoints=\"4\" NumberOfCells=\"1\">"));
+        assert_eq!(data_array_content(&contents, "400.0000 mT"), "1 0 0 1 0 0 1 0 0 1 0 0");
+        assert_eq!(data_array_content(&contents, "-20.5 mT"), "0 0.5 0 0 0.5 0 0 0.5 0 0 0.5 0");
+        assert_eq!(data_array_content(&contents, "subdomain"), "2");
+        assert_eq!(data_array_content(&contents, "connectivity"), "0 1 2 3");
+        assert_eq!(data_array_content(&contents, "offsets"), "4");
+        assert_eq!(data_array_content(&contents, "types"), "10");
+    }
+
+    #[test]
+    fn test_write_mesh_to_vtu_binary() {
+        let file_name = env::temp_dir().join("tec2hdf5_test_write_binary.vtu");
+        let file_name = file_name.to_str().unwrap();
+
+        write_mesh_to_vtu(&example_mesh(), file_name, VtuFormat::Binary).unwrap();
+
+        let contents = fs::read_to_string(file_name).unwrap();
+        let bytes = BASE64.decode(data_array_content(&contents, "connectivity")).unwrap();
+        assert_eq!(u64::from_le_bytes(bytes[0..8].try_into().unwrap()), 32);
+        assert_eq!(i64::from_le_bytes(bytes[32..40].try_into().unwrap()), 3);
+    }
+
+    #[test]
+    fn test_write_mesh_to_vtu_compressed_binary() {
+        let file_name = env::temp_dir().join("tec2hdf5_test_write_compressed.vtu");
+        let file_name = file_name.to_str().unwrap();
+
+        write_mesh_to_vtu(&example_mesh(), file_name, VtuFormat::CompressedBinary).unwrap();
+
+        let contents = fs::read_to_string(file_name).unwrap();
+        assert!(contents.contains("compressor=\"vtkZLibDataCompressor\""));
+
+        // The header is four UInt64 values, i.e. 32 bytes or 44 base64 characters.
+        let encoded = data_array_content(&contents, "connectivity");
+        let header = BASE64.decode(&encoded[..44]).unwrap();
+        assert_eq!(u64::from_le_bytes(header[0..8].try_into().unwrap()), 1);
+        assert_eq!(u64::from_le_bytes(header[8..16].try_into().unwrap()), 32);
+
+        let compressed = BASE64.decode(&encoded[44..]).unwrap();
+        let mut raw = Vec::new();
+        ZlibDecoder::new(&compressed[..]).read_to_end(&mut raw).unwrap();
+        assert_eq!(raw.len(), 32);
+        assert_eq!(i64::from_le_bytes(raw[8..16].try_into().unwrap()), 1);
+    }
+
+    //..........................................................................................//
+    //. write_mesh_to_pvd()                                                                    .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_write_mesh_to_pvd() {
+        let basename = env::temp_dir().join("tec2hdf5_test_write_collection");
+        let basename = basename.to_str().unwrap();
+
+        write_mesh_to_pvd(&example_mesh(), basename, VtuFormat::Ascii).unwrap();
+
+        let contents = fs::read_to_string(format!("{}.pvd", basename)).unwrap();
+        assert!(contents.contains("timestep=\"400\" group=\"\" part=\"0\" file=\"tec2hdf5_test_write_collection_0001.vtu\""));
+        assert!(contents.contains("timestep=\"-20.5\" group=\"\" part=\"0\" file=\"tec2hdf5_test_write_collection_0002.vtu\""));
+
+        let second = fs::read_to_string(format!("{}_0002.vtu", basename)).unwrap();
+        assert!(second.contains("Name=\"-20.5 mT\""));
+        assert!(!second.contains("Name=\"400.0000 mT\""));
+    }
+
+    //..........................................................................................//
+    //. write_polyline_to_vtp()                                                                .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_write_polyline_to_vtp() {
+        let file_name = env::temp_dir().join("tec2hdf5_test_write_polyline.vtp");
+        let file_name = file_name.to_str().unwrap();
+
+        let points = [[0.0, 0.0, -1.0], [0.0, 0.5, 0.0], [0.0, 0.0, 1.0]];
+        write_polyline_to_vtp(&points, file_name, VtuFormat::Ascii).unwrap();
+
+        let contents = fs::read_to_string(file_name).unwrap();
+        assert!(contents.contains("<VTKFile type=\"PolyData\""));
+        assert!(contents.contains("NumberOfPoints=\"3\" NumberOfVerts=\"0\" NumberOfLines=\"1\""));
+        assert_eq!(data_array_content(&contents, "connectivity"), "0 1 2");
+        assert_eq!(data_array_content(&contents, "offsets"), "3");
+    }
+
+    //..........................................................................................//
+    //. vtk_field_name()                                                                       .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_vtk_field_name() {
+        let field = |label: &str| Field { label: String::from(label), vectors: vec![] };
+        assert_eq!(vtk_field_name(&field("\"400.0000 mT\""), 0), "400.0000 mT");
+        assert_eq!(vtk_field_name(&field("\"<m> & \"h\" mT\""), 0), "&lt;m&gt; &amp; &quot;h&quot; mT");
+        assert_eq!(vtk_field_name(&field("\"\""), 2), "field3");
+    }
+
+    //..........................................................................................//
+    //. zone_time_value()                                                                      .//
+    //..........................................................................................//
+
+    #[test]
+    fn test_zone_time_value() {
+        assert_eq!(zone_time_value("\"400.0000 mT\""), Some(400.0));
+        assert_eq!(zone_time_value("\"-1.5e-3 T\""), Some(-1.5e-3));
+        assert_eq!(zone_time_value("\"step 12\""), Some(12.0));
+        assert_eq!(zone_time_value("\"\""), None);
+    }
+}